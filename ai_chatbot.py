@@ -2,7 +2,6 @@ from flask import Flask, request, Response
 from llama_cpp import Llama
 import json
 import random
-import re
 
 # ------------------------------
 # Model setup
@@ -23,35 +22,37 @@ except Exception as e:
 
 app = Flask(__name__)
 @app.route("/healthcheck", methods=["GET"])
+@app.route("/health", methods=["GET"])
 def healthcheck():
     return "OK", 200
 
-SYSTEM_PREFIX = "[INST] <<SYS>> You are a helpful, friendly assistant. <</SYS>> "
+DEFAULT_SYSTEM_PROMPT = "You are a helpful, friendly assistant."
 SYSTEM_SUFFIX = " [/INST]"
 
 # ------------------------------
 # Helpers
 # ------------------------------
-def clean_response(response: str) -> str:
-    """Minimal cleanup: remove system tokens and fix known actions."""
-    # Remove system tokens
-    for token in ["[INST]", "[/INST]", "<<SYS>>", "<</SYS>>", "User:", "user:", "Bot:", "bot:"]:
-        response = response.replace(token, "")
-
-    # Replace known LLaMA action tokens with proper formatting
-    actions = {
-        "thinking": "*thinking*",
-        # add more as needed
-    }
-    for key, val in actions.items():
-        response = response.replace(key, val)
-
-    # Collapse multiple consecutive newlines or trailing spaces
-    response = re.sub(r'\s+\n', '\n', response)
-    response = re.sub(r'\n\s+', '\n', response)
-    response = response.strip()
-
-    return response
+def build_prompt(system: str, history: list, prompt: str) -> str:
+    """Llama-2 multi-turn prompt: history is [{"role": "user"|"assistant", "content": ...}]."""
+    turns = []
+    pending_user = None
+    for entry in history:
+        role = entry.get("role")
+        content = entry.get("content", "")
+        if role == "user":
+            pending_user = content
+        elif role == "assistant" and pending_user is not None:
+            turns.append((pending_user, content))
+            pending_user = None
+
+    text = ""
+    sys_block = f"<<SYS>> {system} <</SYS>> "
+    for i, (user_msg, bot_msg) in enumerate(turns):
+        prefix = sys_block if i == 0 else ""
+        text += f"[INST] {prefix}{user_msg}{SYSTEM_SUFFIX} {bot_msg} "
+    prefix = sys_block if not turns else ""
+    text += f"[INST] {prefix}{prompt}{SYSTEM_SUFFIX}"
+    return text
 
 # ------------------------------
 # Flask route
@@ -61,38 +62,71 @@ def chat():
     data = request.json or {}
     message = data.get("message", "")
     nickname = data.get("nickname", "")
+    persona = (data.get("persona") or "").strip()
+    system = data.get("system_prompt")
+    # A persona replaces the default prompt but follows a server's own system prompt
+    if persona:
+        system = f"{system}\n\n{persona}" if system else persona
+    system = system or DEFAULT_SYSTEM_PROMPT
+    # 0.0 is a valid temperature, so only fall back when none was sent
+    temperature = data.get("temperature")
+    temperature = 0.7 if temperature is None else float(temperature)
 
     # Randomly prepend nickname
     use_nickname = random.choice([True, False, False])
     prompt = f"{nickname}, {message}" if use_nickname and nickname else message
-    system_prompt = f"{SYSTEM_PREFIX}{prompt}{SYSTEM_SUFFIX}"
+    system_prompt = build_prompt(system, data.get("history") or [], prompt)
 
     def generate():
         try:
-            # Generate full response (no streaming)
-            resp = llm(
+            # Stream tokens as they are produced; the bot cleans the final text
+            for chunk in llm(
                 system_prompt,
                 max_tokens=128,
-                temperature=0.7,
-                stream=False
-            )
-
-            # Handle different possible return types
-            if isinstance(resp, dict) and "choices" in resp:
-                text = resp["choices"][0]["text"]
-            elif isinstance(resp, dict) and "text" in resp:
-                text = resp["text"]
-            else:
-                text = str(resp)  # fallback
-
-            text = clean_response(text)
-            yield text
+                temperature=temperature,
+                stream=True
+            ):
+                if isinstance(chunk, dict) and "choices" in chunk:
+                    yield chunk["choices"][0].get("text", "")
+                elif isinstance(chunk, dict) and "text" in chunk:
+                    yield chunk["text"]
+                else:
+                    yield str(chunk)  # fallback
 
         except Exception as e:
             yield f"Error generating response: {e}"
 
     return Response(generate(), mimetype="text/plain")
 
+# ------------------------------
+# Raw completion (same shape as llama.cpp server's /completion); the bot renders the
+# prompt template itself, so any model family works without editing this file
+# ------------------------------
+@app.route("/completion", methods=["POST"])
+def completion():
+    data = request.json or {}
+    prompt = data.get("prompt", "")
+    options = dict(
+        max_tokens=int(data.get("n_predict", 128)),
+        temperature=0.7 if data.get("temperature") is None else float(data["temperature"]),
+        stop=data.get("stop") or None,
+    )
+
+    if not data.get("stream"):
+        result = llm(prompt, stream=False, **options)
+        return {"content": result["choices"][0]["text"]}
+
+    def generate():
+        try:
+            for chunk in llm(prompt, stream=True, **options):
+                text = chunk["choices"][0].get("text", "")
+                yield f"data: {json.dumps({'content': text, 'stop': False})}\n\n"
+        except Exception as e:
+            yield f"data: {json.dumps({'content': f'Error generating response: {e}', 'stop': False})}\n\n"
+        yield f"data: {json.dumps({'content': '', 'stop': True})}\n\n"
+
+    return Response(generate(), mimetype="text/event-stream")
+
 # ------------------------------
 # Run Flask
 # ------------------------------
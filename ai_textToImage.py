@@ -0,0 +1,53 @@
+from flask import Flask, request, Response, jsonify
+from diffusers import StableDiffusionPipeline
+import io
+import torch
+
+# ------------------------------
+# Model setup
+# ------------------------------
+MODEL_ID = "runwayml/stable-diffusion-v1-5"
+DEVICE = "cuda" if torch.cuda.is_available() else "cpu"
+DTYPE = torch.float16 if DEVICE == "cuda" else torch.float32
+
+pipe = StableDiffusionPipeline.from_pretrained(MODEL_ID, torch_dtype=DTYPE)
+pipe = pipe.to(DEVICE)
+
+app = Flask(__name__)
+
+@app.route("/healthcheck", methods=["GET"])
+def healthcheck():
+    return "OK", 200
+
+# ------------------------------
+# Flask route
+# ------------------------------
+# Request:  {"prompt": str, "negative_prompt": str?, "steps": int, "width": int, "height": int}
+# Response: 200 image/png, or non-2xx {"error": str}
+@app.route("/generate", methods=["POST"])
+def generate():
+    data = request.json or {}
+    prompt = data.get("prompt", "").strip()
+    if not prompt:
+        return jsonify({"error": "prompt is required"}), 400
+
+    try:
+        image = pipe(
+            prompt,
+            negative_prompt=data.get("negative_prompt"),
+            num_inference_steps=int(data.get("steps", 25)),
+            width=int(data.get("width", 512)),
+            height=int(data.get("height", 512)),
+        ).images[0]
+    except Exception as e:
+        return jsonify({"error": str(e)}), 500
+
+    buffer = io.BytesIO()
+    image.save(buffer, format="PNG")
+    return Response(buffer.getvalue(), mimetype="image/png")
+
+# ------------------------------
+# Run Flask
+# ------------------------------
+if __name__ == "__main__":
+    app.run(host="127.0.0.1", port=5006)
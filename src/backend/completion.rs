@@ -0,0 +1,158 @@
+use futures_util::StreamExt;
+use serenity::async_trait;
+use crate::backend::template::PromptFormat;
+use crate::backend::{check_status, line_stream, ChatBackend, ChatRequest, TextStream};
+use crate::config::BackendConfig;
+
+/// Raw text completion (llama.cpp server, or ai_chatbot.py's /completion): the bot
+/// renders the prompt itself with the model's template and POSTs it to /completion
+pub struct CompletionBackend {
+    client: reqwest::Client,
+    base_url: String,
+    format: PromptFormat,
+    temperature: f32,
+    max_tokens: u32,
+}
+
+impl CompletionBackend {
+    pub fn new(client: reqwest::Client, config: &BackendConfig) -> Self {
+        let format = config.resolved_prompt_format();
+        println!("[LOG] Using {:?} prompt format for completion backend", format);
+        Self {
+            client,
+            base_url: config.base_url.clone(),
+            format,
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+        }
+    }
+
+    async fn send(&self, request: &ChatRequest, stream: bool) -> Result<reqwest::Response, String> {
+        let body = serde_json::json!({
+            "prompt": self.format.render(request),
+            "n_predict": self.max_tokens,
+            "temperature": request.temperature.unwrap_or(self.temperature),
+            "stop": self.format.stop_sequences(),
+            "stream": stream,
+        });
+        let resp = self
+            .client
+            .post(format!("{}/completion", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach chatbot server: {}", e))?;
+        check_status(resp).await
+    }
+}
+
+#[async_trait]
+impl ChatBackend for CompletionBackend {
+    fn name(&self) -> &'static str {
+        "completion"
+    }
+
+    async fn health(&self) -> bool {
+        match self.client.get(format!("{}/health", self.base_url)).send().await {
+            Ok(resp) => resp.status().is_success(),
+            Err(_) => false,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<String, String> {
+        let json: serde_json::Value = self
+            .send(request, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid completion response: {}", e))?;
+        json["content"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| "Completion response has no content".to_string())
+    }
+
+    async fn stream(&self, request: &ChatRequest) -> Result<TextStream, String> {
+        let resp = self.send(request, true).await?;
+        // Server-sent events: `data: {"content": "...", "stop": false}` until stop is true
+        let stream = line_stream(resp).filter_map(|line| async move {
+            match line {
+                Ok(line) => {
+                    let data = line.strip_prefix("data:")?.trim();
+                    let json: serde_json::Value = serde_json::from_str(data).ok()?;
+                    json["content"]
+                        .as_str()
+                        .filter(|s| !s.is_empty())
+                        .map(|s| Ok(s.to_string()))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_support::{backend_for, request};
+    use crate::config::BackendKind;
+    use crate::backend::template::PromptFormat;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config() -> BackendConfig {
+        BackendConfig {
+            kind: BackendKind::Completion,
+            prompt_format: Some(PromptFormat::ChatMl),
+            ..BackendConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_reads_content() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/completion"))
+            .and(body_partial_json(serde_json::json!({"n_predict": 128, "stream": false})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"content": "Hi Ada"})))
+            .mount(&server)
+            .await;
+
+        assert_eq!(backend_for(&server, config()).chat(&request()).await.unwrap(), "Hi Ada");
+    }
+
+    #[tokio::test]
+    async fn streams_event_content() {
+        let server = MockServer::start().await;
+        let body = concat!(
+            "data: {\"content\":\"Hi \",\"stop\":false}\n\n",
+            "data: {\"content\":\"Ada\",\"stop\":false}\n\n",
+            "data: {\"content\":\"\",\"stop\":true}\n\n",
+        );
+        Mock::given(method("POST"))
+            .and(path("/completion"))
+            .and(body_partial_json(serde_json::json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let stream = backend_for(&server, config()).stream(&request()).await.unwrap();
+        let parts: Vec<String> = stream.map(|part| part.unwrap()).collect().await;
+        assert_eq!(parts, vec!["Hi ", "Ada"]);
+    }
+
+    #[tokio::test]
+    async fn error_status_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("loading model"))
+            .mount(&server)
+            .await;
+
+        let backend = backend_for(&server, config());
+        let err = backend.chat(&request()).await.unwrap_err();
+        assert!(err.contains("503") && err.contains("loading model"), "{}", err);
+        assert!(backend.stream(&request()).await.is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use serenity::async_trait;
+use crate::backend::{check_status, text_stream, ChatBackend, ChatRequest, TextStream};
+use crate::config::BackendConfig;
+
+/// The bundled `ai_chatbot.py` server: POST /chat returns plain (streamed) text
+pub struct FlaskBackend {
+    client: reqwest::Client,
+    base_url: String,
+    temperature: f32,
+}
+
+impl FlaskBackend {
+    pub fn new(client: reqwest::Client, config: &BackendConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.clone(),
+            temperature: config.temperature,
+        }
+    }
+
+    async fn send(&self, request: &ChatRequest) -> Result<reqwest::Response, String> {
+        // Always send a temperature so the configured one applies like it does for other backends
+        let request = ChatRequest {
+            temperature: Some(request.temperature.unwrap_or(self.temperature)),
+            ..request.clone()
+        };
+        let resp = self
+            .client
+            .post(format!("{}/chat", self.base_url))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach chatbot server: {}", e))?;
+        check_status(resp).await
+    }
+}
+
+#[async_trait]
+impl ChatBackend for FlaskBackend {
+    fn name(&self) -> &'static str {
+        "flask"
+    }
+
+    async fn health(&self) -> bool {
+        match self.client.get(format!("{}/healthcheck", self.base_url)).send().await {
+            Ok(resp) => resp.status().is_success(),
+            Err(_) => false,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<String, String> {
+        self.send(request)
+            .await?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read chatbot response: {}", e))
+    }
+
+    async fn stream(&self, request: &ChatRequest) -> Result<TextStream, String> {
+        Ok(text_stream(self.send(request).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_support::{backend_for, request};
+    use crate::config::BackendKind;
+    use futures_util::StreamExt;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config() -> BackendConfig {
+        BackendConfig {
+            kind: BackendKind::Flask,
+            temperature: 0.3,
+            ..BackendConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_sends_configured_temperature() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .and(body_partial_json(serde_json::json!({"message": "Hello", "nickname": "Ada", "temperature": 0.3})))
+            .respond_with(ResponseTemplate::new(200).set_body_string("Hi Ada"))
+            .mount(&server)
+            .await;
+
+        assert_eq!(backend_for(&server, config()).chat(&request()).await.unwrap(), "Hi Ada");
+    }
+
+    #[tokio::test]
+    async fn request_temperature_wins() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .and(body_partial_json(serde_json::json!({"temperature": 0.0})))
+            .respond_with(ResponseTemplate::new(200).set_body_string("Hi"))
+            .mount(&server)
+            .await;
+
+        let request = ChatRequest {
+            temperature: Some(0.0),
+            ..request()
+        };
+        assert_eq!(backend_for(&server, config()).chat(&request).await.unwrap(), "Hi");
+    }
+
+    #[tokio::test]
+    async fn streams_plain_text() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("Hello there, Ada ☕"))
+            .mount(&server)
+            .await;
+
+        let stream = backend_for(&server, config()).stream(&request()).await.unwrap();
+        let parts: Vec<String> = stream.map(|part| part.unwrap()).collect().await;
+        assert_eq!(parts.concat(), "Hello there, Ada ☕");
+    }
+
+    #[tokio::test]
+    async fn error_status_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&server)
+            .await;
+
+        let backend = backend_for(&server, config());
+        let err = backend.chat(&request()).await.unwrap_err();
+        assert!(err.contains("500") && err.contains("boom"), "{}", err);
+        assert!(backend.stream(&request()).await.is_err());
+    }
+}
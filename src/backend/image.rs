@@ -0,0 +1,161 @@
+use serde::Serialize;
+use std::time::Duration;
+use crate::config::ImageConfig;
+
+/// First bytes of every PNG file
+const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Body of POST /generate on the text-to-image service.
+///
+/// The service answers 200 with the raw PNG (`Content-Type: image/png`),
+/// or a non-2xx status with `{"error": "..."}`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ImageRequest {
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub negative_prompt: Option<String>,
+    pub steps: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Client for the local text-to-image service (`ai_textToImage.py`)
+pub struct ImageClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl ImageClient {
+    pub fn new(config: &ImageConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub async fn health(&self) -> bool {
+        match self.client.get(format!("{}/healthcheck", self.base_url)).send().await {
+            Ok(resp) => resp.status().is_success(),
+            Err(_) => false,
+        }
+    }
+
+    /// Generate an image, returning the PNG bytes
+    pub async fn generate(&self, request: &ImageRequest) -> Result<Vec<u8>, String> {
+        let resp = self
+            .client
+            .post(format!("{}/generate", self.base_url))
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach image server: {}", e))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|json| json["error"].as_str().map(|s| s.to_string()))
+                .unwrap_or(body);
+            return Err(format!("Image server returned {}: {}", status, message.trim()));
+        }
+
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read image: {}", e))?;
+        if !bytes.starts_with(PNG_SIGNATURE) {
+            return Err("Image server did not return a PNG".to_string());
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client_for(server: &MockServer) -> ImageClient {
+        ImageClient::new(&ImageConfig {
+            base_url: server.uri(),
+            ..ImageConfig::default()
+        })
+    }
+
+    fn request() -> ImageRequest {
+        ImageRequest {
+            prompt: "a potato in space".to_string(),
+            negative_prompt: None,
+            steps: 20,
+            width: 512,
+            height: 512,
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_png_bytes() {
+        let server = MockServer::start().await;
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(b"rest-of-image");
+        Mock::given(method("POST"))
+            .and(path("/generate"))
+            .and(body_json(serde_json::json!({
+                "prompt": "a potato in space",
+                "steps": 20,
+                "width": 512,
+                "height": 512
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(png.clone(), "image/png"))
+            .mount(&server)
+            .await;
+
+        let bytes = client_for(&server).generate(&request()).await.unwrap();
+        assert_eq!(bytes, png);
+    }
+
+    #[tokio::test]
+    async fn surfaces_server_error_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/generate"))
+            .respond_with(
+                ResponseTemplate::new(500).set_body_json(serde_json::json!({"error": "CUDA out of memory"})),
+            )
+            .mount(&server)
+            .await;
+
+        let err = client_for(&server).generate(&request()).await.unwrap_err();
+        assert!(err.contains("CUDA out of memory"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn rejects_non_png_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/generate"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("not an image"))
+            .mount(&server)
+            .await;
+
+        assert!(client_for(&server).generate(&request()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn health_reflects_status() {
+        let server = MockServer::start().await;
+        assert!(!client_for(&server).health().await);
+
+        Mock::given(method("GET"))
+            .and(path("/healthcheck"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("OK"))
+            .mount(&server)
+            .await;
+        assert!(client_for(&server).health().await);
+    }
+}
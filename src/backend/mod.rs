@@ -0,0 +1,288 @@
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use serenity::async_trait;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::config::{BackendConfig, BackendKind};
+use crate::history::HistoryMessage;
+
+pub mod flask;
+pub mod completion;
+pub mod image;
+pub mod ollama;
+pub mod openai;
+pub mod template;
+
+/// Used when neither the guild nor the request supplies a system prompt
+pub const DEFAULT_SYSTEM_PROMPT: &str = "You are a helpful, friendly assistant.";
+
+/// Incremental text produced by a streaming backend
+pub type TextStream = Pin<Box<dyn Stream<Item = Result<String, String>> + Send>>;
+
+/// Everything a backend needs to answer one user message
+#[derive(Debug, Serialize, Clone)]
+pub struct ChatRequest {
+    pub message: String,
+    pub nickname: String,
+    pub system_prompt: Option<String>,
+    /// The active persona's prompt; replaces the default system prompt, or follows a
+    /// guild's own one
+    pub persona: Option<String>,
+    /// Overrides the configured temperature, e.g. for a persona
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    pub history: Vec<HistoryMessage>,
+}
+
+impl ChatRequest {
+    /// System prompt for backends that take chat messages directly
+    pub fn system_message(&self) -> String {
+        let persona = self.persona.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        let mut system = match (self.system_prompt.as_deref(), persona) {
+            (Some(guild), Some(persona)) => format!("{}\n\n{}", guild, persona),
+            (None, Some(persona)) => persona.to_string(),
+            (Some(guild), None) => guild.to_string(),
+            (None, None) => DEFAULT_SYSTEM_PROMPT.to_string(),
+        };
+        if !self.nickname.is_empty() {
+            system = format!("{} The user's name is {}.", system, self.nickname);
+        }
+        system
+    }
+
+    /// System prompt, history and the new message as role/content pairs
+    pub fn messages(&self) -> Vec<serde_json::Value> {
+        let mut messages = vec![serde_json::json!({"role": "system", "content": self.system_message()})];
+        messages.extend(
+            self.history
+                .iter()
+                .map(|m| serde_json::json!({"role": m.role, "content": m.content})),
+        );
+        messages.push(serde_json::json!({"role": "user", "content": self.message}));
+        messages
+    }
+}
+
+/// A local (or remote) model server the bot can talk to
+#[async_trait]
+pub trait ChatBackend: Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &'static str;
+
+    /// Whether the server is up and able to take requests
+    async fn health(&self) -> bool;
+
+    /// Generate a full response in one go
+    async fn chat(&self, request: &ChatRequest) -> Result<String, String>;
+
+    /// Generate a response as a stream of text fragments
+    async fn stream(&self, request: &ChatRequest) -> Result<TextStream, String>;
+}
+
+/// Build the backend selected in the config
+pub fn from_config(config: &BackendConfig) -> Arc<dyn ChatBackend> {
+    let client = client(config);
+    match config.kind {
+        BackendKind::Flask => Arc::new(flask::FlaskBackend::new(client, config)),
+        BackendKind::OpenAi => Arc::new(openai::OpenAiBackend::new(client, config)),
+        BackendKind::Ollama => Arc::new(ollama::OllamaBackend::new(client, config)),
+        BackendKind::Completion => Arc::new(completion::CompletionBackend::new(client, config)),
+    }
+}
+
+/// HTTP client with the configured timeouts. A read timeout rather than a total one, so a
+/// long reply that keeps streaming is not cut off while a silent server still fails.
+fn client(config: &BackendConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.read_timeout_secs))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Turn an HTTP response into an error string unless it is a 2xx
+pub(crate) async fn check_status(resp: reqwest::Response) -> Result<reqwest::Response, String> {
+    let status = resp.status();
+    if status.is_success() {
+        Ok(resp)
+    } else {
+        let body = resp.text().await.unwrap_or_default();
+        Err(format!("Backend returned {}: {}", status, body.trim()))
+    }
+}
+
+/// Decode a response body as UTF-8 text, holding back characters split across chunks
+pub(crate) fn text_stream(resp: reqwest::Response) -> TextStream {
+    decode_utf8(resp.bytes_stream())
+}
+
+/// Split a streamed body into complete, non-empty lines (for SSE and NDJSON)
+pub(crate) fn line_stream(resp: reqwest::Response) -> TextStream {
+    split_lines(text_stream(resp))
+}
+
+fn decode_utf8<S, B, E>(bytes: S) -> TextStream
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    let state = (bytes.boxed(), Vec::<u8>::new());
+    let stream = stream::unfold(state, |(mut bytes, mut pending)| async move {
+        match bytes.next().await {
+            Some(Ok(chunk)) => {
+                pending.extend_from_slice(chunk.as_ref());
+                let valid = match std::str::from_utf8(&pending) {
+                    Ok(_) => pending.len(),
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    Err(_) => pending.len(),
+                };
+                let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+                pending.drain(..valid);
+                Some((Ok(text), (bytes, pending)))
+            }
+            Some(Err(e)) => Some((Err(e.to_string()), (bytes, pending))),
+            None if !pending.is_empty() => {
+                let text = String::from_utf8_lossy(&pending).into_owned();
+                pending.clear();
+                Some((Ok(text), (bytes, pending)))
+            }
+            None => None,
+        }
+    });
+    Box::pin(stream)
+}
+
+fn split_lines(text: TextStream) -> TextStream {
+    let state = (text, String::new(), VecDeque::<String>::new(), false);
+    let stream = stream::unfold(state, |(mut text, mut buffer, mut lines, mut done)| async move {
+        loop {
+            if let Some(line) = lines.pop_front() {
+                return Some((Ok(line), (text, buffer, lines, done)));
+            }
+            if done {
+                return None;
+            }
+            match text.next().await {
+                Some(Ok(chunk)) => {
+                    buffer.push_str(&chunk);
+                    while let Some(pos) = buffer.find('\n') {
+                        let line: String = buffer.drain(..=pos).collect();
+                        let line = line.trim();
+                        if !line.is_empty() {
+                            lines.push_back(line.to_string());
+                        }
+                    }
+                }
+                Some(Err(e)) => return Some((Err(e), (text, buffer, lines, true))),
+                None => {
+                    done = true;
+                    let rest = buffer.trim().to_string();
+                    buffer.clear();
+                    if !rest.is_empty() {
+                        lines.push_back(rest);
+                    }
+                }
+            }
+        }
+    });
+    Box::pin(stream)
+}
+
+/// Fixtures shared by the backend tests
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+    use wiremock::MockServer;
+
+    /// A backend of `config.kind` that talks to the mock server
+    pub fn backend_for(server: &MockServer, config: BackendConfig) -> Arc<dyn ChatBackend> {
+        from_config(&BackendConfig {
+            base_url: server.uri(),
+            ..config
+        })
+    }
+
+    /// "Hello" from Ada, without history or overrides
+    pub fn request() -> ChatRequest {
+        ChatRequest {
+            message: "Hello".to_string(),
+            nickname: "Ada".to_string(),
+            system_prompt: None,
+            persona: None,
+            temperature: None,
+            history: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_support::{backend_for, request};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn chunks(parts: &[&[u8]]) -> impl Stream<Item = Result<Vec<u8>, String>> + Send + 'static {
+        stream::iter(parts.iter().map(|part| Ok(part.to_vec())).collect::<Vec<_>>())
+    }
+
+    async fn collect(text: TextStream) -> Vec<String> {
+        text.map(|item| item.unwrap()).collect().await
+    }
+
+    #[tokio::test]
+    async fn holds_back_split_characters() {
+        // "é" is 0xC3 0xA9 and "🦀" is four bytes; both arrive split across chunks
+        let crab = "🦀".as_bytes();
+        let text = decode_utf8(chunks(&[b"caf\xC3", b"\xA9 ", &crab[..1], &crab[1..3], &crab[3..]]));
+        assert_eq!(collect(text).await.concat(), "café 🦀");
+    }
+
+    #[tokio::test]
+    async fn flushes_incomplete_tail() {
+        let text = decode_utf8(chunks(&[b"ok\xC3"]));
+        assert_eq!(collect(text).await.concat(), "ok\u{FFFD}");
+    }
+
+    #[tokio::test]
+    async fn splits_lines_across_chunks() {
+        let text = decode_utf8(chunks(&[b"data: {\"a\"", b": 1}\n\ndata: ", b"[DONE]\r\n", b"tail"]));
+        assert_eq!(
+            collect(split_lines(text)).await,
+            vec!["data: {\"a\": 1}", "data: [DONE]", "tail"]
+        );
+    }
+
+    #[tokio::test]
+    async fn maps_error_status_with_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).set_body_string(" model loading \n"))
+            .mount(&server)
+            .await;
+
+        let resp = reqwest::get(server.uri()).await.unwrap();
+        let err = check_status(resp).await.unwrap_err();
+        assert_eq!(err, "Backend returned 503 Service Unavailable: model loading");
+    }
+
+    #[tokio::test]
+    async fn silent_server_times_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let backend = backend_for(&server, BackendConfig {
+            read_timeout_secs: 1,
+            ..BackendConfig::default()
+        });
+        let started = std::time::Instant::now();
+        assert!(backend.chat(&request()).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+}
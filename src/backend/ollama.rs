@@ -0,0 +1,185 @@
+use futures_util::StreamExt;
+use serenity::async_trait;
+use crate::backend::{check_status, line_stream, ChatBackend, ChatRequest, TextStream};
+use crate::config::BackendConfig;
+
+/// Ollama's native API: POST /api/chat, streaming newline-delimited JSON
+pub struct OllamaBackend {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    temperature: f32,
+    max_tokens: u32,
+}
+
+impl OllamaBackend {
+    pub fn new(client: reqwest::Client, config: &BackendConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.clone(),
+            model: config.model.clone(),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+        }
+    }
+
+    async fn send(&self, request: &ChatRequest, stream: bool) -> Result<reqwest::Response, String> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": request.messages(),
+            "stream": stream,
+            "options": {
+                "temperature": request.temperature.unwrap_or(self.temperature),
+                "num_predict": self.max_tokens,
+            },
+        });
+        let resp = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach chatbot server: {}", e))?;
+        check_status(resp).await
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OllamaBackend {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn health(&self) -> bool {
+        match self.client.get(format!("{}/api/tags", self.base_url)).send().await {
+            Ok(resp) => resp.status().is_success(),
+            Err(_) => false,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<String, String> {
+        let json: serde_json::Value = self
+            .send(request, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid chat response: {}", e))?;
+        json["message"]["content"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| "Chat response has no message content".to_string())
+    }
+
+    async fn stream(&self, request: &ChatRequest) -> Result<TextStream, String> {
+        let resp = self.send(request, true).await?;
+        let stream = line_stream(resp).filter_map(|line| async move {
+            match line {
+                Ok(line) => {
+                    let json: serde_json::Value = serde_json::from_str(&line).ok()?;
+                    if let Some(error) = json["error"].as_str() {
+                        return Some(Err(error.to_string()));
+                    }
+                    json["message"]["content"]
+                        .as_str()
+                        .map(|s| Ok(s.to_string()))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_support::{backend_for, request};
+    use crate::config::BackendKind;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config() -> BackendConfig {
+        BackendConfig {
+            kind: BackendKind::Ollama,
+            model: "llama3".to_string(),
+            ..BackendConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_reads_message_content() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(serde_json::json!({
+                "model": "llama3",
+                "stream": false,
+                "options": {"temperature": 0.0}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "message": {"role": "assistant", "content": "Hi Ada"},
+                "done": true
+            })))
+            .mount(&server)
+            .await;
+
+        let request = ChatRequest {
+            temperature: Some(0.0),
+            ..request()
+        };
+        assert_eq!(backend_for(&server, config()).chat(&request).await.unwrap(), "Hi Ada");
+    }
+
+    #[tokio::test]
+    async fn streams_ndjson_lines() {
+        let server = MockServer::start().await;
+        let body = concat!(
+            "{\"message\":{\"content\":\"Hi \"},\"done\":false}\n",
+            "{\"message\":{\"content\":\"Ada\"},\"done\":false}\n",
+            "{\"message\":{\"content\":\"\"},\"done\":true}\n",
+        );
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(serde_json::json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/x-ndjson"))
+            .mount(&server)
+            .await;
+
+        let stream = backend_for(&server, config()).stream(&request()).await.unwrap();
+        let parts: Vec<String> = stream.map(|part| part.unwrap()).collect().await;
+        assert_eq!(parts.concat(), "Hi Ada");
+    }
+
+    #[tokio::test]
+    async fn stream_surfaces_error_field() {
+        let server = MockServer::start().await;
+        let body = concat!(
+            "{\"message\":{\"content\":\"Hi\"},\"done\":false}\n",
+            "{\"error\":\"model runner crashed\"}\n",
+        );
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/x-ndjson"))
+            .mount(&server)
+            .await;
+
+        let stream = backend_for(&server, config()).stream(&request()).await.unwrap();
+        let parts: Vec<Result<String, String>> = stream.collect().await;
+        assert_eq!(parts, vec![Ok("Hi".to_string()), Err("model runner crashed".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn error_status_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(404).set_body_json(serde_json::json!({"error": "model 'llama3' not found"})),
+            )
+            .mount(&server)
+            .await;
+
+        let backend = backend_for(&server, config());
+        let err = backend.chat(&request()).await.unwrap_err();
+        assert!(err.contains("404") && err.contains("not found"), "{}", err);
+        assert!(backend.stream(&request()).await.is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use futures_util::StreamExt;
+use serenity::async_trait;
+use crate::backend::{check_status, line_stream, ChatBackend, ChatRequest, TextStream};
+use crate::config::BackendConfig;
+
+/// OpenAI-compatible servers (llama.cpp server, vLLM, LM Studio): POST /v1/chat/completions
+pub struct OpenAiBackend {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    temperature: f32,
+    max_tokens: u32,
+}
+
+impl OpenAiBackend {
+    pub fn new(client: reqwest::Client, config: &BackendConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.clone(),
+            model: config.model.clone(),
+            api_key: config.api_key.clone(),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+        }
+    }
+
+    fn authorized(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    async fn send(&self, request: &ChatRequest, stream: bool) -> Result<reqwest::Response, String> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": request.messages(),
+            "temperature": request.temperature.unwrap_or(self.temperature),
+            "max_tokens": self.max_tokens,
+            "stream": stream,
+        });
+        let resp = self
+            .authorized(self.client.post(format!("{}/v1/chat/completions", self.base_url)))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach chatbot server: {}", e))?;
+        check_status(resp).await
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OpenAiBackend {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn health(&self) -> bool {
+        match self
+            .authorized(self.client.get(format!("{}/v1/models", self.base_url)))
+            .send()
+            .await
+        {
+            Ok(resp) => resp.status().is_success(),
+            Err(_) => false,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<String, String> {
+        let json: serde_json::Value = self
+            .send(request, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid completion response: {}", e))?;
+        json["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| "Completion response has no message content".to_string())
+    }
+
+    async fn stream(&self, request: &ChatRequest) -> Result<TextStream, String> {
+        let resp = self.send(request, true).await?;
+        // Server-sent events: `data: {json}` lines, terminated by `data: [DONE]`
+        let stream = line_stream(resp)
+            .take_while(|line| {
+                let done = matches!(line, Ok(l) if l.trim() == "data: [DONE]");
+                async move { !done }
+            })
+            .filter_map(|line| async move {
+                match line {
+                    Ok(line) => {
+                        let data = line.strip_prefix("data:")?.trim();
+                        let json: serde_json::Value = serde_json::from_str(data).ok()?;
+                        json["choices"][0]["delta"]["content"]
+                            .as_str()
+                            .map(|s| Ok(s.to_string()))
+                    }
+                    Err(e) => Some(Err(e)),
+                }
+            });
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_support::{backend_for, request};
+    use crate::config::BackendKind;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config() -> BackendConfig {
+        BackendConfig {
+            kind: BackendKind::OpenAi,
+            model: "llama-3".to_string(),
+            api_key: Some("secret".to_string()),
+            ..BackendConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_reads_message_content() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer secret"))
+            .and(body_partial_json(serde_json::json!({"model": "llama-3", "stream": false})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": "Hi Ada"}}]
+            })))
+            .mount(&server)
+            .await;
+
+        assert_eq!(backend_for(&server, config()).chat(&request()).await.unwrap(), "Hi Ada");
+    }
+
+    #[tokio::test]
+    async fn chat_without_content_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"choices": []})))
+            .mount(&server)
+            .await;
+
+        assert!(backend_for(&server, config()).chat(&request()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stream_stops_at_done() {
+        let server = MockServer::start().await;
+        let body = concat!(
+            ": keep-alive\n\n",
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi \"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Ada\"}}]}\n\n",
+            "data: [DONE]\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" (after done)\"}}]}\n\n",
+        );
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_partial_json(serde_json::json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let stream = backend_for(&server, config()).stream(&request()).await.unwrap();
+        let parts: Vec<String> = stream.map(|part| part.unwrap()).collect().await;
+        assert_eq!(parts, vec!["Hi ", "Ada"]);
+    }
+
+    #[tokio::test]
+    async fn error_status_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("bad key"))
+            .mount(&server)
+            .await;
+
+        let backend = backend_for(&server, config());
+        let err = backend.chat(&request()).await.unwrap_err();
+        assert!(err.contains("401") && err.contains("bad key"), "{}", err);
+        assert!(backend.stream(&request()).await.is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use serde::Deserialize;
+use crate::backend::ChatRequest;
+
+/// Prompt layouts for raw-completion servers, one per model family
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptFormat {
+    /// `[INST] <<SYS>> ... <</SYS>> ... [/INST]`, as used by the bundled ai_chatbot.py
+    Llama2,
+    /// `<|start_header_id|>role<|end_header_id|> ... <|eot_id|>`
+    Llama3,
+    /// `<|im_start|>role ... <|im_end|>` (Qwen, OpenHermes, ...)
+    ChatMl,
+    /// Llama-2 style without a system block; the system prompt leads the first message
+    Mistral,
+    /// `### Instruction:` / `### Response:`
+    Alpaca,
+}
+
+impl PromptFormat {
+    /// Guess the format from a model name such as "llama-3-8b-instruct.Q4_K_M.gguf"
+    pub fn detect(model: &str) -> Option<PromptFormat> {
+        let model = model.to_lowercase().replace(['-', '_', ' ', '.'], "");
+        if model.contains("llama3") {
+            Some(PromptFormat::Llama3)
+        } else if model.contains("llama2") {
+            Some(PromptFormat::Llama2)
+        } else if model.contains("mistral") || model.contains("mixtral") {
+            Some(PromptFormat::Mistral)
+        } else if model.contains("alpaca") {
+            Some(PromptFormat::Alpaca)
+        } else if ["chatml", "qwen", "hermes"].iter().any(|name| model.contains(name)) {
+            Some(PromptFormat::ChatMl)
+        } else {
+            None
+        }
+    }
+
+    /// Sequences that end the model's turn
+    pub fn stop_sequences(self) -> &'static [&'static str] {
+        match self {
+            PromptFormat::Llama2 | PromptFormat::Mistral => &["</s>", "[INST]"],
+            PromptFormat::Llama3 => &["<|eot_id|>", "<|start_header_id|>"],
+            PromptFormat::ChatMl => &["<|im_end|>", "<|im_start|>"],
+            PromptFormat::Alpaca => &["### Instruction:"],
+        }
+    }
+
+    /// Build the full prompt: system prompt (with persona and nickname), history, then
+    /// the new message, ending where the model should start writing
+    pub fn render(self, request: &ChatRequest) -> String {
+        let system = request.system_message();
+        let turns = request
+            .history
+            .iter()
+            .map(|m| (m.role, m.content.as_str()))
+            .chain(std::iter::once(("user", request.message.as_str())));
+
+        let mut prompt = String::new();
+        match self {
+            PromptFormat::Llama2 | PromptFormat::Mistral => {
+                let mut first = true;
+                for (role, content) in turns {
+                    if role != "user" {
+                        prompt.push_str(&format!(" {} </s>", content.trim()));
+                        continue;
+                    }
+                    let lead = match (first, self) {
+                        (true, PromptFormat::Llama2) => format!("<<SYS>>\n{}\n<</SYS>>\n\n", system),
+                        (true, _) => format!("{}\n\n", system),
+                        _ => String::new(),
+                    };
+                    prompt.push_str(&format!("[INST] {}{} [/INST]", lead, content.trim()));
+                    first = false;
+                }
+            }
+            PromptFormat::Llama3 => {
+                let header = |role: &str| format!("<|start_header_id|>{}<|end_header_id|>\n\n", role);
+                prompt.push_str(&format!("{}{}<|eot_id|>", header("system"), system));
+                for (role, content) in turns {
+                    prompt.push_str(&format!("{}{}<|eot_id|>", header(role), content.trim()));
+                }
+                prompt.push_str(&header("assistant"));
+            }
+            PromptFormat::ChatMl => {
+                prompt.push_str(&format!("<|im_start|>system\n{}<|im_end|>\n", system));
+                for (role, content) in turns {
+                    prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", role, content.trim()));
+                }
+                prompt.push_str("<|im_start|>assistant\n");
+            }
+            PromptFormat::Alpaca => {
+                prompt.push_str(&format!("{}\n\n", system));
+                for (role, content) in turns {
+                    let heading = if role == "user" { "Instruction" } else { "Response" };
+                    prompt.push_str(&format!("### {}:\n{}\n\n", heading, content.trim()));
+                }
+                prompt.push_str("### Response:\n");
+            }
+        }
+        prompt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_support::request;
+    use crate::history::HistoryMessage;
+
+    /// A follow-up after one exchange, with a system prompt
+    fn follow_up() -> ChatRequest {
+        ChatRequest {
+            message: "And now?".to_string(),
+            system_prompt: Some("Be brief.".to_string()),
+            history: vec![
+                HistoryMessage { role: "user", content: "Hi".to_string() },
+                HistoryMessage { role: "assistant", content: "Hello!".to_string() },
+            ],
+            ..request()
+        }
+    }
+
+    #[test]
+    fn renders_each_family() {
+        let system = "Be brief. The user's name is Ada.";
+        let request = follow_up();
+
+        assert_eq!(
+            PromptFormat::Llama2.render(&request),
+            format!("[INST] <<SYS>>\n{}\n<</SYS>>\n\nHi [/INST] Hello! </s>[INST] And now? [/INST]", system)
+        );
+        assert_eq!(
+            PromptFormat::Mistral.render(&request),
+            format!("[INST] {}\n\nHi [/INST] Hello! </s>[INST] And now? [/INST]", system)
+        );
+        assert_eq!(
+            PromptFormat::Llama3.render(&request),
+            format!(
+                "<|start_header_id|>system<|end_header_id|>\n\n{}<|eot_id|>\
+                 <|start_header_id|>user<|end_header_id|>\n\nHi<|eot_id|>\
+                 <|start_header_id|>assistant<|end_header_id|>\n\nHello!<|eot_id|>\
+                 <|start_header_id|>user<|end_header_id|>\n\nAnd now?<|eot_id|>\
+                 <|start_header_id|>assistant<|end_header_id|>\n\n",
+                system
+            )
+        );
+        assert_eq!(
+            PromptFormat::ChatMl.render(&request),
+            format!(
+                "<|im_start|>system\n{}<|im_end|>\n<|im_start|>user\nHi<|im_end|>\n\
+                 <|im_start|>assistant\nHello!<|im_end|>\n<|im_start|>user\nAnd now?<|im_end|>\n\
+                 <|im_start|>assistant\n",
+                system
+            )
+        );
+        assert_eq!(
+            PromptFormat::Alpaca.render(&request),
+            format!(
+                "{}\n\n### Instruction:\nHi\n\n### Response:\nHello!\n\n### Instruction:\nAnd now?\n\n### Response:\n",
+                system
+            )
+        );
+    }
+
+    #[test]
+    fn system_block_only_once_and_detection() {
+        let mut request = follow_up();
+        request.history.clear();
+        assert!(PromptFormat::Llama2.render(&request).starts_with("[INST] <<SYS>>"));
+
+        request.system_prompt = None;
+        request.persona = Some("You are a pirate.".to_string());
+        assert!(PromptFormat::ChatMl.render(&request).starts_with("<|im_start|>system\nYou are a pirate. The user's"));
+
+        assert_eq!(PromptFormat::detect("Meta-Llama-3-8B-Instruct.Q4_K_M.gguf"), Some(PromptFormat::Llama3));
+        assert_eq!(PromptFormat::detect("llama-2-7b-chat.Q5_K_M.gguf"), Some(PromptFormat::Llama2));
+        assert_eq!(PromptFormat::detect("mistral-7b-instruct-v0.2"), Some(PromptFormat::Mistral));
+        assert_eq!(PromptFormat::detect("qwen2-7b"), Some(PromptFormat::ChatMl));
+        assert_eq!(PromptFormat::detect("gpt-j"), None);
+    }
+}
@@ -0,0 +1,191 @@
+use chrono::Local;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use once_cell::sync::Lazy;
+use crate::config::ChatbotConfig;
+
+/// Most recent chatbot output lines, oldest first
+static LOG_BUFFER: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Append-only log file that rotates to `.1`, `.2`, ... once it grows past `max_bytes`
+struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Option<File>,
+    written: u64,
+}
+
+impl RotatingLog {
+    fn open(config: &ChatbotConfig) -> Self {
+        let path = PathBuf::from(&config.log_path);
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path);
+        if let Err(e) = &file {
+            eprintln!("[ERROR] Failed to open chatbot log '{}': {}", path.display(), e);
+        }
+        let written = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        Self {
+            path,
+            max_bytes: config.log_max_bytes,
+            keep: config.log_keep_files,
+            file: file.ok(),
+            written,
+        }
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.written >= self.max_bytes {
+            self.rotate();
+        }
+        if let Some(file) = self.file.as_mut() {
+            if writeln!(file, "{}", line).is_ok() {
+                self.written += line.len() as u64 + 1;
+            }
+        }
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        if self.keep == 0 {
+            let _ = fs::remove_file(&self.path);
+        } else {
+            let _ = fs::remove_file(rotated(self.keep));
+            for n in (1..self.keep).rev() {
+                let _ = fs::rename(rotated(n), rotated(n + 1));
+            }
+            let _ = fs::rename(&self.path, rotated(1));
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path).ok();
+        self.written = 0;
+    }
+}
+
+/// Take the child's piped stdout/stderr and copy every line to the log file and ring buffer.
+///
+/// Uses plain threads since the child is a `std::process::Child` with blocking pipes;
+/// the readers end on their own when the process exits and closes its pipes.
+pub fn capture(child: &mut Child, config: &ChatbotConfig) {
+    let log = Arc::new(Mutex::new(RotatingLog::open(config)));
+    let capacity = config.log_buffer_lines;
+
+    if let Some(stdout) = child.stdout.take() {
+        spawn_reader(stdout, "stdout", log.clone(), capacity);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_reader(stderr, "stderr", log, capacity);
+    }
+}
+
+fn spawn_reader<R: Read + Send + 'static>(
+    source: R,
+    stream: &'static str,
+    log: Arc<Mutex<RotatingLog>>,
+    capacity: usize,
+) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut raw = Vec::new();
+        loop {
+            raw.clear();
+            match reader.read_until(b'\n', &mut raw) {
+                Ok(0) => break,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&raw);
+                    let line = format!(
+                        "{} [{}] {}",
+                        Local::now().format("%Y-%m-%d %H:%M:%S"),
+                        stream,
+                        text.trim_end()
+                    );
+                    if let Ok(mut log) = log.lock() {
+                        log.write_line(&line);
+                    }
+                    push_line(line, capacity);
+                }
+                Err(e) => {
+                    eprintln!("[ERROR] Failed to read chatbot {}: {}", stream, e);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+fn push_line(line: String, capacity: usize) {
+    if let Ok(mut buffer) = LOG_BUFFER.lock() {
+        buffer.push_back(line);
+        while buffer.len() > capacity {
+            buffer.pop_front();
+        }
+    }
+}
+
+/// The last `lines` captured lines, oldest first
+pub fn tail(lines: usize) -> Vec<String> {
+    match LOG_BUFFER.lock() {
+        Ok(buffer) => {
+            let skip = buffer.len().saturating_sub(lines);
+            buffer.iter().skip(skip).cloned().collect()
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_past_max_bytes() {
+        let dir = std::env::temp_dir().join(format!("chatbot-logs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("chatbot.log");
+        let config = ChatbotConfig {
+            log_path: path.display().to_string(),
+            log_max_bytes: 10,
+            log_keep_files: 2,
+            ..ChatbotConfig::default()
+        };
+
+        let mut log = RotatingLog::open(&config);
+        log.write_line("first line");
+        log.write_line("second line");
+        log.write_line("third line");
+        drop(log);
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("chatbot.log"), "third line\n");
+        assert_eq!(read("chatbot.log.1"), "second line\n");
+        assert_eq!(read("chatbot.log.2"), "first line\n");
+
+        // Beyond `keep` the oldest file is dropped
+        let mut log = RotatingLog::open(&config);
+        log.write_line("fourth line");
+        drop(log);
+        assert_eq!(read("chatbot.log"), "fourth line\n");
+        assert_eq!(read("chatbot.log.1"), "third line\n");
+        assert_eq!(read("chatbot.log.2"), "second line\n");
+        assert!(!dir.join("chatbot.log.3").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn buffer_keeps_the_newest_lines() {
+        for n in 1..=8 {
+            push_line(format!("line {}", n), 5);
+        }
+        assert_eq!(tail(100).len(), 5);
+        assert_eq!(tail(3), vec!["line 6", "line 7", "line 8"]);
+        assert_eq!(tail(5).first().map(String::as_str), Some("line 4"));
+    }
+}
@@ -0,0 +1,234 @@
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::{
+    application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+    InteractionResponseType,
+};
+use serenity::model::channel::ChannelType;
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::model::Permissions;
+use serenity::prelude::*;
+use crate::config::Config;
+use crate::db::Store;
+
+/// Longest per-guild system prompt
+const PROMPT_MAX_LEN: u16 = 2000;
+
+/// Register /ai-channel add|remove|list|prompt|toggle
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("ai-channel")
+        .description("Manage where and how the AI answers in this server.")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
+        .create_option(|sub| {
+            sub.name("add")
+                .description("Let the AI answer in a channel")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("channel")
+                        .description("Channel to add")
+                        .kind(CommandOptionType::Channel)
+                        .channel_types(&[ChannelType::Text])
+                        .required(true)
+                })
+        })
+        .create_option(|sub| {
+            sub.name("remove")
+                .description("Stop the AI answering in a channel")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("channel")
+                        .description("Channel to remove")
+                        .kind(CommandOptionType::Channel)
+                        .channel_types(&[ChannelType::Text])
+                        .required(true)
+                })
+        })
+        .create_option(|sub| {
+            sub.name("list")
+                .description("List the channels the AI answers in")
+                .kind(CommandOptionType::SubCommand)
+        })
+        .create_option(|sub| {
+            sub.name("prompt")
+                .description("Set the system prompt used in this server")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("text")
+                        .description("How the AI should behave here (leave out for the default)")
+                        .kind(CommandOptionType::String)
+                        .max_length(PROMPT_MAX_LEN)
+                        .required(false)
+                })
+        })
+        .create_option(|sub| {
+            sub.name("toggle")
+                .description("Turn AI chat on or off in this server")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("chat")
+                        .description("Answer messages in the AI channels")
+                        .kind(CommandOptionType::Boolean)
+                        .required(true)
+                })
+        })
+}
+
+/// Handle /ai-channel
+pub async fn handle_ai_channel(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    store: &dyn Store,
+    config: &Config,
+) {
+    let guild_id = match command.guild_id {
+        Some(id) => id.0,
+        None => {
+            respond(ctx, command, "This command can only be used in a server.".to_string()).await;
+            return;
+        }
+    };
+
+    let subcommand = match command.data.options.first() {
+        Some(sub) => sub,
+        None => return,
+    };
+
+    let option = |name: &str| {
+        subcommand
+            .options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| opt.resolved.as_ref())
+    };
+    let channel_id = match option("channel") {
+        Some(CommandDataOptionValue::Channel(channel)) => Some(channel.id.0),
+        _ => None,
+    };
+
+    // The first change copies the config file's channels into the guild's settings, which
+    // replace them from then on; otherwise the bot would stop answering in those channels
+    if matches!(subcommand.name.as_str(), "add" | "remove" | "prompt" | "toggle") {
+        let seed = config_channels(ctx, guild_id, config).await;
+        match store.ensure_guild_settings(guild_id, &seed).await {
+            Ok(true) => println!("[LOG] Guild {} settings created with config channels {:?}", guild_id, seed),
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                respond(ctx, command, "❌ Failed to update guild settings.".to_string()).await;
+                return;
+            }
+        }
+    }
+
+    let reply = match (subcommand.name.as_str(), channel_id) {
+        ("add", Some(channel_id)) => match store.add_ai_channel(guild_id, channel_id).await {
+            Ok(_) => {
+                println!("[LOG] Guild {} added AI channel {}", guild_id, channel_id);
+                format!("✅ The AI will now answer in <#{}>.", channel_id)
+            }
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                "❌ Failed to update guild settings.".to_string()
+            }
+        },
+        ("remove", Some(channel_id)) => match store.remove_ai_channel(guild_id, channel_id).await {
+            Ok(true) => {
+                println!("[LOG] Guild {} removed AI channel {}", guild_id, channel_id);
+                format!("🛑 The AI will no longer answer in <#{}>.", channel_id)
+            }
+            Ok(false) => format!("⚠️ <#{}> is not an AI channel.", channel_id),
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                "❌ Failed to update guild settings.".to_string()
+            }
+        },
+        ("list", _) => {
+            let channels: Vec<String> = match store.get_guild_settings(guild_id).await {
+                Ok(Some(settings)) => settings.ai_channel_ids,
+                Ok(None) | Err(_) => config_channels(ctx, guild_id, config)
+                    .await
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect(),
+            };
+            if channels.is_empty() {
+                "No AI channels configured. Use `/ai-channel add`.".to_string()
+            } else {
+                let lines: Vec<String> = channels.iter().map(|id| format!("• <#{}>", id)).collect();
+                format!("AI channels:\n{}", lines.join("\n"))
+            }
+        }
+        ("prompt", _) => {
+            let prompt = match option("text") {
+                Some(CommandDataOptionValue::String(text)) => Some(text.trim().to_string()).filter(|t| !t.is_empty()),
+                _ => None,
+            };
+            let cleared = prompt.is_none();
+            match store.set_system_prompt(guild_id, prompt).await {
+                Ok(()) if cleared => {
+                    println!("[LOG] Guild {} cleared its system prompt", guild_id);
+                    "✅ The AI is back to its default system prompt here.".to_string()
+                }
+                Ok(()) => {
+                    println!("[LOG] Guild {} set its system prompt", guild_id);
+                    "✅ Updated the system prompt for this server.".to_string()
+                }
+                Err(e) => {
+                    eprintln!("[ERROR] {}", e);
+                    "❌ Failed to update guild settings.".to_string()
+                }
+            }
+        }
+        ("toggle", _) => {
+            let enabled = matches!(option("chat"), Some(CommandDataOptionValue::Boolean(true)));
+            match store.set_ai_chat(guild_id, enabled).await {
+                Ok(()) => {
+                    println!("[LOG] Guild {} turned AI chat {}", guild_id, if enabled { "on" } else { "off" });
+                    if enabled {
+                        "✅ The AI will answer in this server's AI channels.".to_string()
+                    } else {
+                        "🛑 The AI will stay quiet in this server until chat is turned back on.".to_string()
+                    }
+                }
+                Err(e) => {
+                    eprintln!("[ERROR] {}", e);
+                    "❌ Failed to update guild settings.".to_string()
+                }
+            }
+        }
+        _ => "⚠️ Unknown subcommand.".to_string(),
+    };
+
+    respond(ctx, command, reply).await;
+}
+
+/// The config file's `ai_channel_ids` that belong to this guild
+async fn config_channels(ctx: &Context, guild_id: u64, config: &Config) -> Vec<u64> {
+    let configured = &config.discord.ai_channel_ids;
+    if configured.is_empty() {
+        return Vec::new();
+    }
+    match GuildId(guild_id).channels(&ctx.http).await {
+        Ok(channels) => configured
+            .iter()
+            .copied()
+            .filter(|id| channels.contains_key(&ChannelId(*id)))
+            .collect(),
+        Err(e) => {
+            // Keeping another guild's ids is harmless; dropping this guild's is not
+            eprintln!("[ERROR] Failed to fetch channels of guild {}: {:?}", guild_id, e);
+            configured.clone()
+        }
+    }
+}
+
+async fn respond(ctx: &Context, command: &ApplicationCommandInteraction, content: String) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(content).ephemeral(true))
+        })
+        .await;
+}
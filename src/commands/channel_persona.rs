@@ -0,0 +1,140 @@
+use serenity::builder::{CreateApplicationCommand, CreateApplicationCommandOption};
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::{
+    application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+    InteractionResponseType,
+};
+use serenity::model::channel::ChannelType;
+use serenity::model::Permissions;
+use serenity::prelude::*;
+use crate::db::Store;
+use crate::webhooks::WebhookManager;
+
+/// Register /channel-persona set|clear|show
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("channel-persona")
+        .description("Make the AI speak as one persona in a channel.")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
+        .create_option(|sub| {
+            sub.name("set")
+                .description("Bind a persona to a channel")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("persona")
+                        .description("Persona name (see /persona list)")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                });
+            channel_option(sub)
+        })
+        .create_option(|sub| {
+            sub.name("clear")
+                .description("Remove the channel's persona and its webhook")
+                .kind(CommandOptionType::SubCommand);
+            channel_option(sub)
+        })
+        .create_option(|sub| {
+            sub.name("show")
+                .description("Show the persona bound to a channel")
+                .kind(CommandOptionType::SubCommand);
+            channel_option(sub)
+        })
+}
+
+fn channel_option(sub: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    sub.create_sub_option(|opt| {
+        opt.name("channel")
+            .description("Channel (defaults to this one)")
+            .kind(CommandOptionType::Channel)
+            .channel_types(&[ChannelType::Text])
+            .required(false)
+    })
+}
+
+/// Handle /channel-persona
+pub async fn handle_channel_persona(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    store: &dyn Store,
+    webhooks: &WebhookManager,
+) {
+    let guild_id = match command.guild_id {
+        Some(id) => id.0,
+        None => {
+            respond(ctx, command, "This command can only be used in a server.".to_string()).await;
+            return;
+        }
+    };
+
+    let subcommand = match command.data.options.first() {
+        Some(sub) => sub,
+        None => return,
+    };
+
+    let mut channel_id = command.channel_id.0;
+    let mut persona_name = None;
+    for opt in &subcommand.options {
+        match (opt.name.as_str(), opt.resolved.as_ref()) {
+            ("channel", Some(CommandDataOptionValue::Channel(channel))) => channel_id = channel.id.0,
+            ("persona", Some(CommandDataOptionValue::String(name))) => persona_name = Some(name.trim().to_lowercase()),
+            _ => {}
+        }
+    }
+
+    let failed = |e: String| {
+        eprintln!("[ERROR] {}", e);
+        "❌ Failed to update channel personas.".to_string()
+    };
+
+    let reply = match (subcommand.name.as_str(), persona_name) {
+        ("set", Some(name)) => match store.get_persona(&name).await {
+            Ok(Some(persona)) => match webhooks.bind(guild_id, channel_id, &persona).await {
+                Ok(binding) => {
+                    println!("[LOG] Guild {} bound persona '{}' to channel {}", guild_id, persona.name, channel_id);
+                    // Create the webhook now so a missing Manage Webhooks permission shows up here
+                    match webhooks.webhook_for(&ctx.http, &binding).await {
+                        Ok(_) => format!("✅ The AI now speaks as **{}** in <#{}>.", persona.name, channel_id),
+                        Err(e) => {
+                            eprintln!("[ERROR] {}", e);
+                            format!(
+                                "⚠️ Bound **{}** to <#{}>, but the webhook could not be created. \
+                                 Replies will come from the bot until it has the Manage Webhooks permission.",
+                                persona.name, channel_id
+                            )
+                        }
+                    }
+                }
+                Err(e) => failed(e),
+            },
+            Ok(None) => format!("⚠️ There is no persona called **{}**.", name),
+            Err(e) => failed(e),
+        },
+        ("clear", _) => match webhooks.unbind(&ctx.http, channel_id).await {
+            Ok(Some(name)) => {
+                println!("[LOG] Guild {} removed persona '{}' from channel {}", guild_id, name, channel_id);
+                format!("🛑 <#{}> no longer has a persona.", channel_id)
+            }
+            Ok(None) => format!("⚠️ <#{}> has no persona.", channel_id),
+            Err(e) => failed(e),
+        },
+        ("show", _) => match store.get_channel_persona(channel_id).await {
+            Ok(Some(binding)) => format!("<#{}> speaks as **{}**.", channel_id, binding.persona),
+            Ok(None) => format!("<#{}> has no persona. Use `/channel-persona set`.", channel_id),
+            Err(e) => failed(e),
+        },
+        _ => "⚠️ Unknown subcommand.".to_string(),
+    };
+
+    respond(ctx, command, reply).await;
+}
+
+async fn respond(ctx: &Context, command: &ApplicationCommandInteraction, content: String) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(content).ephemeral(true))
+        })
+        .await;
+}
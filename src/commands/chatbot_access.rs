@@ -0,0 +1,152 @@
+use chrono::Utc;
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::{
+    application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+    InteractionResponseType,
+};
+use serenity::model::Permissions;
+use serenity::prelude::*;
+use crate::db::access::{AccessGrant, AccessKind};
+use crate::db::audit::AuditEntry;
+use crate::db::Store;
+
+/// Register /chatbot-access allow|revoke|list
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("chatbot-access")
+        .description("Manage who may start and stop the chatbot.")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
+        .create_option(|sub| {
+            sub.name("allow")
+                .description("Let a role or member use /run-chatbot and /stop-chatbot")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("target")
+                        .description("Role or member")
+                        .kind(CommandOptionType::Mentionable)
+                        .required(true)
+                })
+        })
+        .create_option(|sub| {
+            sub.name("revoke")
+                .description("Take chatbot control away from a role or member")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("target")
+                        .description("Role or member")
+                        .kind(CommandOptionType::Mentionable)
+                        .required(true)
+                })
+        })
+        .create_option(|sub| {
+            sub.name("list")
+                .description("List who may control the chatbot")
+                .kind(CommandOptionType::SubCommand)
+        })
+}
+
+fn mention(kind: AccessKind, id: &str) -> String {
+    match kind {
+        AccessKind::Role => format!("<@&{}>", id),
+        AccessKind::User => format!("<@{}>", id),
+    }
+}
+
+/// Handle /chatbot-access
+pub async fn handle_chatbot_access(ctx: &Context, command: &ApplicationCommandInteraction, store: &dyn Store) {
+    let guild_id = match command.guild_id {
+        Some(id) => id.0,
+        None => {
+            respond(ctx, command, "This command can only be used in a server.".to_string()).await;
+            return;
+        }
+    };
+
+    let subcommand = match command.data.options.first() {
+        Some(sub) => sub,
+        None => return,
+    };
+
+    let target = subcommand
+        .options
+        .first()
+        .and_then(|opt| opt.resolved.as_ref())
+        .and_then(|val| match val {
+            CommandDataOptionValue::Role(role) => Some((AccessKind::Role, role.id.0)),
+            CommandDataOptionValue::User(user, _) => Some((AccessKind::User, user.id.0)),
+            _ => None,
+        });
+
+    let (reply, audit) = match (subcommand.name.as_str(), target) {
+        ("allow", Some((kind, id))) => match store.grant_access(AccessGrant::new(guild_id, kind, id)).await {
+            Ok(true) => (
+                format!("✅ {} can now start and stop the chatbot.", mention(kind, &id.to_string())),
+                Some(("chatbot_access_grant", kind, id)),
+            ),
+            Ok(false) => (format!("⚠️ {} already has access.", mention(kind, &id.to_string())), None),
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                ("❌ Failed to update chatbot access.".to_string(), None)
+            }
+        },
+        ("revoke", Some((kind, id))) => match store.revoke_access(AccessGrant::new(guild_id, kind, id)).await {
+            Ok(true) => (
+                format!("🛑 {} can no longer control the chatbot.", mention(kind, &id.to_string())),
+                Some(("chatbot_access_revoke", kind, id)),
+            ),
+            Ok(false) => (format!("⚠️ {} had no access.", mention(kind, &id.to_string())), None),
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                ("❌ Failed to update chatbot access.".to_string(), None)
+            }
+        },
+        ("list", _) => match store.list_access(guild_id).await {
+            Ok(grants) if grants.is_empty() => (
+                "Only admins can control the chatbot. Use `/chatbot-access allow` to add others.".to_string(),
+                None,
+            ),
+            Ok(grants) => {
+                let lines: Vec<String> = grants
+                    .iter()
+                    .map(|g| format!("• {}", mention(g.kind, &g.target_id)))
+                    .collect();
+                (format!("Besides admins, these can control the chatbot:\n{}", lines.join("\n")), None)
+            }
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                ("❌ Failed to load chatbot access.".to_string(), None)
+            }
+        },
+        _ => ("⚠️ Unknown subcommand.".to_string(), None),
+    };
+
+    if let Some((action, kind, id)) = audit {
+        println!("[LOG] Guild {}: {} {:?} {} by {}", guild_id, action, kind, id, command.user.id);
+        let entry = AuditEntry {
+            actor_id: command.user.id.to_string(),
+            target_id: id.to_string(),
+            action: action.to_string(),
+            detail: format!("guild={} kind={:?}", guild_id, kind),
+            timestamp: Utc::now().timestamp(),
+        };
+        if let Err(e) = store.record_audit(entry).await {
+            eprintln!("[ERROR] {}", e);
+        }
+    }
+
+    respond(ctx, command, reply).await;
+}
+
+async fn respond(ctx: &Context, command: &ApplicationCommandInteraction, content: String) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    // Listing roles should not ping them
+                    d.content(content).ephemeral(true).allowed_mentions(|m| m.empty_parse())
+                })
+        })
+        .await;
+}
@@ -0,0 +1,73 @@
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::{
+    application_command::ApplicationCommandInteraction,
+    InteractionResponseType,
+};
+use serenity::model::channel::AttachmentType;
+use serenity::model::Permissions;
+use serenity::prelude::*;
+use std::borrow::Cow;
+use crate::chatbot_logs;
+
+const DEFAULT_LINES: u64 = 30;
+const MAX_LINES: u64 = 500;
+
+/// Logs longer than this go out as an attachment instead of a code block
+const INLINE_LIMIT: usize = 1900;
+
+/// Register /chatbot-logs
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("chatbot-logs")
+        .description("Show the most recent output of the chatbot process.")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
+        .create_option(|opt| {
+            opt.name("lines")
+                .description("Number of lines to show")
+                .kind(CommandOptionType::Integer)
+                .min_int_value(1)
+                .max_int_value(MAX_LINES)
+                .required(false)
+        })
+}
+
+/// Handle /chatbot-logs
+pub async fn handle_chatbot_logs(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let lines = command
+        .data
+        .options
+        .first()
+        .and_then(|opt| opt.value.as_ref())
+        .and_then(|val| val.as_u64())
+        .unwrap_or(DEFAULT_LINES)
+        .min(MAX_LINES) as usize;
+
+    let text = chatbot_logs::tail(lines).join("\n");
+
+    let result = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.ephemeral(true);
+                    if text.is_empty() {
+                        d.content("No chatbot output captured yet.")
+                    } else if text.len() <= INLINE_LIMIT {
+                        // Keep the log from closing our code block early
+                        d.content(format!("```\n{}\n```", text.replace("```", "`\u{200b}``")))
+                    } else {
+                        d.content(format!("Last {} lines of chatbot output:", lines))
+                            .add_file(AttachmentType::Bytes {
+                                data: Cow::from(text.clone().into_bytes()),
+                                filename: "chatbot.log".to_string(),
+                            })
+                    }
+                })
+        })
+        .await;
+
+    if let Err(e) = result {
+        eprintln!("[ERROR] Failed to send chatbot logs: {:?}", e);
+    }
+}
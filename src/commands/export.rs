@@ -0,0 +1,176 @@
+use chrono::Utc;
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::{
+    application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+    InteractionResponseType,
+};
+use serenity::model::channel::AttachmentType;
+use serenity::model::id::ChannelId;
+use serenity::prelude::*;
+use std::borrow::Cow;
+use crate::config::DiscordConfig;
+use crate::permissions::{is_admin, is_bot_admin};
+use crate::db::user::Conversation;
+use crate::db::Store;
+use crate::export::{self, ExportFormat};
+
+/// Discord rejects uploads above this size on servers without boosts
+const MAX_UPLOAD_BYTES: usize = 8 * 1024 * 1024;
+
+/// Register /export
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("export")
+        .description("Download your conversations with the AI as a file.")
+        .create_option(|opt| {
+            opt.name("format")
+                .description("File format")
+                .kind(CommandOptionType::String)
+                .required(true)
+                .add_string_choice("JSON (can be re-imported)", "json")
+                .add_string_choice("Markdown transcript", "markdown")
+                .add_string_choice("ShareGPT JSONL", "sharegpt")
+                .add_string_choice("Alpaca JSONL", "alpaca")
+        })
+        .create_option(|opt| {
+            opt.name("all_users")
+                .description("Export every user's conversations in this server (admins only)")
+                .kind(CommandOptionType::Boolean)
+                .required(false)
+        })
+}
+
+/// Keep the conversations held in the channels of the guild the command was run in
+async fn guild_conversations(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    conversations: Vec<(u64, Conversation)>,
+) -> Result<Vec<(u64, Conversation)>, String> {
+    let guild_id = command.guild_id.ok_or_else(|| "Bulk export outside a server".to_string())?;
+    let channels = guild_id
+        .channels(&ctx.http)
+        .await
+        .map_err(|e| format!("Failed to fetch channels of guild {}: {:?}", guild_id, e))?;
+    Ok(conversations
+        .into_iter()
+        .filter(|(_, c)| {
+            c.channel_id
+                .as_deref()
+                .and_then(|id| id.parse::<u64>().ok())
+                .is_some_and(|id| channels.contains_key(&ChannelId(id)))
+        })
+        .collect())
+}
+
+/// Handle /export
+pub async fn handle_export(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    store: &dyn Store,
+    config: &DiscordConfig,
+) {
+    let mut format = ExportFormat::Json;
+    let mut all_users = false;
+    for opt in &command.data.options {
+        match (opt.name.as_str(), opt.resolved.as_ref()) {
+            ("format", Some(CommandDataOptionValue::String(value))) => {
+                format = ExportFormat::parse(value).unwrap_or(ExportFormat::Json)
+            }
+            ("all_users", Some(CommandDataOptionValue::Boolean(b))) => all_users = *b,
+            _ => {}
+        }
+    }
+
+    if all_users && !is_admin(command.member.as_ref(), config) {
+        println!("[LOG] User {} was denied a bulk /export", command.user.id);
+        let _ = command
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.content("Only admins can export every user's conversations.").ephemeral(true)
+                    })
+            })
+            .await;
+        return;
+    }
+
+    // Bulk exports can take a while to read
+    if let Err(e) = command.defer_ephemeral(&ctx.http).await {
+        eprintln!("[ERROR] Failed to defer /export: {:?}", e);
+        return;
+    }
+
+    let owner = if all_users { None } else { Some(command.user.id.0) };
+    let conversations = match store.export_conversations(owner).await {
+        // Server admins only get what was said in their own server; the configured bot
+        // admins may export everything
+        Ok(conversations) if all_users && !is_bot_admin(command.member.as_ref(), config) => {
+            guild_conversations(ctx, command, conversations).await
+        }
+        other => other,
+    };
+    let rendered = match conversations {
+        Ok(conversations) if conversations.is_empty() => {
+            Err("There are no stored conversations to export.".to_string())
+        }
+        Ok(conversations) => export::render(format, &conversations)
+            .map(|text| (text, conversations.len()))
+            .map_err(|e| {
+                eprintln!("[ERROR] {}", e);
+                "❌ Failed to build the export.".to_string()
+            }),
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            Err("❌ Failed to load conversations.".to_string())
+        }
+    };
+
+    let (text, count) = match rendered {
+        Ok((text, _)) if text.len() > MAX_UPLOAD_BYTES => {
+            let _ = command
+                .edit_original_interaction_response(&ctx.http, |r| {
+                    r.content("❌ The export is too large to upload to Discord.")
+                })
+                .await;
+            return;
+        }
+        Ok(result) => result,
+        Err(message) => {
+            let _ = command
+                .edit_original_interaction_response(&ctx.http, |r| r.content(message))
+                .await;
+            return;
+        }
+    };
+
+    println!(
+        "[LOG] User {} exported {} conversation(s) as {:?}{}",
+        command.user.id,
+        count,
+        format,
+        if all_users { " (all users)" } else { "" }
+    );
+
+    let scope = if all_users { "all-users".to_string() } else { command.user.id.to_string() };
+    let filename = format!(
+        "conversations-{}-{}.{}",
+        scope,
+        Utc::now().format("%Y%m%d"),
+        format.extension()
+    );
+
+    let result = command
+        .create_followup_message(&ctx.http, |f| {
+            f.content(format!("📦 Exported {} conversation(s).", count))
+                .ephemeral(true)
+                .add_file(AttachmentType::Bytes {
+                    data: Cow::from(text.into_bytes()),
+                    filename,
+                })
+        })
+        .await;
+    if let Err(e) = result {
+        eprintln!("[ERROR] Failed to upload export: {:?}", e);
+    }
+}
@@ -0,0 +1,230 @@
+use chrono::Utc;
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::{
+    application_command::ApplicationCommandInteraction,
+    message_component::MessageComponentInteraction,
+    InteractionResponseType,
+};
+use serenity::prelude::*;
+use crate::db::audit::AuditEntry;
+use crate::db::Store;
+
+/// Prefix of every custom_id this module owns
+pub const CUSTOM_ID_PREFIX: &str = "forget";
+
+/// Shown when someone presses buttons on another user's deletion prompt
+const NOT_ALLOWED: &str = "Only the person who asked for this deletion can confirm it.";
+
+/// Register /forget last|all
+pub fn register_forget(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("forget")
+        .description("Delete your stored conversations with the AI.")
+        .create_option(|sub| {
+            sub.name("last")
+                .description("Delete your most recent conversations")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("count")
+                        .description("How many conversations to delete")
+                        .kind(CommandOptionType::Integer)
+                        .min_int_value(1)
+                        .required(true)
+                })
+        })
+        .create_option(|sub| {
+            sub.name("all")
+                .description("Delete all of your conversations")
+                .kind(CommandOptionType::SubCommand)
+        })
+}
+
+/// Register /unregister
+pub fn register_unregister(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("unregister")
+        .description("Delete your profile, nicknames, personas and all conversations.")
+}
+
+/// What a confirmed button press deletes
+enum Deletion {
+    Conversations(Option<u64>),
+    User,
+}
+
+impl Deletion {
+    fn encode(&self) -> String {
+        match self {
+            Deletion::Conversations(Some(n)) => n.to_string(),
+            Deletion::Conversations(None) => "all".to_string(),
+            Deletion::User => "user".to_string(),
+        }
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        match value {
+            "all" => Some(Deletion::Conversations(None)),
+            "user" => Some(Deletion::User),
+            n => n.parse::<u64>().ok().map(|n| Deletion::Conversations(Some(n))),
+        }
+    }
+
+    fn question(&self) -> String {
+        match self {
+            Deletion::Conversations(Some(1)) => "Delete your most recent conversation?".to_string(),
+            Deletion::Conversations(Some(n)) => format!("Delete your {} most recent conversations?", n),
+            Deletion::Conversations(None) => "Delete **all** of your conversations?".to_string(),
+            Deletion::User => {
+                "Delete your profile, nicknames, the personas you created and **all** conversations? \
+                 You will need to run /setup-bot again to chat."
+                    .to_string()
+            }
+        }
+    }
+}
+
+/// Handle /forget
+pub async fn handle_forget(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let deletion = match command.data.options.first() {
+        Some(sub) if sub.name == "last" => {
+            let count = sub
+                .options
+                .first()
+                .and_then(|opt| opt.value.as_ref())
+                .and_then(|val| val.as_u64())
+                .unwrap_or(1)
+                .max(1);
+            Deletion::Conversations(Some(count))
+        }
+        Some(sub) if sub.name == "all" => Deletion::Conversations(None),
+        _ => return,
+    };
+
+    ask_confirmation(ctx, command, deletion).await;
+}
+
+/// Handle /unregister
+pub async fn handle_unregister(ctx: &Context, command: &ApplicationCommandInteraction) {
+    ask_confirmation(ctx, command, Deletion::User).await;
+}
+
+async fn ask_confirmation(ctx: &Context, command: &ApplicationCommandInteraction, deletion: Deletion) {
+    let user_id = command.user.id.0;
+    let confirm_id = format!("{}:confirm:{}:{}", CUSTOM_ID_PREFIX, user_id, deletion.encode());
+    let cancel_id = format!("{}:cancel:{}", CUSTOM_ID_PREFIX, user_id);
+
+    let result = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.content(format!("⚠️ {} This cannot be undone.", deletion.question()))
+                        .ephemeral(true)
+                        .components(|c| {
+                            c.create_action_row(|row| {
+                                row.create_button(|b| {
+                                    b.custom_id(confirm_id).label("Delete").style(ButtonStyle::Danger)
+                                })
+                                .create_button(|b| {
+                                    b.custom_id(cancel_id).label("Cancel").style(ButtonStyle::Secondary)
+                                })
+                            })
+                        })
+                })
+        })
+        .await;
+
+    if let Err(e) = result {
+        eprintln!("[ERROR] Failed to send deletion confirmation: {:?}", e);
+    }
+}
+
+/// Handle the Delete / Cancel buttons
+pub async fn handle_component(ctx: &Context, component: &MessageComponentInteraction, store: &dyn Store) {
+    let parts: Vec<&str> = component.data.custom_id.split(':').collect();
+    let (action, target_id, deletion) = match parts.as_slice() {
+        [_, action, target, rest @ ..] => (
+            *action,
+            target.parse::<u64>().unwrap_or(0),
+            rest.first().and_then(|value| Deletion::decode(value)),
+        ),
+        _ => return,
+    };
+
+    // The prompt is ephemeral, but never act on someone else's behalf
+    if target_id != component.user.id.0 {
+        println!("[LOG] User {} pressed the deletion prompt of {}", component.user.id, target_id);
+        let result = component
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| d.content(NOT_ALLOWED).ephemeral(true))
+            })
+            .await;
+        if let Err(e) = result {
+            eprintln!("[ERROR] Failed to refuse deletion prompt: {:?}", e);
+        }
+        return;
+    }
+
+    let content = match (action, deletion) {
+        ("confirm", Some(deletion)) => delete(store, target_id, deletion).await,
+        _ => "Cancelled, nothing was deleted.".to_string(),
+    };
+
+    let _ = component
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.content(content).components(|c| c))
+        })
+        .await;
+}
+
+/// Perform the deletion, write the audit record and describe the outcome
+async fn delete(store: &dyn Store, discord_id: u64, deletion: Deletion) -> String {
+    let (action, result) = match deletion {
+        Deletion::Conversations(count) => (
+            "forget",
+            store.delete_conversations(discord_id, count).await.map(|deleted| {
+                (
+                    format!("requested={} deleted={}", deletion.encode(), deleted),
+                    format!("🗑️ Deleted {} conversation(s).", deleted),
+                )
+            }),
+        ),
+        Deletion::User => (
+            "unregister",
+            store.delete_user(discord_id).await.map(|existed| {
+                let reply = if existed {
+                    "🗑️ Your profile, personas and conversations have been deleted."
+                } else {
+                    "You were not registered; any leftover data has been deleted."
+                };
+                (format!("existed={}", existed), reply.to_string())
+            }),
+        ),
+    };
+
+    let (detail, reply) = match result {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            return "❌ Failed to delete your data. Please try again later.".to_string();
+        }
+    };
+
+    println!("[LOG] User {} ran {} ({})", discord_id, action, detail);
+
+    let entry = AuditEntry {
+        actor_id: discord_id.to_string(),
+        target_id: discord_id.to_string(),
+        action: action.to_string(),
+        detail,
+        timestamp: Utc::now().timestamp(),
+    };
+    if let Err(e) = store.record_audit(entry).await {
+        eprintln!("[ERROR] {}", e);
+    }
+
+    reply
+}
@@ -0,0 +1,412 @@
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::component::{ActionRowComponent, ButtonStyle, InputTextStyle};
+use serenity::model::application::interaction::{
+    application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+    message_component::MessageComponentInteraction,
+    modal::ModalSubmitInteraction,
+    InteractionResponseType,
+};
+use serenity::prelude::*;
+use serenity::model::guild::Member;
+use crate::config::DiscordConfig;
+use crate::permissions::is_bot_admin;
+use crate::db::{ConversationQuery, Store};
+use crate::formatting::shorten;
+
+/// Conversations shown per page
+const PAGE_SIZE: u64 = 5;
+
+/// Each prompt/response is cut to this many characters so a page fits in one embed
+const ENTRY_LIMIT: usize = 300;
+
+/// Prefix of every custom_id this module owns
+pub const CUSTOM_ID_PREFIX: &str = "history";
+
+/// Shown when someone else's buttons or jump form are used
+const NOT_ALLOWED: &str = "Only the owner or a bot admin can page through this history.";
+
+/// Register /history
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("history")
+        .description("Browse your stored conversations with the AI.")
+        .create_option(|opt| {
+            opt.name("user")
+                .description("Whose history to show (bot admins only)")
+                .kind(CommandOptionType::User)
+                .required(false)
+        })
+        .create_option(|opt| {
+            opt.name("page")
+                .description("Page to open")
+                .kind(CommandOptionType::Integer)
+                .min_int_value(1)
+                .required(false)
+        })
+        .create_option(|opt| {
+            opt.name("public")
+                .description("Show the history to everyone in the channel")
+                .kind(CommandOptionType::Boolean)
+                .required(false)
+        })
+}
+
+/// Render one page as an embed plus its navigation buttons
+async fn render_page(
+    store: &dyn Store,
+    target_id: u64,
+    page: u64,
+) -> Result<(CreateEmbed, CreateComponents), String> {
+    let query = ConversationQuery::user(target_id);
+    let total = store.count_conversations(&query).await?;
+    let (page, pages) = clamp_page(page, total);
+
+    let conversations = store
+        .conversation_page(&query, (page - 1) * PAGE_SIZE, PAGE_SIZE)
+        .await?;
+
+    let mut embed = CreateEmbed::default();
+    embed.title("Conversation history");
+    if conversations.is_empty() {
+        embed.description(format!("<@{}> has no stored conversations.", target_id));
+    } else {
+        let entries: Vec<String> = conversations
+            .iter()
+            .map(|c| {
+                format!(
+                    "<t:{}:f>\n**Prompt:** {}\n**Response:** {}",
+                    c.timestamp,
+                    shorten(c.prompt.trim(), ENTRY_LIMIT),
+                    shorten(c.response.trim(), ENTRY_LIMIT)
+                )
+            })
+            .collect();
+        embed.description(format!("<@{}>\n\n{}", target_id, entries.join("\n\n")));
+    }
+    embed.footer(|f| f.text(format!("Page {} of {} • {} conversations", page, pages, total)));
+
+    let mut components = CreateComponents::default();
+    components.create_action_row(|row| {
+        row.create_button(|b| {
+            b.custom_id(format!("{}:page:{}:{}", CUSTOM_ID_PREFIX, target_id, page.saturating_sub(1)))
+                .label("Previous")
+                .style(ButtonStyle::Secondary)
+                .disabled(page <= 1)
+        })
+        .create_button(|b| {
+            b.custom_id(format!("{}:page:{}:{}", CUSTOM_ID_PREFIX, target_id, page + 1))
+                .label("Next")
+                .style(ButtonStyle::Secondary)
+                .disabled(page >= pages)
+        })
+        .create_button(|b| {
+            b.custom_id(format!("{}:jump:{}", CUSTOM_ID_PREFIX, target_id))
+                .label("Jump…")
+                .style(ButtonStyle::Primary)
+                .disabled(pages <= 1)
+        })
+    });
+
+    Ok((embed, components))
+}
+
+/// The requested page moved into range, and the page count; an empty history still has one page
+fn clamp_page(page: u64, total: u64) -> (u64, u64) {
+    let pages = total.div_ceil(PAGE_SIZE).max(1);
+    (page.clamp(1, pages), pages)
+}
+
+/// Handle /history
+pub async fn handle_history(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    store: &dyn Store,
+    config: &DiscordConfig,
+) {
+    let mut target_id = command.user.id.0;
+    let mut page = 1;
+    let mut public = false;
+
+    for opt in &command.data.options {
+        match (opt.name.as_str(), opt.resolved.as_ref()) {
+            ("user", Some(CommandDataOptionValue::User(user, _))) => target_id = user.id.0,
+            ("page", Some(CommandDataOptionValue::Integer(n))) => page = (*n).max(1) as u64,
+            ("public", Some(CommandDataOptionValue::Boolean(b))) => public = *b,
+            _ => {}
+        }
+    }
+
+    if !may_browse(command.user.id.0, command.member.as_ref(), target_id, config) {
+        println!("[LOG] User {} was denied /history for {}", command.user.id, target_id);
+        let _ = command
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.content("Only bot admins can view other users' history.").ephemeral(true)
+                    })
+            })
+            .await;
+        return;
+    }
+
+    let result = match render_page(store, target_id, page).await {
+        Ok((embed, components)) => {
+            command
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.set_embed(embed).set_components(components).ephemeral(!public)
+                        })
+                })
+                .await
+        }
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            command
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.content("Failed to load your history.").ephemeral(true)
+                        })
+                })
+                .await
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("[ERROR] Failed to send /history: {:?}", e);
+    }
+}
+
+/// Handle the Previous / Next / Jump buttons
+pub async fn handle_component(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn Store,
+    config: &DiscordConfig,
+) {
+    let parts: Vec<&str> = component.data.custom_id.split(':').collect();
+    let (action, target_id, page) = match parts.as_slice() {
+        [_, action, target, rest @ ..] => (
+            *action,
+            target.parse::<u64>().unwrap_or(0),
+            rest.first().and_then(|p| p.parse::<u64>().ok()).unwrap_or(1),
+        ),
+        _ => return,
+    };
+
+    // Public history messages can be clicked by anyone
+    if !may_browse(component.user.id.0, component.member.as_ref(), target_id, config) {
+        let result = component
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| d.content(NOT_ALLOWED).ephemeral(true))
+            })
+            .await;
+        if let Err(e) = result {
+            eprintln!("[ERROR] Failed to refuse history paging: {:?}", e);
+        }
+        return;
+    }
+
+    if action == "jump" {
+        let result = component
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::Modal).interaction_response_data(|d| {
+                    d.custom_id(format!("{}:jump:{}", CUSTOM_ID_PREFIX, target_id))
+                        .title("Jump to page")
+                        .components(|c| {
+                            c.create_action_row(|row| {
+                                row.create_input_text(|t| {
+                                    t.custom_id("page")
+                                        .label("Page number")
+                                        .style(InputTextStyle::Short)
+                                        .min_length(1)
+                                        .max_length(6)
+                                        .required(true)
+                                })
+                            })
+                        })
+                })
+            })
+            .await;
+        if let Err(e) = result {
+            eprintln!("[ERROR] Failed to open the jump to page form: {:?}", e);
+        }
+        return;
+    }
+
+    match render_page(store, target_id, page).await {
+        Ok((embed, components)) => {
+            let result = component
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| d.set_embed(embed).set_components(components))
+                })
+                .await;
+            if let Err(e) = result {
+                eprintln!("[ERROR] Failed to update /history page: {:?}", e);
+            }
+        }
+        Err(e) => eprintln!("[ERROR] {}", e),
+    }
+}
+
+/// Handle the "Jump to page" modal
+pub async fn handle_modal(
+    ctx: &Context,
+    modal: &ModalSubmitInteraction,
+    store: &dyn Store,
+    config: &DiscordConfig,
+) {
+    let target_id = modal
+        .data
+        .custom_id
+        .rsplit(':')
+        .next()
+        .and_then(|id| id.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    // The custom_id comes back from the client, so check it like the buttons do
+    if !may_browse(modal.user.id.0, modal.member.as_ref(), target_id, config) {
+        let result = modal
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| d.content(NOT_ALLOWED).ephemeral(true))
+            })
+            .await;
+        if let Err(e) = result {
+            eprintln!("[ERROR] Failed to refuse history paging: {:?}", e);
+        }
+        return;
+    }
+
+    let page = modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|c| match c {
+            ActionRowComponent::InputText(input) if input.custom_id == "page" => {
+                input.value.trim().parse::<u64>().ok()
+            }
+            _ => None,
+        })
+        .unwrap_or(1);
+
+    match render_page(store, target_id, page).await {
+        Ok((embed, components)) => {
+            let result = modal
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| d.set_embed(embed).set_components(components))
+                })
+                .await;
+            if let Err(e) = result {
+                eprintln!("[ERROR] Failed to jump to /history page {}: {:?}", page, e);
+            }
+        }
+        Err(e) => eprintln!("[ERROR] {}", e),
+    }
+}
+
+/// Whether the user may page through `target_id`'s history: their own, or anyone's for bot admins.
+/// History spans every guild, so Manage Server in the current one is not enough.
+fn may_browse(user_id: u64, member: Option<&Member>, target_id: u64, config: &DiscordConfig) -> bool {
+    user_id == target_id || is_bot_admin(member, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryStore;
+    use crate::db::user::Conversation;
+    use crate::db::ConversationStore;
+
+    fn member(roles: &[u64], permissions: u64) -> Member {
+        serde_json::from_value(serde_json::json!({
+            "guild_id": "9",
+            "deaf": false,
+            "mute": false,
+            "joined_at": null,
+            "roles": roles.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+            "permissions": permissions.to_string(),
+            "user": { "id": "1", "username": "someone", "discriminator": "0001", "avatar": null },
+        }))
+        .unwrap()
+    }
+
+    fn config() -> DiscordConfig {
+        DiscordConfig {
+            admin_role_ids: vec![500],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn owners_and_bot_admins_may_browse() {
+        assert!(may_browse(1, None, 1, &config()));
+        assert!(!may_browse(1, None, 2, &config()));
+        assert!(may_browse(1, Some(&member(&[500], 0)), 2, &config()));
+        assert!(!may_browse(1, Some(&member(&[400], 0)), 2, &config()));
+    }
+
+    #[test]
+    fn guild_admins_may_not_browse_others() {
+        // Manage Server (0x20) and Administrator (0x8) only cover one guild
+        assert!(!may_browse(1, Some(&member(&[], 0x20)), 2, &config()));
+        assert!(!may_browse(1, Some(&member(&[], 0x8)), 2, &config()));
+    }
+
+    #[test]
+    fn pages_are_clamped() {
+        assert_eq!(clamp_page(1, 0), (1, 1));
+        assert_eq!(clamp_page(0, 12), (1, 3));
+        assert_eq!(clamp_page(2, 12), (2, 3));
+        assert_eq!(clamp_page(9, 12), (3, 3));
+        assert_eq!(clamp_page(2, 10), (2, 2));
+    }
+
+    fn button(components: &CreateComponents, index: usize) -> &serde_json::Value {
+        &components.0[0]["components"][index]
+    }
+
+    #[tokio::test]
+    async fn buttons_follow_the_page() {
+        let store = MemoryStore::default();
+        for n in 1..=12 {
+            let turn = Conversation {
+                prompt: format!("prompt {}", n),
+                response: format!("response {}", n),
+                timestamp: n,
+                channel_id: None,
+                session_id: None,
+            };
+            store.push_conversation(7, turn).await.unwrap();
+        }
+
+        let (embed, components) = render_page(&store, 7, 1).await.unwrap();
+        assert_eq!(embed.0["footer"]["text"], "Page 1 of 3 • 12 conversations");
+        assert_eq!(button(&components, 0)["disabled"], true);
+        assert_eq!(button(&components, 1)["custom_id"], "history:page:7:2");
+        assert_eq!(button(&components, 1)["disabled"], false);
+
+        // Past the end lands on the last page, which holds the two oldest turns
+        let (embed, components) = render_page(&store, 7, 10).await.unwrap();
+        assert_eq!(embed.0["footer"]["text"], "Page 3 of 3 • 12 conversations");
+        let description = embed.0["description"].as_str().unwrap();
+        assert!(description.contains("prompt 2") && description.contains("prompt 1"));
+        assert!(!description.contains("prompt 3"));
+        assert_eq!(button(&components, 0)["custom_id"], "history:page:7:2");
+        assert_eq!(button(&components, 1)["disabled"], true);
+    }
+
+    #[tokio::test]
+    async fn empty_history_has_one_page() {
+        let (embed, components) = render_page(&MemoryStore::default(), 7, 3).await.unwrap();
+        assert_eq!(embed.0["footer"]["text"], "Page 1 of 1 • 0 conversations");
+        assert_eq!(button(&components, 2)["disabled"], true);
+    }
+}
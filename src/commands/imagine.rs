@@ -0,0 +1,141 @@
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::channel::AttachmentType;
+use serenity::prelude::*;
+use std::borrow::Cow;
+use crate::backend::image::{ImageClient, ImageRequest};
+use crate::config::ImageConfig;
+use crate::formatting::shorten;
+
+/// Sizes offered in the `size` option, as "WIDTHxHEIGHT"
+const SIZES: &[&str] = &["512x512", "768x768", "512x768", "768x512"];
+
+/// Longest prompt echoed above the image, leaving room in Discord's 2000 character limit
+const CAPTION_PROMPT_LEN: usize = 1900;
+
+/// Register /imagine, offering up to `max_steps` diffusion steps
+pub fn register_commands(command: &mut CreateApplicationCommand, max_steps: u32) -> &mut CreateApplicationCommand {
+    command
+        .name("imagine")
+        .description("Generate an image from a text prompt.")
+        .create_option(|opt| {
+            opt.name("prompt")
+                .description("What to draw")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+        .create_option(|opt| {
+            opt.name("negative")
+                .description("What to avoid in the image")
+                .kind(CommandOptionType::String)
+                .required(false)
+        })
+        .create_option(|opt| {
+            opt.name("steps")
+                .description("Number of diffusion steps")
+                .kind(CommandOptionType::Integer)
+                .min_int_value(1)
+                .max_int_value(max_steps)
+                .required(false)
+        })
+        .create_option(|opt| {
+            opt.name("size")
+                .description("Image size")
+                .kind(CommandOptionType::String)
+                .required(false);
+            for size in SIZES {
+                opt.add_string_choice(size, size);
+            }
+            opt
+        })
+}
+
+/// Handle /imagine
+pub async fn handle_imagine(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    image_client: &ImageClient,
+    config: &ImageConfig,
+) {
+    let option = |name: &str| {
+        command
+            .data
+            .options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| opt.value.as_ref())
+    };
+
+    let prompt = option("prompt").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let negative_prompt = option("negative").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let steps = option("steps")
+        .and_then(|v| v.as_u64())
+        .map(|s| s as u32)
+        .unwrap_or(config.default_steps)
+        .clamp(1, config.max_steps);
+    let (width, height) = option("size")
+        .and_then(|v| v.as_str())
+        .and_then(parse_size)
+        .unwrap_or((512, 512));
+
+    // Generation takes far longer than the 3 second interaction deadline
+    if let Err(e) = command.defer(&ctx.http).await {
+        eprintln!("[ERROR] Failed to defer /imagine: {:?}", e);
+        return;
+    }
+
+    if !image_client.health().await {
+        let _ = command
+            .edit_original_interaction_response(&ctx.http, |r| {
+                r.content("⚠️ The image server is offline. Please try again later.")
+            })
+            .await;
+        return;
+    }
+
+    let request = ImageRequest {
+        prompt: prompt.clone(),
+        negative_prompt,
+        steps,
+        width,
+        height,
+    };
+
+    println!(
+        "[LOG] User {} requested image ({}x{}, {} steps): {}",
+        command.user.id, width, height, steps, prompt
+    );
+
+    match image_client.generate(&request).await {
+        Ok(png) => {
+            let result = command
+                .create_followup_message(&ctx.http, |f| {
+                    f.content(format!("🎨 **{}**", shorten(&prompt, CAPTION_PROMPT_LEN)))
+                        .allowed_mentions(|m| m.empty_parse())
+                        .add_file(AttachmentType::Bytes {
+                            data: Cow::from(png),
+                            filename: "imagine.png".to_string(),
+                        })
+                })
+                .await;
+            if let Err(e) = result {
+                eprintln!("[ERROR] Failed to upload generated image: {:?}", e);
+            }
+        }
+        Err(e) => {
+            eprintln!("[ERROR] Image generation failed: {}", e);
+            let _ = command
+                .edit_original_interaction_response(&ctx.http, |r| {
+                    r.content("❌ Image generation failed.")
+                })
+                .await;
+        }
+    }
+}
+
+/// Parse "WIDTHxHEIGHT"
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
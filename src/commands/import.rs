@@ -0,0 +1,109 @@
+use chrono::Utc;
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::prelude::*;
+use crate::db::audit::AuditEntry;
+use crate::db::Store;
+use crate::export;
+
+/// Largest attachment we are willing to download and parse
+const MAX_IMPORT_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Most turns a single import may add
+const MAX_IMPORT_TURNS: usize = 5000;
+
+/// Register /import
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("import")
+        .description("Add conversations from an /export file (JSON) or a ShareGPT file to your history.")
+        .create_option(|opt| {
+            opt.name("file")
+                .description("JSON or JSONL file")
+                .kind(CommandOptionType::Attachment)
+                .required(true)
+        })
+}
+
+/// Handle /import
+pub async fn handle_import(ctx: &Context, command: &ApplicationCommandInteraction, store: &dyn Store) {
+    if let Err(e) = command.defer_ephemeral(&ctx.http).await {
+        eprintln!("[ERROR] Failed to defer /import: {:?}", e);
+        return;
+    }
+
+    let reply = import(command, store).await;
+    let _ = command
+        .edit_original_interaction_response(&ctx.http, |r| r.content(reply))
+        .await;
+}
+
+async fn import(command: &ApplicationCommandInteraction, store: &dyn Store) -> String {
+    let discord_id = command.user.id.0;
+
+    let attachment = match command.data.options.first().and_then(|opt| opt.resolved.as_ref()) {
+        Some(CommandDataOptionValue::Attachment(attachment)) => attachment,
+        _ => return "⚠️ Please attach a file.".to_string(),
+    };
+
+    match store.find_user(discord_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return "⚠️ You are not registered yet. Run /setup-bot first.".to_string(),
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            return "❌ Failed to look up your profile.".to_string();
+        }
+    }
+
+    if attachment.size > MAX_IMPORT_BYTES {
+        return format!("⚠️ The file is too large (max {} MB).", MAX_IMPORT_BYTES / 1024 / 1024);
+    }
+
+    let bytes = match attachment.download().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("[ERROR] Failed to download import attachment: {:?}", e);
+            return "❌ Failed to download the file.".to_string();
+        }
+    };
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(_) => return "⚠️ The file is not UTF-8 text.".to_string(),
+    };
+
+    let conversations = match export::parse_import(&text, Utc::now().timestamp()) {
+        Ok(conversations) => conversations,
+        Err(e) => return format!("⚠️ Could not import `{}`: {}", attachment.filename, e),
+    };
+    if conversations.len() > MAX_IMPORT_TURNS {
+        return format!(
+            "⚠️ The file has {} turns; at most {} can be imported at once.",
+            conversations.len(),
+            MAX_IMPORT_TURNS
+        );
+    }
+
+    let count = conversations.len();
+    if let Err(e) = store.push_conversations(discord_id, conversations).await {
+        eprintln!("[ERROR] {}", e);
+        return "❌ Failed to save the imported conversations.".to_string();
+    }
+
+    println!("[LOG] User {} imported {} conversation(s) from {}", discord_id, count, attachment.filename);
+
+    let entry = AuditEntry {
+        actor_id: discord_id.to_string(),
+        target_id: discord_id.to_string(),
+        action: "import".to_string(),
+        detail: format!("file={} imported={}", attachment.filename, count),
+        timestamp: Utc::now().timestamp(),
+    };
+    if let Err(e) = store.record_audit(entry).await {
+        eprintln!("[ERROR] {}", e);
+    }
+
+    format!("📥 Imported {} conversation(s) into your history.", count)
+}
@@ -1,2 +1,13 @@
+pub mod ai_channel;
+pub mod channel_persona;
+pub mod chatbot_access;
+pub mod chatbot_logs;
+pub mod export;
+pub mod forget;
+pub mod history;
+pub mod imagine;
+pub mod import;
+pub mod nickname;
+pub mod persona;
 pub mod setup_bot;
-pub mod start_chatbot;
\ No newline at end of file
+pub mod start_chatbot;
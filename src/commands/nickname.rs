@@ -0,0 +1,155 @@
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::{
+    application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+    InteractionResponseType,
+};
+use serenity::prelude::*;
+use crate::db::Store;
+use crate::nickname;
+
+/// Register /nickname set|show|reset
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("nickname")
+        .description("View or change the nickname the AI calls you.")
+        .create_option(|sub| {
+            sub.name("set")
+                .description("Change your nickname")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("nickname")
+                        .description("New nickname")
+                        .kind(CommandOptionType::String)
+                        .min_length(nickname::MIN_LEN as u16)
+                        .max_length(nickname::MAX_LEN as u16)
+                        .required(true)
+                })
+                .create_sub_option(|opt| {
+                    opt.name("server_only")
+                        .description("Only use this nickname in this server")
+                        .kind(CommandOptionType::Boolean)
+                        .required(false)
+                })
+        })
+        .create_option(|sub| {
+            sub.name("show")
+                .description("Show your nickname")
+                .kind(CommandOptionType::SubCommand)
+        })
+        .create_option(|sub| {
+            sub.name("reset")
+                .description("Drop this server's nickname, or reset your nickname to your Discord name")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|opt| {
+                    opt.name("server_only")
+                        .description("Only remove this server's nickname")
+                        .kind(CommandOptionType::Boolean)
+                        .required(false)
+                })
+        })
+}
+
+/// Handle /nickname
+pub async fn handle_nickname(ctx: &Context, command: &ApplicationCommandInteraction, store: &dyn Store) {
+    let reply = run(command, store).await;
+    let _ = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(reply).ephemeral(true))
+        })
+        .await;
+}
+
+async fn run(command: &ApplicationCommandInteraction, store: &dyn Store) -> String {
+    let discord_id = command.user.id.0;
+    let guild_id = command.guild_id.map(|id| id.0);
+
+    let subcommand = match command.data.options.first() {
+        Some(sub) => sub,
+        None => return "⚠️ Unknown subcommand.".to_string(),
+    };
+
+    let mut requested = None;
+    let mut server_only = false;
+    for opt in &subcommand.options {
+        match (opt.name.as_str(), opt.resolved.as_ref()) {
+            ("nickname", Some(CommandDataOptionValue::String(value))) => requested = Some(value.clone()),
+            ("server_only", Some(CommandDataOptionValue::Boolean(b))) => server_only = *b,
+            _ => {}
+        }
+    }
+
+    let user = match store.find_user(discord_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return "⚠️ You are not registered yet. Run /setup-bot first.".to_string(),
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            return "❌ Failed to look up your profile.".to_string();
+        }
+    };
+
+    let guild_id = match (server_only, guild_id) {
+        (true, None) => return "⚠️ Server nicknames can only be set inside a server.".to_string(),
+        (true, Some(id)) => Some(id),
+        (false, _) => None,
+    };
+
+    let result = match subcommand.name.as_str() {
+        "show" => {
+            let here = user.nickname_in(command.guild_id.map(|id| id.0));
+            return if here == user.nickname {
+                format!("Your nickname is **{}**.", user.nickname)
+            } else {
+                format!(
+                    "Your nickname is **{}** (in this server: **{}**).",
+                    user.nickname, here
+                )
+            };
+        }
+        "set" => {
+            let nickname = match nickname::validate(requested.as_deref().unwrap_or_default()) {
+                Ok(nickname) => nickname,
+                Err(reason) => return format!("⚠️ {}", reason),
+            };
+            let saved = match guild_id {
+                Some(guild_id) => store.set_guild_nickname(discord_id, guild_id, Some(nickname.clone())).await,
+                None => store.set_nickname(discord_id, nickname.clone()).await,
+            };
+            saved.map(|_| match guild_id {
+                Some(_) => format!("✅ In this server the AI will call you **{}**.", nickname),
+                None => format!("✅ The AI will now call you **{}**.", nickname),
+            })
+        }
+        "reset" => match guild_id {
+            Some(guild_id) => store
+                .set_guild_nickname(discord_id, guild_id, None)
+                .await
+                .map(|_| format!("✅ This server now uses your nickname **{}**.", user.nickname)),
+            None => {
+                // Discord usernames allow characters we don't, e.g. underscores
+                let fallback = nickname::validate(&command.user.name.replace('_', " "))
+                    .unwrap_or_else(|_| "friend".to_string());
+                store
+                    .set_nickname(discord_id, fallback.clone())
+                    .await
+                    .map(|_| format!("✅ Your nickname was reset to **{}**.", fallback))
+            }
+        },
+        _ => return "⚠️ Unknown subcommand.".to_string(),
+    };
+
+    match result {
+        Ok(reply) => {
+            println!(
+                "[LOG] User {} ran /nickname {} (guild: {:?})",
+                discord_id, subcommand.name, guild_id
+            );
+            reply
+        }
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            "❌ Failed to update your nickname.".to_string()
+        }
+    }
+}
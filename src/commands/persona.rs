@@ -0,0 +1,317 @@
+use chrono::Utc;
+use serenity::builder::{CreateApplicationCommand, CreateApplicationCommandOption, CreateEmbed};
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::{
+    application_command::{ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue},
+    InteractionResponseType,
+};
+use serenity::prelude::*;
+use crate::config::DiscordConfig;
+use crate::db::persona::Persona;
+use crate::db::Store;
+use crate::formatting::shorten;
+use crate::permissions::is_bot_admin;
+
+const NAME_MAX_LEN: usize = 32;
+const PROMPT_MAX_LEN: u16 = 2000;
+const GREETING_MAX_LEN: u16 = 500;
+
+/// Personas one user may create, so the list stays readable
+const MAX_PER_OWNER: usize = 10;
+
+/// Embeds hold at most 25 fields
+const LIST_LIMIT: usize = 25;
+
+/// Register /persona create|edit|list|use
+pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("persona")
+        .description("Create personas and choose which one the AI plays for you.")
+        .create_option(|sub| {
+            sub.name("create")
+                .description("Create a new persona")
+                .kind(CommandOptionType::SubCommand);
+            name_option(sub, true);
+            persona_options(sub, true)
+        })
+        .create_option(|sub| {
+            sub.name("edit")
+                .description("Change a persona you created")
+                .kind(CommandOptionType::SubCommand);
+            name_option(sub, true);
+            persona_options(sub, false)
+        })
+        .create_option(|sub| {
+            sub.name("list")
+                .description("Show all personas")
+                .kind(CommandOptionType::SubCommand)
+        })
+        .create_option(|sub| {
+            sub.name("use")
+                .description("Switch the AI to a persona when it talks to you")
+                .kind(CommandOptionType::SubCommand);
+            name_option(sub, false)
+        })
+}
+
+fn name_option(sub: &mut CreateApplicationCommandOption, required: bool) -> &mut CreateApplicationCommandOption {
+    sub.create_sub_option(|opt| {
+        opt.name("name")
+            .description(if required { "Persona name" } else { "Persona name (leave out for the default)" })
+            .kind(CommandOptionType::String)
+            .min_length(2)
+            .max_length(NAME_MAX_LEN as u16)
+            .required(required)
+    })
+}
+
+fn persona_options(sub: &mut CreateApplicationCommandOption, create: bool) -> &mut CreateApplicationCommandOption {
+    sub.create_sub_option(|opt| {
+        opt.name("prompt")
+            .description("How the AI should behave as this persona")
+            .kind(CommandOptionType::String)
+            .max_length(PROMPT_MAX_LEN)
+            .required(create)
+    })
+    .create_sub_option(|opt| {
+        opt.name("temperature")
+            .description("Creativity from 0 to 2 (leave out for the bot default)")
+            .kind(CommandOptionType::Number)
+            .min_number_value(0.0)
+            .max_number_value(2.0)
+            .required(false)
+    })
+    .create_sub_option(|opt| {
+        opt.name("greeting")
+            .description("What the AI says when someone switches to this persona")
+            .kind(CommandOptionType::String)
+            .max_length(GREETING_MAX_LEN)
+            .required(false)
+    })
+    .create_sub_option(|opt| {
+        opt.name("avatar")
+            .description("Image URL for the persona")
+            .kind(CommandOptionType::String)
+            .required(false)
+    })
+}
+
+/// Persona names are lowercase letters, digits, `-` and `_`, like slash command names
+fn normalize_name(raw: &str) -> Result<String, String> {
+    let name = raw.trim().to_lowercase();
+    if name.chars().count() < 2 || name.chars().count() > NAME_MAX_LEN {
+        return Err(format!("Persona names must be 2 to {} characters long.", NAME_MAX_LEN));
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err("Persona names may only contain letters, digits, `-` and `_`.".to_string());
+    }
+    Ok(name)
+}
+
+/// Values given for create/edit; `None` leaves a field unchanged when editing
+#[derive(Default)]
+struct Fields {
+    name: Option<String>,
+    prompt: Option<String>,
+    temperature: Option<f32>,
+    greeting: Option<String>,
+    avatar: Option<String>,
+}
+
+impl Fields {
+    fn parse(options: &[CommandDataOption]) -> Fields {
+        let mut fields = Fields::default();
+        for opt in options {
+            match (opt.name.as_str(), opt.resolved.as_ref()) {
+                ("name", Some(CommandDataOptionValue::String(v))) => fields.name = Some(v.clone()),
+                ("prompt", Some(CommandDataOptionValue::String(v))) => fields.prompt = Some(v.trim().to_string()),
+                ("temperature", Some(CommandDataOptionValue::Number(v))) => fields.temperature = Some(*v as f32),
+                ("greeting", Some(CommandDataOptionValue::String(v))) => fields.greeting = Some(v.trim().to_string()),
+                ("avatar", Some(CommandDataOptionValue::String(v))) => fields.avatar = Some(v.trim().to_string()),
+                _ => {}
+            }
+        }
+        fields
+    }
+
+    /// Copy the given values onto `persona`, rejecting unusable ones
+    fn apply(self, persona: &mut Persona) -> Result<(), String> {
+        if let Some(prompt) = self.prompt {
+            if prompt.is_empty() {
+                return Err("The prompt can't be empty.".to_string());
+            }
+            persona.system_prompt = prompt;
+        }
+        if let Some(temperature) = self.temperature {
+            persona.temperature = Some(temperature);
+        }
+        if let Some(greeting) = self.greeting {
+            persona.greeting = Some(greeting).filter(|g| !g.is_empty());
+        }
+        if let Some(avatar) = self.avatar {
+            persona.avatar_url = match avatar.as_str() {
+                "" => None,
+                url => match reqwest::Url::parse(url) {
+                    Ok(parsed) if parsed.scheme() == "https" || parsed.scheme() == "http" => Some(avatar),
+                    _ => return Err("The avatar must be an http(s) image URL.".to_string()),
+                },
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Handle /persona
+pub async fn handle_persona(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    store: &dyn Store,
+    config: &DiscordConfig,
+) {
+    let (content, embed) = match run(command, store, config).await {
+        Ok(reply) => reply,
+        Err(e) => (e, None),
+    };
+    let result = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    if let Some(embed) = embed {
+                        d.set_embed(embed);
+                    }
+                    d.content(content).ephemeral(true)
+                })
+        })
+        .await;
+    if let Err(e) = result {
+        eprintln!("[ERROR] Failed to send /persona: {:?}", e);
+    }
+}
+
+async fn run(
+    command: &ApplicationCommandInteraction,
+    store: &dyn Store,
+    config: &DiscordConfig,
+) -> Result<(String, Option<CreateEmbed>), String> {
+    let discord_id = command.user.id.0;
+    let subcommand = command
+        .data
+        .options
+        .first()
+        .ok_or_else(|| "⚠️ Unknown subcommand.".to_string())?;
+    let fields = Fields::parse(&subcommand.options);
+    let failed = |e: String| {
+        eprintln!("[ERROR] {}", e);
+        "❌ Failed to update personas.".to_string()
+    };
+
+    let name = match fields.name.as_deref().map(normalize_name) {
+        Some(Ok(name)) => Some(name),
+        Some(Err(reason)) => return Err(format!("⚠️ {}", reason)),
+        None => None,
+    };
+
+    match (subcommand.name.as_str(), name) {
+        ("create", Some(name)) => {
+            let owned = store
+                .list_personas()
+                .await
+                .map_err(failed)?
+                .iter()
+                .filter(|p| p.owner_id == discord_id.to_string())
+                .count();
+            if owned >= MAX_PER_OWNER {
+                return Err(format!("⚠️ You can create at most {} personas.", MAX_PER_OWNER));
+            }
+
+            let mut persona = Persona {
+                name: name.clone(),
+                owner_id: discord_id.to_string(),
+                system_prompt: String::new(),
+                temperature: None,
+                greeting: None,
+                avatar_url: None,
+                created_at: Utc::now().timestamp(),
+            };
+            fields.apply(&mut persona).map_err(|reason| format!("⚠️ {}", reason))?;
+            if !store.create_persona(persona).await.map_err(failed)? {
+                return Err(format!("⚠️ A persona called **{}** already exists.", name));
+            }
+            println!("[LOG] User {} created persona '{}'", discord_id, name);
+            Ok((format!("✅ Created persona **{}**. Switch to it with `/persona use {}`.", name, name), None))
+        }
+        ("edit", Some(name)) => {
+            let mut persona = store
+                .get_persona(&name)
+                .await
+                .map_err(failed)?
+                .ok_or_else(|| format!("⚠️ There is no persona called **{}**.", name))?;
+            // Personas are shared by every guild, so a guild's own admins don't get to edit them
+            if persona.owner_id != discord_id.to_string() && !is_bot_admin(command.member.as_ref(), config) {
+                return Err("⚠️ Only the creator of a persona or a bot admin can edit it.".to_string());
+            }
+            fields.apply(&mut persona).map_err(|reason| format!("⚠️ {}", reason))?;
+            store.update_persona(persona).await.map_err(failed)?;
+            println!("[LOG] User {} edited persona '{}'", discord_id, name);
+            Ok((
+                format!(
+                    "✅ Updated persona **{}**. Channels using it keep the old version until an admin runs \
+                     `/channel-persona set` again.",
+                    name
+                ),
+                None,
+            ))
+        }
+        ("list", _) => {
+            let personas = store.list_personas().await.map_err(failed)?;
+            if personas.is_empty() {
+                return Ok(("No personas yet. Create one with `/persona create`.".to_string(), None));
+            }
+            let active = store
+                .find_user(discord_id)
+                .await
+                .map_err(failed)?
+                .and_then(|user| user.active_persona);
+
+            let mut embed = CreateEmbed::default();
+            embed.title("Personas");
+            for persona in personas.iter().take(LIST_LIMIT) {
+                let marker = if active.as_deref() == Some(persona.name.as_str()) { " ✅" } else { "" };
+                embed.field(
+                    format!("{}{}", persona.name, marker),
+                    format!("{}\nby <@{}>", shorten(&persona.system_prompt, 100), persona.owner_id),
+                    false,
+                );
+            }
+            embed.footer(|f| f.text(format!("{} persona(s)", personas.len())));
+            Ok((String::new(), Some(embed)))
+        }
+        ("use", None) => {
+            if !store.set_active_persona(discord_id, None).await.map_err(failed)? {
+                return Err("⚠️ You are not registered yet. Run /setup-bot first.".to_string());
+            }
+            println!("[LOG] User {} switched back to the default persona", discord_id);
+            Ok(("✅ The AI is back to its default personality.".to_string(), None))
+        }
+        ("use", Some(name)) => {
+            let persona = store
+                .get_persona(&name)
+                .await
+                .map_err(failed)?
+                .ok_or_else(|| format!("⚠️ There is no persona called **{}**.", name))?;
+            if !store.set_active_persona(discord_id, Some(name.clone())).await.map_err(failed)? {
+                return Err("⚠️ You are not registered yet. Run /setup-bot first.".to_string());
+            }
+            println!("[LOG] User {} switched to persona '{}'", discord_id, name);
+
+            let mut embed = CreateEmbed::default();
+            embed.title(&persona.name);
+            embed.description(persona.greeting.as_deref().unwrap_or("*The persona is ready.*"));
+            if let Some(avatar) = &persona.avatar_url {
+                embed.thumbnail(avatar);
+            }
+            Ok((format!("✅ The AI is now **{}** when it talks to you.", name), Some(embed)))
+        }
+        _ => Err("⚠️ Unknown subcommand.".to_string()),
+    }
+}
@@ -1,40 +1,23 @@
 use serenity::builder::CreateApplicationCommand;
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::Permissions;
 use serenity::prelude::*;
-use std::path::Path;
-use std::process::{Child, Command as StdCommand};
-use tokio::sync::Mutex;
-use once_cell::sync::Lazy;
-
-/// Global async-safe storage for the running chatbot process
-static CHATBOT_PROCESS: Lazy<Mutex<Option<Child>>> = Lazy::new(|| Mutex::new(None));
-
-/// Helper: start Python chatbot process
-fn start_ai_chatbot() -> Result<Child, String> {
-    let venv_path = "./venv";
-    let python_exe = if cfg!(windows) {
-        format!("{}/Scripts/python.exe", venv_path)
-    } else {
-        format!("{}/bin/python", venv_path)
-    };
-
-    let chatbot_script = "./ai_chatbot.py";
-
-    if !Path::new(chatbot_script).exists() {
-        return Err("ai_chatbot.py not found.".into());
-    }
-
-    StdCommand::new(python_exe)
-        .arg(chatbot_script)
-        .spawn()
-        .map_err(|e| format!("Failed to start chatbot: {}", e))
-}
+use std::sync::Arc;
+use std::time::Duration;
+use crate::backend::ChatBackend;
+use crate::config::ChatbotConfig;
+use crate::supervisor::{self, ChatbotState, SupervisorContext};
 
 /// Register /run-chatbot command
 pub fn register_commands(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
     command
         .name("run-chatbot")
         .description("Start the AI chatbot and launch the local Python server.")
+        // Hidden from regular members by default; the allowlist is enforced by the guard
+        // in interaction_create, and servers can widen visibility under Integrations
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
 }
 
 /// Register /stop-chatbot command
@@ -42,54 +25,69 @@ pub fn register_stop_commands(command: &mut CreateApplicationCommand) -> &mut Cr
     command
         .name("stop-chatbot")
         .description("Stop the AI chatbot and end the chat session.")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
 }
 
-/// Handle /run-chatbot
-pub async fn run_chatbot(ctx: &Context, command: &ApplicationCommandInteraction) {
-    let user_id = command.user.id;
+fn supervisor_context(ctx: &Context, config: &ChatbotConfig, backend: &Arc<dyn ChatBackend>) -> SupervisorContext {
+    SupervisorContext {
+        http: ctx.http.clone(),
+        config: config.clone(),
+        backend: backend.clone(),
+    }
+}
 
-    let mut chatbot = CHATBOT_PROCESS.lock().await;
-    if chatbot.is_some() {
-        let _ = command.create_interaction_response(&ctx.http, |r| {
-            r.kind(serenity::model::application::interaction::InteractionResponseType::ChannelMessageWithSource)
-             .interaction_response_data(|msg| msg.content("⚠️ Chatbot is already running."))
-        }).await;
+/// Handle /run-chatbot
+pub async fn run_chatbot(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    config: &ChatbotConfig,
+    backend: &Arc<dyn ChatBackend>,
+) {
+    // Loading the model takes far longer than the 3 second interaction deadline
+    if let Err(e) = command.defer(&ctx.http).await {
+        eprintln!("[ERROR] Failed to defer /run-chatbot: {:?}", e);
         return;
     }
 
-    match start_ai_chatbot() {
-        Ok(child) => {
-            *chatbot = Some(child);
-            let _ = command.create_interaction_response(&ctx.http, |r| {
-                r.kind(serenity::model::application::interaction::InteractionResponseType::ChannelMessageWithSource)
-                 .interaction_response_data(|msg| msg.content("✅ Chatbot started successfully."))
-            }).await;
-        }
-        Err(err) => {
-            let _ = command.create_interaction_response(&ctx.http, |r| {
-                r.kind(serenity::model::application::interaction::InteractionResponseType::ChannelMessageWithSource)
-                 .interaction_response_data(|msg| msg.content(format!("❌ {}", err)))
-            }).await;
+    let content = match supervisor::start(supervisor_context(ctx, config, backend)).await {
+        Ok(()) => {
+            let timeout = Duration::from_secs(config.ready_timeout_secs);
+            match supervisor::wait_until_ready(timeout).await {
+                Ok(()) => "✅ Chatbot is ready.".to_string(),
+                Err(ChatbotState::Crashed) => {
+                    "❌ Chatbot crashed while loading; it will be restarted automatically.".to_string()
+                }
+                Err(ChatbotState::Stopped) => "🛑 Chatbot was stopped before it became ready.".to_string(),
+                Err(state) => format!(
+                    "⚠️ Chatbot did not become ready within {}s (state: {}).",
+                    config.ready_timeout_secs, state
+                ),
+            }
         }
-    }
+        Err(err) => format!("⚠️ {} (state: {})", err, supervisor::current_state()),
+    };
+
+    let _ = command
+        .edit_original_interaction_response(&ctx.http, |r| r.content(content))
+        .await;
 }
 
 /// Handle /stop-chatbot
-pub async fn stop_chatbot(ctx: &Context, command: &ApplicationCommandInteraction) {
-    let user_id = command.user.id;
-
-    let mut chatbot = CHATBOT_PROCESS.lock().await;
-    if let Some(mut child) = chatbot.take() {
-        let _ = child.kill();
-        let _ = child.wait();
-        let _ = command.create_interaction_response(&ctx.http, |r| {
-            r.kind(serenity::model::application::interaction::InteractionResponseType::ChannelMessageWithSource)
-             .interaction_response_data(|msg| msg.content("🛑 Chatbot has been stopped."))
-        }).await;
+pub async fn stop_chatbot(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    config: &ChatbotConfig,
+    backend: &Arc<dyn ChatBackend>,
+) {
+    let content = if supervisor::stop(&supervisor_context(ctx, config, backend)).await {
+        "🛑 Chatbot has been stopped."
     } else {
-        let _ = command.create_interaction_response(&ctx.http, |r| {
-            r.kind(serenity::model::application::interaction::InteractionResponseType::ChannelMessageWithSource)
-             .interaction_response_data(|msg| msg.content("⚠️ Chatbot is not running."))
-        }).await;
-    }
+        "⚠️ Chatbot is not running."
+    };
+
+    let _ = command.create_interaction_response(&ctx.http, |r| {
+        r.kind(InteractionResponseType::ChannelMessageWithSource)
+         .interaction_response_data(|msg| msg.content(content))
+    }).await;
 }
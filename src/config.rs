@@ -0,0 +1,721 @@
+use serde::Deserialize;
+use crate::backend::template::PromptFormat;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Default location of the config file, relative to the working directory
+const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+
+/// Top-level bot configuration, loaded from TOML and then overridden by env vars
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub discord: DiscordConfig,
+    pub backend: BackendConfig,
+    pub responses: ResponseConfig,
+    pub postprocess: PostprocessConfig,
+    pub history: HistoryConfig,
+    pub threads: ThreadConfig,
+    pub onboarding: OnboardingConfig,
+    pub rate_limit: RateLimitConfig,
+    pub queue: QueueConfig,
+    pub image: ImageConfig,
+    pub chatbot: ChatbotConfig,
+    pub storage: StorageConfig,
+    pub mongo: MongoConfig,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DiscordConfig {
+    /// Bot token; usually left out of the file and supplied via DISCORD_TOKEN
+    pub token: String,
+    pub application_id: u64,
+    /// Guilds the slash commands are registered in
+    pub guild_ids: Vec<u64>,
+    /// Channels the AI answers in
+    pub ai_channel_ids: Vec<u64>,
+    /// Members with any of these roles count as bot admins in every guild
+    pub admin_role_ids: Vec<u64>,
+}
+
+/// Which model server protocol the bot speaks
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// The bundled ai_chatbot.py Flask server
+    #[default]
+    Flask,
+    /// OpenAI-compatible /v1/chat/completions (llama.cpp server, vLLM, LM Studio)
+    OpenAi,
+    /// Ollama's /api/chat
+    Ollama,
+    /// Raw /completion endpoint (llama.cpp server, ai_chatbot.py); the bot applies the prompt template
+    Completion,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct BackendConfig {
+    pub kind: BackendKind,
+    /// Base URL of the local chat server, e.g. http://127.0.0.1:5005
+    pub base_url: String,
+    /// Model name sent to OpenAI-compatible and Ollama servers
+    pub model: String,
+    /// Optional bearer token for OpenAI-compatible servers
+    pub api_key: Option<String>,
+    pub temperature: f32,
+    pub max_tokens: u32,
+    /// Stream tokens into the reply as they arrive; otherwise post the full response once ready
+    pub stream: bool,
+    /// Minimum delay between progressive edits of a streamed reply
+    pub stream_edit_interval_ms: u64,
+    /// Prompt template for the completion backend; guessed from `model` when unset
+    pub prompt_format: Option<PromptFormat>,
+    /// Give up if the server does not accept the connection within this long
+    pub connect_timeout_secs: u64,
+    /// Give up if the server sends nothing (headers or the next streamed chunk) for this long
+    pub read_timeout_secs: u64,
+}
+
+impl BackendConfig {
+    /// The configured prompt format, else one detected from the model name, else Llama-2
+    /// (the format of the bundled model)
+    pub fn resolved_prompt_format(&self) -> PromptFormat {
+        self.prompt_format
+            .or_else(|| PromptFormat::detect(&self.model))
+            .unwrap_or(PromptFormat::Llama2)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ResponseConfig {
+    /// Replies longer than this many characters are sent as a .md attachment instead of
+    /// being split across messages; 0 always splits
+    pub attach_over_chars: usize,
+}
+
+/// Filters applied to every AI response; see `postprocess::BUILTIN_FILTERS`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PostprocessConfig {
+    /// Filters that run by default, in this order. Rules left out only run where an
+    /// override enables them.
+    pub filters: Vec<String>,
+    pub rules: Vec<RegexRule>,
+    pub overrides: Vec<FilterOverride>,
+}
+
+/// A regex replacement applied to responses
+#[derive(Debug, Deserialize, Clone)]
+pub struct RegexRule {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+}
+
+/// Switches filters on or off for one guild, one model, or both
+#[derive(Debug, Deserialize, Clone)]
+pub struct FilterOverride {
+    pub guild_id: Option<u64>,
+    pub model: Option<String>,
+    #[serde(default)]
+    pub enable: Vec<String>,
+    #[serde(default)]
+    pub disable: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ImageConfig {
+    /// Base URL of the text-to-image service, e.g. http://127.0.0.1:5006
+    pub base_url: String,
+    /// Generation can take minutes on small GPUs
+    pub timeout_secs: u64,
+    pub default_steps: u32,
+    pub max_steps: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Maximum number of previous prompt/response pairs sent as context
+    pub max_turns: usize,
+    /// Approximate token budget for the history window (~4 chars per token)
+    pub max_tokens: usize,
+}
+
+/// Thread durations Discord accepts, in minutes
+pub const THREAD_ARCHIVE_MINUTES: [u16; 4] = [60, 1440, 4320, 10080];
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ThreadConfig {
+    /// Start a thread for each new message in an AI channel and answer there
+    pub enabled: bool,
+    /// Inactivity before Discord archives the thread; one of 60, 1440, 4320 or 10080
+    pub auto_archive_minutes: u16,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct OnboardingConfig {
+    /// Unfinished registrations are forgotten after this many seconds
+    pub expiry_secs: u64,
+}
+
+/// Token bucket per user: `capacity` requests at once, refilled at `per_minute`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub capacity: u32,
+    pub per_minute: u32,
+    /// Separate buckets per guild instead of one per user across all guilds
+    pub per_guild: bool,
+    /// Admins (see discord.admin_role_ids) are never limited
+    pub exempt_admins: bool,
+    /// Slash commands that draw from the same bucket as chat messages
+    pub commands: Vec<String>,
+    /// Different limits for members with these roles; the most generous one wins
+    pub roles: Vec<RoleRateLimit>,
+}
+
+/// Generation jobs waiting for the backend
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct QueueConfig {
+    /// Requests sent to the backend at the same time
+    pub workers: usize,
+    /// Waiting requests beyond this are turned away
+    pub max_length: usize,
+    /// A job still running after this long is dropped so it can't hold its worker forever
+    pub job_timeout_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoleRateLimit {
+    pub role_id: u64,
+    pub capacity: u32,
+    pub per_minute: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ChatbotConfig {
+    /// Python virtualenv used to launch the chat server
+    pub venv_path: String,
+    /// Chat server script started by /run-chatbot
+    pub script_path: String,
+    /// Channel that receives chatbot state changes (started, ready, crashed, ...)
+    pub admin_channel_id: Option<u64>,
+    /// How long /run-chatbot waits for the healthcheck before giving up
+    pub ready_timeout_secs: u64,
+    pub health_poll_secs: u64,
+    /// Consecutive crashes tolerated before the supervisor stops restarting
+    pub max_restarts: u32,
+    /// First restart delay; doubles on each consecutive crash up to max_backoff_secs
+    pub restart_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    /// File the process's stdout/stderr is copied to
+    pub log_path: String,
+    /// Rotate the log file once it exceeds this size
+    pub log_max_bytes: u64,
+    /// Rotated files to keep (chatbot.log.1, chatbot.log.2, ...)
+    pub log_keep_files: usize,
+    /// Lines kept in memory for /chatbot-logs
+    pub log_buffer_lines: usize,
+}
+
+/// Where users, conversations and guild settings are persisted
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    #[default]
+    Mongo,
+    /// Single-file database for small self-hosted installs
+    Sqlite,
+    /// Nothing persisted; for tests and throwaway runs
+    Memory,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub kind: StorageKind,
+    pub sqlite_path: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MongoConfig {
+    pub uri: String,
+    pub database: String,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self {
+            kind: BackendKind::Flask,
+            base_url: "http://127.0.0.1:5005".to_string(),
+            model: String::new(),
+            api_key: None,
+            temperature: 0.7,
+            max_tokens: 128,
+            stream: true,
+            stream_edit_interval_ms: 1200,
+            prompt_format: None,
+            connect_timeout_secs: 10,
+            read_timeout_secs: 300,
+        }
+    }
+}
+
+impl Default for ResponseConfig {
+    fn default() -> Self {
+        Self { attach_over_chars: 6000 }
+    }
+}
+
+impl Default for PostprocessConfig {
+    fn default() -> Self {
+        Self {
+            filters: crate::postprocess::BUILTIN_FILTERS.iter().map(|f| f.to_string()).collect(),
+            rules: Vec::new(),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://127.0.0.1:5006".to_string(),
+            timeout_secs: 300,
+            default_steps: 25,
+            max_steps: 60,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_turns: 6,
+            max_tokens: 1024,
+        }
+    }
+}
+
+impl Default for ThreadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            auto_archive_minutes: 60,
+        }
+    }
+}
+
+impl Default for OnboardingConfig {
+    fn default() -> Self {
+        Self { expiry_secs: 900 }
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            workers: 1,
+            max_length: 20,
+            job_timeout_secs: 600,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capacity: 3,
+            per_minute: 6,
+            per_guild: false,
+            exempt_admins: true,
+            commands: vec!["imagine".to_string()],
+            roles: Vec::new(),
+        }
+    }
+}
+
+impl Default for ChatbotConfig {
+    fn default() -> Self {
+        Self {
+            venv_path: "./venv".to_string(),
+            script_path: "./ai_chatbot.py".to_string(),
+            admin_channel_id: None,
+            ready_timeout_secs: 300,
+            health_poll_secs: 2,
+            max_restarts: 5,
+            restart_backoff_secs: 2,
+            max_backoff_secs: 60,
+            log_path: "./logs/chatbot.log".to_string(),
+            log_max_bytes: 5 * 1024 * 1024,
+            log_keep_files: 3,
+            log_buffer_lines: 1000,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            kind: StorageKind::Mongo,
+            sqlite_path: "./discord_bot.sqlite3".to_string(),
+        }
+    }
+}
+
+impl Default for MongoConfig {
+    fn default() -> Self {
+        Self {
+            uri: "mongodb://localhost:27017".to_string(),
+            database: "discord_bot".to_string(),
+        }
+    }
+}
+
+impl ChatbotConfig {
+    /// Path to the python executable inside the configured venv
+    pub fn python_exe(&self) -> String {
+        if cfg!(windows) {
+            format!("{}/Scripts/python.exe", self.venv_path)
+        } else {
+            format!("{}/bin/python", self.venv_path)
+        }
+    }
+}
+
+impl Config {
+    /// Load config from BOT_CONFIG (or ./config.toml), apply env overrides and validate.
+    ///
+    /// A missing default config file is fine (everything can come from env);
+    /// a missing file that was explicitly requested via BOT_CONFIG is an error.
+    pub fn load() -> Result<Config, String> {
+        let (path, explicit) = match env::var("BOT_CONFIG") {
+            Ok(p) => (p, true),
+            Err(_) => (DEFAULT_CONFIG_PATH.to_string(), false),
+        };
+
+        let mut config = if Path::new(&path).exists() {
+            let raw = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read config file '{}': {}", path, e))?;
+            toml::from_str::<Config>(&raw)
+                .map_err(|e| format!("Failed to parse config file '{}': {}", path, e))?
+        } else if explicit {
+            return Err(format!("Config file '{}' (from BOT_CONFIG) does not exist", path));
+        } else {
+            println!("[LOG] No config file at '{}', using defaults and environment", path);
+            Config::default()
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Override file values with environment variables where present
+    fn apply_env(&mut self) -> Result<(), String> {
+        self.apply_vars(|name| env::var(name).ok())
+    }
+
+    /// Override file values with the variables `var` finds, reporting every bad value at once
+    fn apply_vars(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let mut problems = Vec::new();
+        match var("APPLICATION_ID").map(|v| parse_id("APPLICATION_ID", &v)) {
+            Some(Ok(id)) => self.discord.application_id = id,
+            Some(Err(e)) => problems.push(e),
+            None => {}
+        }
+        match var("BOT_ADMIN_CHANNEL_ID").map(|v| parse_id("BOT_ADMIN_CHANNEL_ID", &v)) {
+            Some(Ok(id)) => self.chatbot.admin_channel_id = Some(id),
+            Some(Err(e)) => problems.push(e),
+            None => {}
+        }
+        let mut set_ids = |name: &str, target: &mut Vec<u64>| match var(name).map(|v| parse_id_list(name, &v)) {
+            Some(Ok(ids)) => *target = ids,
+            Some(Err(e)) => problems.push(e),
+            None => {}
+        };
+        set_ids("BOT_GUILD_IDS", &mut self.discord.guild_ids);
+        set_ids("BOT_AI_CHANNEL_IDS", &mut self.discord.ai_channel_ids);
+        set_ids("BOT_ADMIN_ROLE_IDS", &mut self.discord.admin_role_ids);
+
+        if let Some(token) = var("DISCORD_TOKEN") {
+            self.discord.token = token;
+        }
+        if let Some(kind) = var("BOT_BACKEND_KIND") {
+            match kind.trim().to_lowercase().as_str() {
+                "flask" => self.backend.kind = BackendKind::Flask,
+                "openai" => self.backend.kind = BackendKind::OpenAi,
+                "ollama" => self.backend.kind = BackendKind::Ollama,
+                "completion" => self.backend.kind = BackendKind::Completion,
+                other => problems.push(format!(
+                    "BOT_BACKEND_KIND must be flask, openai, ollama or completion, got '{}'",
+                    other
+                )),
+            }
+        }
+        if let Some(url) = var("BOT_BACKEND_URL") {
+            self.backend.base_url = url;
+        }
+        if let Some(url) = var("BOT_IMAGE_URL") {
+            self.image.base_url = url;
+        }
+        if let Some(model) = var("BOT_BACKEND_MODEL") {
+            self.backend.model = model;
+        }
+        if let Some(key) = var("BOT_BACKEND_API_KEY") {
+            self.backend.api_key = Some(key);
+        }
+        if let Some(path) = var("BOT_VENV_PATH") {
+            self.chatbot.venv_path = path;
+        }
+        if let Some(path) = var("BOT_CHATBOT_SCRIPT") {
+            self.chatbot.script_path = path;
+        }
+        if let Some(kind) = var("BOT_STORAGE") {
+            match kind.trim().to_lowercase().as_str() {
+                "mongo" => self.storage.kind = StorageKind::Mongo,
+                "sqlite" => self.storage.kind = StorageKind::Sqlite,
+                "memory" => self.storage.kind = StorageKind::Memory,
+                other => problems.push(format!("BOT_STORAGE must be mongo, sqlite or memory, got '{}'", other)),
+            }
+        }
+        if let Some(path) = var("BOT_SQLITE_PATH") {
+            self.storage.sqlite_path = path;
+        }
+        if let Some(uri) = var("MONGODB_URI") {
+            self.mongo.uri = uri;
+        }
+        if let Some(db) = var("MONGODB_DATABASE") {
+            self.mongo.database = db;
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid environment:\n  - {}", problems.join("\n  - ")))
+        }
+    }
+
+    /// Check the merged config, reporting every problem at once
+    fn validate(&mut self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        if self.discord.token.trim().is_empty() {
+            problems.push("discord.token is empty (set DISCORD_TOKEN)".to_string());
+        }
+        if self.discord.application_id == 0 {
+            problems.push("discord.application_id is missing (set APPLICATION_ID)".to_string());
+        }
+        if self.discord.guild_ids.is_empty() {
+            problems.push("discord.guild_ids must list at least one guild".to_string());
+        }
+        if self.discord.guild_ids.contains(&0)
+            || self.discord.ai_channel_ids.contains(&0)
+            || self.discord.admin_role_ids.contains(&0)
+        {
+            problems.push("discord ids must be non-zero".to_string());
+        }
+
+        check_base_url("backend.base_url", &mut self.backend.base_url, &mut problems);
+
+        if matches!(self.backend.kind, BackendKind::OpenAi | BackendKind::Ollama)
+            && self.backend.model.trim().is_empty()
+        {
+            problems.push("backend.model is required for openai and ollama backends".to_string());
+        }
+        if self.backend.stream_edit_interval_ms < 1000 {
+            problems.push("backend.stream_edit_interval_ms must be at least 1000 (Discord edit limits)".to_string());
+        }
+        if self.backend.connect_timeout_secs == 0 || self.backend.read_timeout_secs == 0 {
+            problems.push("backend.connect_timeout_secs and backend.read_timeout_secs must be at least 1".to_string());
+        }
+
+        if self.responses.attach_over_chars != 0
+            && self.responses.attach_over_chars < crate::formatting::MESSAGE_LIMIT
+        {
+            problems.push(format!(
+                "responses.attach_over_chars must be 0 or at least {}",
+                crate::formatting::MESSAGE_LIMIT
+            ));
+        }
+
+        if let Err(e) = crate::postprocess::Pipeline::new(&self.postprocess) {
+            problems.push(e);
+        }
+
+        if !THREAD_ARCHIVE_MINUTES.contains(&self.threads.auto_archive_minutes) {
+            problems.push("threads.auto_archive_minutes must be 60, 1440, 4320 or 10080".to_string());
+        }
+
+        if self.onboarding.expiry_secs == 0 {
+            problems.push("onboarding.expiry_secs must be greater than 0".to_string());
+        }
+
+        let role_limits = self.rate_limit.roles.iter().map(|r| (r.capacity, r.per_minute));
+        if std::iter::once((self.rate_limit.capacity, self.rate_limit.per_minute))
+            .chain(role_limits)
+            .any(|(capacity, per_minute)| capacity == 0 || per_minute == 0)
+        {
+            problems.push("rate_limit capacity and per_minute must be at least 1".to_string());
+        }
+
+        if self.queue.workers == 0 || self.queue.max_length == 0 || self.queue.job_timeout_secs == 0 {
+            problems.push("queue.workers, queue.max_length and queue.job_timeout_secs must be at least 1".to_string());
+        }
+
+        check_base_url("image.base_url", &mut self.image.base_url, &mut problems);
+        if self.image.default_steps == 0 || self.image.default_steps > self.image.max_steps {
+            problems.push("image.default_steps must be between 1 and image.max_steps".to_string());
+        }
+
+        if self.storage.kind == StorageKind::Mongo && self.mongo.database.trim().is_empty() {
+            problems.push("mongo.database is empty".to_string());
+        }
+
+        // Not fatal: deployments that run the model elsewhere never use /run-chatbot
+        if !Path::new(&self.chatbot.script_path).exists() {
+            println!(
+                "[WARN] chatbot.script_path '{}' does not exist; /run-chatbot will fail",
+                self.chatbot.script_path
+            );
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid configuration:\n  - {}", problems.join("\n  - ")))
+        }
+    }
+}
+
+/// Drop trailing slashes (paths are appended with a leading one) and require http(s)
+fn check_base_url(name: &str, url: &mut String, problems: &mut Vec<String>) {
+    *url = url.trim_end_matches('/').to_string();
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {}
+        Ok(_) => problems.push(format!("{} '{}' must be http or https", name, url)),
+        Err(e) => problems.push(format!("{} '{}' is not a valid URL: {}", name, url, e)),
+    }
+}
+
+fn parse_id(name: &str, value: &str) -> Result<u64, String> {
+    value
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("{} must be a valid u64, got '{}'", name, value))
+}
+
+fn parse_id_list(name: &str, value: &str) -> Result<Vec<u64>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| parse_id(name, s))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let map: HashMap<String, String> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| map.get(name).cloned()
+    }
+
+    fn valid() -> Config {
+        let mut config = Config::default();
+        config.discord.token = "token".to_string();
+        config.discord.application_id = 1;
+        config.discord.guild_ids = vec![10];
+        config
+    }
+
+    #[test]
+    fn env_overrides_file_values() {
+        let mut config = valid();
+        config
+            .apply_vars(vars(&[
+                ("DISCORD_TOKEN", "from-env"),
+                ("APPLICATION_ID", " 42 "),
+                ("BOT_GUILD_IDS", "1, 2,,3"),
+                ("BOT_ADMIN_CHANNEL_ID", "7"),
+                ("BOT_BACKEND_KIND", "Ollama"),
+                ("BOT_BACKEND_MODEL", "llama3"),
+                ("BOT_STORAGE", "sqlite"),
+            ]))
+            .unwrap();
+        assert_eq!(config.discord.token, "from-env");
+        assert_eq!(config.discord.application_id, 42);
+        assert_eq!(config.discord.guild_ids, vec![1, 2, 3]);
+        assert_eq!(config.chatbot.admin_channel_id, Some(7));
+        assert_eq!(config.backend.kind, BackendKind::Ollama);
+        assert_eq!(config.backend.model, "llama3");
+        assert_eq!(config.storage.kind, StorageKind::Sqlite);
+        // Unset variables leave the file's values alone
+        assert!(config.discord.ai_channel_ids.is_empty());
+    }
+
+    #[test]
+    fn env_reports_every_bad_value() {
+        let mut config = valid();
+        let err = config
+            .apply_vars(vars(&[
+                ("APPLICATION_ID", "abc"),
+                ("BOT_GUILD_IDS", "1,x"),
+                ("BOT_STORAGE", "redis"),
+                ("DISCORD_TOKEN", "still-applied"),
+            ]))
+            .unwrap_err();
+        assert!(err.contains("APPLICATION_ID must be a valid u64, got 'abc'"), "{}", err);
+        assert!(err.contains("BOT_GUILD_IDS must be a valid u64, got 'x'"), "{}", err);
+        assert!(err.contains("BOT_STORAGE must be mongo, sqlite or memory, got 'redis'"), "{}", err);
+        assert_eq!(config.discord.token, "still-applied");
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let mut config = Config::default();
+        config.backend.base_url = "ftp://example.com".to_string();
+        config.threads.auto_archive_minutes = 30;
+        let err = config.validate().unwrap_err();
+        for expected in [
+            "discord.token is empty",
+            "discord.application_id is missing",
+            "discord.guild_ids must list at least one guild",
+            "backend.base_url 'ftp://example.com' must be http or https",
+            "threads.auto_archive_minutes",
+        ] {
+            assert!(err.contains(expected), "missing '{}' in {}", expected, err);
+        }
+    }
+
+    #[test]
+    fn base_urls_are_normalised() {
+        let mut config = valid();
+        config.backend.base_url = "http://127.0.0.1:5005/".to_string();
+        config.image.base_url = "https://images.local//".to_string();
+        config.validate().unwrap();
+        assert_eq!(config.backend.base_url, "http://127.0.0.1:5005");
+        assert_eq!(config.image.base_url, "https://images.local");
+
+        config.image.base_url = "file:///tmp/images".to_string();
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("image.base_url 'file:///tmp/images' must be http or https"), "{}", err);
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// What a chatbot access grant applies to
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessKind {
+    Role,
+    User,
+}
+
+/// Permission for a role or user to run /run-chatbot and /stop-chatbot in one guild
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AccessGrant {
+    pub guild_id: String,
+    pub kind: AccessKind,
+    pub target_id: String,
+}
+
+impl AccessGrant {
+    pub fn new(guild_id: u64, kind: AccessKind, target_id: u64) -> Self {
+        Self {
+            guild_id: guild_id.to_string(),
+            kind,
+            target_id: target_id.to_string(),
+        }
+    }
+}
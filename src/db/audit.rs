@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// A record that something sensitive happened, e.g. a user deleting their data
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// User who performed the action
+    pub actor_id: String,
+    /// User whose data was affected
+    pub target_id: String,
+    /// Short machine-readable action name, e.g. "forget" or "unregister"
+    pub action: String,
+    pub detail: String,
+    pub timestamp: i64,
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use crate::db::persona::Persona;
+
+/// A channel where the AI always speaks as one persona, through a webhook the bot manages.
+///
+/// The persona's prompt, temperature and avatar are copied when the channel is bound, so
+/// later edits by the persona's creator don't change what the channel's webhook posts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChannelPersona {
+    pub guild_id: String,
+    pub channel_id: String,
+    /// Name of the bound [`Persona`], also the name replies are posted under
+    pub persona: String,
+    pub system_prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    /// The managed webhook, once created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_token: Option<String>,
+}
+
+impl ChannelPersona {
+    pub fn new(guild_id: u64, channel_id: u64, persona: &Persona) -> Self {
+        Self {
+            guild_id: guild_id.to_string(),
+            channel_id: channel_id.to_string(),
+            persona: persona.name.clone(),
+            system_prompt: persona.system_prompt.clone(),
+            temperature: persona.temperature,
+            avatar_url: persona.avatar_url.clone(),
+            webhook_id: None,
+            webhook_token: None,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Feature switches an admin can flip per guild
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GuildFeatures {
+    /// Answer messages in the AI channels at all
+    #[serde(default = "default_true")]
+    pub ai_chat: bool,
+}
+
+impl Default for GuildFeatures {
+    fn default() -> Self {
+        Self { ai_chat: true }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GuildSettings {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub guild_id: String,
+
+    /// Channels the AI listens in
+    #[serde(default)]
+    pub ai_channel_ids: Vec<String>,
+
+    /// Persona / system prompt sent with every chat request from this guild
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+
+    #[serde(default)]
+    pub features: GuildFeatures,
+}
+
+impl GuildSettings {
+    pub fn listens_in(&self, channel_id: u64) -> bool {
+        self.ai_channel_ids.contains(&channel_id.to_string())
+    }
+}
@@ -0,0 +1,394 @@
+use serenity::async_trait;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use crate::db::access::{AccessGrant, AccessKind};
+use crate::db::audit::AuditEntry;
+use crate::db::channel_persona::ChannelPersona;
+use crate::db::guild_settings::{GuildFeatures, GuildSettings};
+use crate::db::onboarding::OnboardingRecord;
+use crate::db::persona::Persona;
+use crate::db::thread::ChatThread;
+use crate::db::user::{Conversation, User};
+use crate::db::{
+    AccessStore, AuditStore, ChannelPersonaStore, ConversationQuery, ConversationStore, GuildSettingsStore,
+    OnboardingStore, PersonaStore, StoreResult, ThreadStore, UserStore,
+};
+
+/// Non-persistent storage, used by tests and for throwaway runs
+#[derive(Default)]
+pub struct MemoryStore {
+    users: Mutex<HashMap<u64, User>>,
+    /// (discord_id, conversation) in insertion order
+    conversations: Mutex<Vec<(u64, Conversation)>>,
+    guild_settings: Mutex<HashMap<u64, GuildSettings>>,
+    audit_log: Mutex<Vec<AuditEntry>>,
+    onboarding: Mutex<HashMap<u64, OnboardingRecord>>,
+    access: Mutex<Vec<AccessGrant>>,
+    personas: Mutex<HashMap<String, Persona>>,
+    channel_personas: Mutex<HashMap<u64, ChannelPersona>>,
+    threads: Mutex<HashMap<String, ChatThread>>,
+}
+
+#[async_trait]
+impl UserStore for MemoryStore {
+    async fn find_user(&self, discord_id: u64) -> StoreResult<Option<User>> {
+        Ok(self.users.lock().await.get(&discord_id).cloned())
+    }
+
+    async fn insert_user(&self, user: User) -> StoreResult<()> {
+        let discord_id = user
+            .discord_id
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid discord_id '{}'", user.discord_id))?;
+        self.users.lock().await.insert(discord_id, user);
+        Ok(())
+    }
+
+    async fn delete_user(&self, discord_id: u64) -> StoreResult<bool> {
+        let owner = discord_id.to_string();
+        self.conversations.lock().await.retain(|(id, _)| *id != discord_id);
+        self.onboarding.lock().await.remove(&discord_id);
+        self.threads.lock().await.retain(|_, t| t.owner_id != owner);
+        self.access
+            .lock()
+            .await
+            .retain(|g| !(g.kind == AccessKind::User && g.target_id == owner));
+
+        let mut personas = self.personas.lock().await;
+        let owned: Vec<String> = personas.values().filter(|p| p.owner_id == owner).map(|p| p.name.clone()).collect();
+        personas.retain(|_, p| p.owner_id != owner);
+        let mut users = self.users.lock().await;
+        for user in users.values_mut() {
+            if user.active_persona.as_ref().is_some_and(|name| owned.contains(name)) {
+                user.active_persona = None;
+            }
+        }
+        Ok(users.remove(&discord_id).is_some())
+    }
+
+    async fn set_nickname(&self, discord_id: u64, nickname: String) -> StoreResult<bool> {
+        match self.users.lock().await.get_mut(&discord_id) {
+            Some(user) => {
+                user.nickname = nickname;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_guild_nickname(
+        &self,
+        discord_id: u64,
+        guild_id: u64,
+        nickname: Option<String>,
+    ) -> StoreResult<bool> {
+        match self.users.lock().await.get_mut(&discord_id) {
+            Some(user) => {
+                match nickname {
+                    Some(nickname) => user.guild_nicknames.insert(guild_id.to_string(), nickname),
+                    None => user.guild_nicknames.remove(&guild_id.to_string()),
+                };
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_active_persona(&self, discord_id: u64, persona: Option<String>) -> StoreResult<bool> {
+        match self.users.lock().await.get_mut(&discord_id) {
+            Some(user) => {
+                user.active_persona = persona;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl ConversationStore for MemoryStore {
+    async fn push_conversation(&self, discord_id: u64, conversation: Conversation) -> StoreResult<()> {
+        // Kept sorted by timestamp; equal timestamps stay in insertion order
+        let mut conversations = self.conversations.lock().await;
+        let index = conversations.partition_point(|(_, c)| c.timestamp <= conversation.timestamp);
+        conversations.insert(index, (discord_id, conversation));
+        Ok(())
+    }
+
+    async fn conversation_page(
+        &self,
+        query: &ConversationQuery,
+        offset: u64,
+        limit: u64,
+    ) -> StoreResult<Vec<Conversation>> {
+        let conversations = self.conversations.lock().await;
+        Ok(conversations
+            .iter()
+            .rev()
+            .filter(|(id, c)| *id == query.discord_id && query.matches(c))
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|(_, c)| c.clone())
+            .collect())
+    }
+
+    async fn count_conversations(&self, query: &ConversationQuery) -> StoreResult<u64> {
+        let conversations = self.conversations.lock().await;
+        Ok(conversations
+            .iter()
+            .filter(|(id, c)| *id == query.discord_id && query.matches(c))
+            .count() as u64)
+    }
+
+    async fn export_conversations(&self, discord_id: Option<u64>) -> StoreResult<Vec<(u64, Conversation)>> {
+        let conversations = self.conversations.lock().await;
+        Ok(conversations
+            .iter()
+            .filter(|(id, _)| discord_id.is_none_or(|wanted| *id == wanted))
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_conversations(&self, discord_id: u64, count: Option<u64>) -> StoreResult<u64> {
+        let mut conversations = self.conversations.lock().await;
+        let mut remaining = count.unwrap_or(u64::MAX);
+        let mut deleted = 0;
+        // Walk newest-first so `count` removes the most recent turns
+        let mut index = conversations.len();
+        while index > 0 && remaining > 0 {
+            index -= 1;
+            if conversations[index].0 == discord_id {
+                conversations.remove(index);
+                remaining -= 1;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+#[async_trait]
+impl GuildSettingsStore for MemoryStore {
+    async fn get_guild_settings(&self, guild_id: u64) -> StoreResult<Option<GuildSettings>> {
+        Ok(self.guild_settings.lock().await.get(&guild_id).cloned())
+    }
+
+    async fn ensure_guild_settings(&self, guild_id: u64, ai_channel_ids: &[u64]) -> StoreResult<bool> {
+        let mut settings = self.guild_settings.lock().await;
+        if settings.contains_key(&guild_id) {
+            return Ok(false);
+        }
+        settings.insert(
+            guild_id,
+            GuildSettings {
+                id: None,
+                guild_id: guild_id.to_string(),
+                ai_channel_ids: ai_channel_ids.iter().map(|id| id.to_string()).collect(),
+                system_prompt: None,
+                features: GuildFeatures::default(),
+            },
+        );
+        Ok(true)
+    }
+
+    async fn add_ai_channel(&self, guild_id: u64, channel_id: u64) -> StoreResult<()> {
+        let mut settings = self.guild_settings.lock().await;
+        let entry = settings.entry(guild_id).or_insert_with(|| GuildSettings {
+            id: None,
+            guild_id: guild_id.to_string(),
+            ai_channel_ids: Vec::new(),
+            system_prompt: None,
+            features: GuildFeatures::default(),
+        });
+        let channel_id = channel_id.to_string();
+        if !entry.ai_channel_ids.contains(&channel_id) {
+            entry.ai_channel_ids.push(channel_id);
+        }
+        Ok(())
+    }
+
+    async fn set_system_prompt(&self, guild_id: u64, prompt: Option<String>) -> StoreResult<()> {
+        match self.guild_settings.lock().await.get_mut(&guild_id) {
+            Some(entry) => {
+                entry.system_prompt = prompt;
+                Ok(())
+            }
+            None => Err(format!("Guild {} has no settings", guild_id)),
+        }
+    }
+
+    async fn set_ai_chat(&self, guild_id: u64, enabled: bool) -> StoreResult<()> {
+        match self.guild_settings.lock().await.get_mut(&guild_id) {
+            Some(entry) => {
+                entry.features.ai_chat = enabled;
+                Ok(())
+            }
+            None => Err(format!("Guild {} has no settings", guild_id)),
+        }
+    }
+
+    async fn remove_ai_channel(&self, guild_id: u64, channel_id: u64) -> StoreResult<bool> {
+        let mut settings = self.guild_settings.lock().await;
+        let channel_id = channel_id.to_string();
+        Ok(match settings.get_mut(&guild_id) {
+            Some(entry) => {
+                let before = entry.ai_channel_ids.len();
+                entry.ai_channel_ids.retain(|id| *id != channel_id);
+                entry.ai_channel_ids.len() != before
+            }
+            None => false,
+        })
+    }
+}
+
+#[async_trait]
+impl AuditStore for MemoryStore {
+    async fn record_audit(&self, entry: AuditEntry) -> StoreResult<()> {
+        self.audit_log.lock().await.push(entry);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OnboardingStore for MemoryStore {
+    async fn get_onboarding(&self, discord_id: u64, now: i64) -> StoreResult<Option<OnboardingRecord>> {
+        let onboarding = self.onboarding.lock().await;
+        Ok(onboarding.get(&discord_id).filter(|r| r.expires_at > now).cloned())
+    }
+
+    async fn save_onboarding(&self, record: OnboardingRecord, now: i64) -> StoreResult<()> {
+        let discord_id = record
+            .discord_id
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid discord_id '{}'", record.discord_id))?;
+        let mut onboarding = self.onboarding.lock().await;
+        onboarding.retain(|_, r| r.expires_at > now);
+        onboarding.insert(discord_id, record);
+        Ok(())
+    }
+
+    async fn clear_onboarding(&self, discord_id: u64) -> StoreResult<()> {
+        self.onboarding.lock().await.remove(&discord_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AccessStore for MemoryStore {
+    async fn list_access(&self, guild_id: u64) -> StoreResult<Vec<AccessGrant>> {
+        let guild_id = guild_id.to_string();
+        let access = self.access.lock().await;
+        Ok(access.iter().filter(|g| g.guild_id == guild_id).cloned().collect())
+    }
+
+    async fn grant_access(&self, grant: AccessGrant) -> StoreResult<bool> {
+        let mut access = self.access.lock().await;
+        if access.contains(&grant) {
+            return Ok(false);
+        }
+        access.push(grant);
+        Ok(true)
+    }
+
+    async fn revoke_access(&self, grant: AccessGrant) -> StoreResult<bool> {
+        let mut access = self.access.lock().await;
+        let before = access.len();
+        access.retain(|g| *g != grant);
+        Ok(access.len() != before)
+    }
+}
+
+#[async_trait]
+impl PersonaStore for MemoryStore {
+    async fn get_persona(&self, name: &str) -> StoreResult<Option<Persona>> {
+        Ok(self.personas.lock().await.get(name).cloned())
+    }
+
+    async fn list_personas(&self) -> StoreResult<Vec<Persona>> {
+        let mut personas: Vec<Persona> = self.personas.lock().await.values().cloned().collect();
+        personas.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(personas)
+    }
+
+    async fn create_persona(&self, persona: Persona) -> StoreResult<bool> {
+        let mut personas = self.personas.lock().await;
+        if personas.contains_key(&persona.name) {
+            return Ok(false);
+        }
+        personas.insert(persona.name.clone(), persona);
+        Ok(true)
+    }
+
+    async fn update_persona(&self, persona: Persona) -> StoreResult<bool> {
+        match self.personas.lock().await.get_mut(&persona.name) {
+            Some(existing) => {
+                *existing = persona;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl ChannelPersonaStore for MemoryStore {
+    async fn get_channel_persona(&self, channel_id: u64) -> StoreResult<Option<ChannelPersona>> {
+        Ok(self.channel_personas.lock().await.get(&channel_id).cloned())
+    }
+
+    async fn save_channel_persona(&self, binding: ChannelPersona) -> StoreResult<()> {
+        let channel_id = binding
+            .channel_id
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid channel_id '{}'", binding.channel_id))?;
+        self.channel_personas.lock().await.insert(channel_id, binding);
+        Ok(())
+    }
+
+    async fn remove_channel_persona(&self, channel_id: u64) -> StoreResult<Option<ChannelPersona>> {
+        Ok(self.channel_personas.lock().await.remove(&channel_id))
+    }
+}
+
+#[async_trait]
+impl ThreadStore for MemoryStore {
+    async fn get_thread(&self, thread_id: u64) -> StoreResult<Option<ChatThread>> {
+        Ok(self.threads.lock().await.get(&thread_id.to_string()).cloned())
+    }
+
+    async fn create_thread(&self, thread: ChatThread) -> StoreResult<()> {
+        self.threads.lock().await.insert(thread.thread_id.clone(), thread);
+        Ok(())
+    }
+
+    async fn set_thread_archived(&self, thread_id: u64, archived: bool) -> StoreResult<bool> {
+        let mut threads = self.threads.lock().await;
+        match threads.get_mut(&thread_id.to_string()) {
+            Some(thread) if thread.archived != archived => {
+                thread.archived = archived;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn delete_thread(&self, thread_id: u64) -> StoreResult<bool> {
+        Ok(self.threads.lock().await.remove(&thread_id.to_string()).is_some())
+    }
+
+    async fn find_open_thread(&self, owner_id: u64, parent_id: u64) -> StoreResult<Option<ChatThread>> {
+        let (owner_id, parent_id) = (owner_id.to_string(), parent_id.to_string());
+        Ok(self
+            .threads
+            .lock()
+            .await
+            .values()
+            .filter(|t| t.owner_id == owner_id && t.parent_id == parent_id && !t.archived)
+            .max_by_key(|t| t.created_at)
+            .cloned())
+    }
+
+    async fn thread_ids(&self) -> StoreResult<Vec<u64>> {
+        Ok(self.threads.lock().await.keys().filter_map(|id| id.parse().ok()).collect())
+    }
+}
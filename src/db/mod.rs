@@ -1,7 +1,694 @@
+pub mod access;
+pub mod audit;
+pub mod channel_persona;
+pub mod guild_settings;
+pub mod memory;
+pub mod mongo;
+pub mod onboarding;
+pub mod persona;
+pub mod thread;
+pub mod sqlite;
 pub mod user;
-use mongodb::{Client as MongoClient, options::ClientOptions, bson::doc, Collection};
-use crate::db::user::User;
 
-pub fn get_user_collection(db_client: &MongoClient) -> Collection<User> {
-    db_client.database("discord_bot").collection::<User>("users")
-}
\ No newline at end of file
+use serenity::async_trait;
+use std::sync::Arc;
+use crate::config::{Config, StorageKind};
+use crate::db::access::AccessGrant;
+use crate::db::audit::AuditEntry;
+use crate::db::channel_persona::ChannelPersona;
+use crate::db::guild_settings::GuildSettings;
+use crate::db::onboarding::OnboardingRecord;
+use crate::db::persona::Persona;
+use crate::db::thread::ChatThread;
+use crate::db::user::{Conversation, User};
+
+/// Storage errors are reported as plain messages, like the rest of the bot
+pub type StoreResult<T> = Result<T, String>;
+
+/// Registered users and their nicknames
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    /// Fetch a user record (conversations live in [`ConversationStore`])
+    async fn find_user(&self, discord_id: u64) -> StoreResult<Option<User>>;
+
+    async fn insert_user(&self, user: User) -> StoreResult<()>;
+
+    /// Delete the user record and everything tied to them: conversations, guild nicknames,
+    /// onboarding record, thread records, user access grants and the personas they created
+    /// (anyone using one of those goes back to the default). Returns whether the user existed.
+    async fn delete_user(&self, discord_id: u64) -> StoreResult<bool>;
+
+    /// Change the global nickname; returns whether the user exists
+    async fn set_nickname(&self, discord_id: u64, nickname: String) -> StoreResult<bool>;
+
+    /// Set (or with None, remove) the nickname used in one guild; returns whether the user exists
+    async fn set_guild_nickname(
+        &self,
+        discord_id: u64,
+        guild_id: u64,
+        nickname: Option<String>,
+    ) -> StoreResult<bool>;
+
+    /// Switch the user to a persona (None for the default); returns whether the user exists
+    async fn set_active_persona(&self, discord_id: u64, persona: Option<String>) -> StoreResult<bool>;
+
+    /// The nickname to use in `guild_id`, falling back to the global one
+    async fn get_nickname(&self, discord_id: u64, guild_id: Option<u64>) -> StoreResult<Option<String>> {
+        Ok(self
+            .find_user(discord_id)
+            .await?
+            .map(|user| user.nickname_in(guild_id).to_string()))
+    }
+}
+
+/// Which stored conversations to read
+#[derive(Debug, Clone, Default)]
+pub struct ConversationQuery {
+    pub discord_id: u64,
+    pub channel_id: Option<u64>,
+    pub session_id: Option<String>,
+    /// Only conversations outside any session, e.g. thread summaries
+    pub sessionless: bool,
+}
+
+impl ConversationQuery {
+    /// All of a user's conversations, across channels and sessions
+    pub fn user(discord_id: u64) -> Self {
+        Self {
+            discord_id,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a stored conversation matches the channel/session filters
+    pub fn matches(&self, conversation: &Conversation) -> bool {
+        let channel_ok = match self.channel_id {
+            Some(id) => conversation.channel_id.as_deref() == Some(id.to_string().as_str()),
+            None => true,
+        };
+        let session_ok = match &self.session_id {
+            Some(id) => conversation.session_id.as_ref() == Some(id),
+            None => !self.sessionless || conversation.session_id.is_none(),
+        };
+        channel_ok && session_ok
+    }
+}
+
+/// Prompt/response history per user, stored separately from the user record.
+///
+/// Every backend orders conversations by timestamp, breaking ties by insertion order,
+/// so imported older turns sort before newer ones however late they were added.
+#[async_trait]
+pub trait ConversationStore: Send + Sync {
+    async fn push_conversation(&self, discord_id: u64, conversation: Conversation) -> StoreResult<()>;
+
+    /// Append several conversations in order, e.g. from an import
+    async fn push_conversations(&self, discord_id: u64, conversations: Vec<Conversation>) -> StoreResult<()> {
+        for conversation in conversations {
+            self.push_conversation(discord_id, conversation).await?;
+        }
+        Ok(())
+    }
+
+    /// One page of matching conversations, newest first
+    async fn conversation_page(
+        &self,
+        query: &ConversationQuery,
+        offset: u64,
+        limit: u64,
+    ) -> StoreResult<Vec<Conversation>>;
+
+    /// Number of conversations matching the query
+    async fn count_conversations(&self, query: &ConversationQuery) -> StoreResult<u64>;
+
+    /// Delete the user's newest `count` conversations, or all of them when `count` is None.
+    /// Returns how many were deleted.
+    async fn delete_conversations(&self, discord_id: u64, count: Option<u64>) -> StoreResult<u64>;
+
+    /// Every stored conversation, oldest first, optionally limited to one user
+    async fn export_conversations(&self, discord_id: Option<u64>) -> StoreResult<Vec<(u64, Conversation)>>;
+
+    /// The user's most recent conversations (oldest first), at most `limit` entries
+    async fn recent_conversations(&self, discord_id: u64, limit: usize) -> StoreResult<Vec<Conversation>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let mut page = self
+            .conversation_page(&ConversationQuery::user(discord_id), 0, limit as u64)
+            .await?;
+        page.reverse();
+        Ok(page)
+    }
+
+    /// Like [`recent_conversations`](Self::recent_conversations), limited to one session
+    async fn recent_session_conversations(
+        &self,
+        discord_id: u64,
+        session_id: &str,
+        limit: usize,
+    ) -> StoreResult<Vec<Conversation>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let query = ConversationQuery {
+            session_id: Some(session_id.to_string()),
+            ..ConversationQuery::user(discord_id)
+        };
+        let mut page = self.conversation_page(&query, 0, limit as u64).await?;
+        page.reverse();
+        Ok(page)
+    }
+
+    /// Like [`recent_conversations`](Self::recent_conversations), skipping every session
+    async fn recent_sessionless_conversations(&self, discord_id: u64, limit: usize) -> StoreResult<Vec<Conversation>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let query = ConversationQuery {
+            sessionless: true,
+            ..ConversationQuery::user(discord_id)
+        };
+        let mut page = self.conversation_page(&query, 0, limit as u64).await?;
+        page.reverse();
+        Ok(page)
+    }
+}
+
+/// Per-guild AI channels, system prompt and feature toggles
+#[async_trait]
+pub trait GuildSettingsStore: Send + Sync {
+    async fn get_guild_settings(&self, guild_id: u64) -> StoreResult<Option<GuildSettings>>;
+
+    /// Create the guild's settings listening in `ai_channel_ids` (normally the config file's
+    /// channels in that guild) unless they exist already. Returns whether they were created.
+    async fn ensure_guild_settings(&self, guild_id: u64, ai_channel_ids: &[u64]) -> StoreResult<bool>;
+
+    /// Add a channel to the guild's AI channels, creating the settings if needed.
+    /// Once a guild has settings stored, the config file's `ai_channel_ids` no longer apply to it.
+    async fn add_ai_channel(&self, guild_id: u64, channel_id: u64) -> StoreResult<()>;
+
+    /// Remove a channel from the guild's AI channels; returns whether it was listed
+    async fn remove_ai_channel(&self, guild_id: u64, channel_id: u64) -> StoreResult<bool>;
+
+    /// Set or clear (None) the guild's system prompt. The settings must exist.
+    async fn set_system_prompt(&self, guild_id: u64, prompt: Option<String>) -> StoreResult<()>;
+
+    /// Turn AI chat on or off for the guild. The settings must exist.
+    async fn set_ai_chat(&self, guild_id: u64, enabled: bool) -> StoreResult<()>;
+}
+
+/// Append-only record of data deletions and other sensitive actions
+#[async_trait]
+pub trait AuditStore: Send + Sync {
+    async fn record_audit(&self, entry: AuditEntry) -> StoreResult<()>;
+}
+
+/// Unfinished registrations, see [`crate::onboarding`]
+#[async_trait]
+pub trait OnboardingStore: Send + Sync {
+    /// The user's onboarding record, unless it expired at or before `now`
+    async fn get_onboarding(&self, discord_id: u64, now: i64) -> StoreResult<Option<OnboardingRecord>>;
+
+    /// Create or replace the user's record; also drops every record expired at `now`
+    async fn save_onboarding(&self, record: OnboardingRecord, now: i64) -> StoreResult<()>;
+
+    async fn clear_onboarding(&self, discord_id: u64) -> StoreResult<()>;
+}
+
+/// Per-guild allowlist for controlling the chatbot process, kept apart from
+/// [`GuildSettings`] so granting access never shadows the config's AI channels
+#[async_trait]
+pub trait AccessStore: Send + Sync {
+    async fn list_access(&self, guild_id: u64) -> StoreResult<Vec<AccessGrant>>;
+
+    /// Returns false if the grant already existed
+    async fn grant_access(&self, grant: AccessGrant) -> StoreResult<bool>;
+
+    /// Returns whether the grant existed
+    async fn revoke_access(&self, grant: AccessGrant) -> StoreResult<bool>;
+}
+
+/// Named personas created with /persona
+#[async_trait]
+pub trait PersonaStore: Send + Sync {
+    async fn get_persona(&self, name: &str) -> StoreResult<Option<Persona>>;
+
+    /// All personas, sorted by name
+    async fn list_personas(&self) -> StoreResult<Vec<Persona>>;
+
+    /// Returns false if the name is already taken
+    async fn create_persona(&self, persona: Persona) -> StoreResult<bool>;
+
+    /// Replace an existing persona; returns false if there is none by that name
+    async fn update_persona(&self, persona: Persona) -> StoreResult<bool>;
+}
+
+/// Channels bound to a persona with /channel-persona
+#[async_trait]
+pub trait ChannelPersonaStore: Send + Sync {
+    async fn get_channel_persona(&self, channel_id: u64) -> StoreResult<Option<ChannelPersona>>;
+
+    /// Create or replace the channel's binding
+    async fn save_channel_persona(&self, binding: ChannelPersona) -> StoreResult<()>;
+
+    /// Remove the channel's binding, returning it so its webhook can be cleaned up
+    async fn remove_channel_persona(&self, channel_id: u64) -> StoreResult<Option<ChannelPersona>>;
+}
+
+/// Conversation threads the bot opened
+#[async_trait]
+pub trait ThreadStore: Send + Sync {
+    async fn get_thread(&self, thread_id: u64) -> StoreResult<Option<ChatThread>>;
+
+    async fn create_thread(&self, thread: ChatThread) -> StoreResult<()>;
+
+    /// Set the archived flag. Returns whether it changed, so each archive is summarised once.
+    async fn set_thread_archived(&self, thread_id: u64, archived: bool) -> StoreResult<bool>;
+
+    async fn delete_thread(&self, thread_id: u64) -> StoreResult<bool>;
+
+    /// The newest unarchived thread the user has in `parent_id`
+    async fn find_open_thread(&self, owner_id: u64, parent_id: u64) -> StoreResult<Option<ChatThread>>;
+
+    /// Every thread the bot has a record of, so other channels need no lookup
+    async fn thread_ids(&self) -> StoreResult<Vec<u64>>;
+}
+
+/// Everything the bot persists
+pub trait Store:
+    UserStore
+    + ConversationStore
+    + GuildSettingsStore
+    + AuditStore
+    + OnboardingStore
+    + AccessStore
+    + PersonaStore
+    + ChannelPersonaStore
+    + ThreadStore
+{
+}
+
+impl<T> Store for T where
+    T: UserStore
+        + ConversationStore
+        + GuildSettingsStore
+        + AuditStore
+        + OnboardingStore
+        + AccessStore
+        + PersonaStore
+        + ChannelPersonaStore
+        + ThreadStore
+{
+}
+
+/// Open the storage backend selected in the config
+pub async fn from_config(config: &Config) -> StoreResult<Arc<dyn Store>> {
+    match config.storage.kind {
+        StorageKind::Mongo => Ok(Arc::new(mongo::MongoStore::connect(&config.mongo).await?)),
+        StorageKind::Sqlite => Ok(Arc::new(sqlite::SqliteStore::open(&config.storage.sqlite_path)?)),
+        StorageKind::Memory => {
+            println!("[WARN] Using in-memory storage; nothing will survive a restart");
+            Ok(Arc::new(memory::MemoryStore::default()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::access::AccessKind;
+    use crate::db::onboarding::OnboardingState;
+
+    fn user(discord_id: u64, nickname: &str) -> User {
+        User {
+            id: None,
+            discord_id: discord_id.to_string(),
+            nickname: nickname.to_string(),
+            guild_nicknames: Default::default(),
+            active_persona: None,
+        }
+    }
+
+    fn persona(name: &str, prompt: &str) -> Persona {
+        Persona {
+            name: name.to_string(),
+            owner_id: "1".to_string(),
+            system_prompt: prompt.to_string(),
+            temperature: Some(0.9),
+            greeting: Some("Ahoy!".to_string()),
+            avatar_url: None,
+            created_at: 0,
+        }
+    }
+
+    fn turn(n: i64) -> Conversation {
+        Conversation {
+            prompt: format!("prompt {}", n),
+            response: format!("response {}", n),
+            timestamp: n,
+            channel_id: Some(if n % 2 == 0 { "20" } else { "30" }.to_string()),
+            session_id: None,
+        }
+    }
+
+    async fn users(store: &dyn Store) {
+        assert!(store.find_user(1).await.unwrap().is_none());
+        assert_eq!(store.get_nickname(1, None).await.unwrap(), None);
+        assert!(!store.set_nickname(1, "nobody".to_string()).await.unwrap());
+
+        store.insert_user(user(1, "potato")).await.unwrap();
+        assert_eq!(store.get_nickname(1, None).await.unwrap().as_deref(), Some("potato"));
+
+        assert!(store.set_nickname(1, "tomato".to_string()).await.unwrap());
+        assert!(store.set_guild_nickname(1, 10, Some("spud".to_string())).await.unwrap());
+        assert_eq!(store.get_nickname(1, Some(10)).await.unwrap().as_deref(), Some("spud"));
+        assert_eq!(store.get_nickname(1, Some(11)).await.unwrap().as_deref(), Some("tomato"));
+        assert!(store.set_guild_nickname(1, 10, None).await.unwrap());
+        assert_eq!(store.get_nickname(1, Some(10)).await.unwrap().as_deref(), Some("tomato"));
+        assert!(!store.set_guild_nickname(2, 10, Some("ghost".to_string())).await.unwrap());
+
+        store.push_conversation(1, turn(1)).await.unwrap();
+        assert!(store.delete_user(1).await.unwrap());
+        assert!(store.find_user(1).await.unwrap().is_none());
+        assert_eq!(store.count_conversations(&ConversationQuery::user(1)).await.unwrap(), 0);
+        assert!(!store.delete_user(1).await.unwrap());
+    }
+
+    async fn user_deletion(store: &dyn Store) {
+        let mut potato = user(1, "potato");
+        potato.guild_nicknames.insert("10".to_string(), "spud".to_string());
+        store.insert_user(potato).await.unwrap();
+        store.insert_user(user(2, "carrot")).await.unwrap();
+        store.insert_user(user(3, "leek")).await.unwrap();
+        store.push_conversation(1, turn(1)).await.unwrap();
+        store
+            .save_onboarding(
+                OnboardingRecord {
+                    discord_id: "1".to_string(),
+                    state: OnboardingState::AwaitingConfirmation {
+                        nickname: "spud".to_string(),
+                    },
+                    expires_at: 100,
+                },
+                0,
+            )
+            .await
+            .unwrap();
+
+        let thread = |id: &str, owner: &str| ChatThread {
+            thread_id: id.to_string(),
+            guild_id: "10".to_string(),
+            parent_id: "100".to_string(),
+            owner_id: owner.to_string(),
+            created_at: 0,
+            archived: false,
+        };
+        store.create_thread(thread("200", "1")).await.unwrap();
+        store.create_thread(thread("201", "2")).await.unwrap();
+
+        store.grant_access(AccessGrant::new(10, AccessKind::User, 1)).await.unwrap();
+        store.grant_access(AccessGrant::new(11, AccessKind::User, 1)).await.unwrap();
+        store.grant_access(AccessGrant::new(10, AccessKind::User, 2)).await.unwrap();
+        // A role that happens to share the user's id is not theirs
+        store.grant_access(AccessGrant::new(10, AccessKind::Role, 1)).await.unwrap();
+
+        store.create_persona(persona("pirate", "Talk like a pirate.")).await.unwrap();
+        let chef = Persona {
+            owner_id: "2".to_string(),
+            ..persona("chef", "Talk about food.")
+        };
+        store.create_persona(chef).await.unwrap();
+        store.set_active_persona(2, Some("pirate".to_string())).await.unwrap();
+        store.set_active_persona(3, Some("chef".to_string())).await.unwrap();
+
+        assert!(store.delete_user(1).await.unwrap());
+
+        assert!(store.find_user(1).await.unwrap().is_none());
+        assert_eq!(store.get_nickname(1, Some(10)).await.unwrap(), None);
+        assert_eq!(store.count_conversations(&ConversationQuery::user(1)).await.unwrap(), 0);
+        assert_eq!(store.get_onboarding(1, 0).await.unwrap(), None);
+        assert_eq!(store.get_thread(200).await.unwrap(), None);
+        assert!(store.get_thread(201).await.unwrap().is_some());
+        assert_eq!(
+            store.list_access(10).await.unwrap(),
+            vec![AccessGrant::new(10, AccessKind::User, 2), AccessGrant::new(10, AccessKind::Role, 1)]
+        );
+        assert!(store.list_access(11).await.unwrap().is_empty());
+        assert_eq!(store.get_persona("pirate").await.unwrap(), None);
+        assert!(store.get_persona("chef").await.unwrap().is_some());
+        assert_eq!(store.find_user(2).await.unwrap().unwrap().active_persona, None);
+        assert_eq!(store.find_user(3).await.unwrap().unwrap().active_persona.as_deref(), Some("chef"));
+
+        // Registering again starts from scratch
+        store.insert_user(user(1, "potato")).await.unwrap();
+        assert_eq!(store.get_nickname(1, Some(10)).await.unwrap().as_deref(), Some("potato"));
+    }
+
+    async fn personas(store: &dyn Store) {
+        store.insert_user(user(1, "potato")).await.unwrap();
+
+        assert!(store.create_persona(persona("pirate", "Talk like a pirate.")).await.unwrap());
+        assert!(!store.create_persona(persona("pirate", "Duplicate")).await.unwrap());
+        assert!(store.create_persona(persona("chef", "Talk about food.")).await.unwrap());
+        assert!(store.update_persona(persona("pirate", "Talk like a polite pirate.")).await.unwrap());
+        assert!(!store.update_persona(persona("ghost", "Boo")).await.unwrap());
+        assert_eq!(
+            store.get_persona("pirate").await.unwrap(),
+            Some(persona("pirate", "Talk like a polite pirate."))
+        );
+        let names: Vec<String> = store.list_personas().await.unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["chef", "pirate"]);
+
+        assert!(store.set_active_persona(1, Some("pirate".to_string())).await.unwrap());
+        assert_eq!(store.find_user(1).await.unwrap().unwrap().active_persona.as_deref(), Some("pirate"));
+        assert!(store.set_active_persona(1, None).await.unwrap());
+        assert_eq!(store.find_user(1).await.unwrap().unwrap().active_persona, None);
+        assert!(!store.set_active_persona(2, Some("pirate".to_string())).await.unwrap());
+    }
+
+    async fn channel_personas(store: &dyn Store) {
+        let mut binding = ChannelPersona::new(10, 100, &persona("pirate", "Talk like a pirate."));
+        store.save_channel_persona(binding.clone()).await.unwrap();
+        assert_eq!(store.get_channel_persona(100).await.unwrap(), Some(binding.clone()));
+        binding.webhook_id = Some("55".to_string());
+        binding.webhook_token = Some("secret".to_string());
+        store.save_channel_persona(binding.clone()).await.unwrap();
+        assert_eq!(store.get_channel_persona(100).await.unwrap(), Some(binding.clone()));
+        assert_eq!(store.remove_channel_persona(100).await.unwrap(), Some(binding));
+        assert_eq!(store.remove_channel_persona(100).await.unwrap(), None);
+        assert_eq!(store.get_channel_persona(100).await.unwrap(), None);
+    }
+
+    async fn threads(store: &dyn Store) {
+        let thread = ChatThread {
+            thread_id: "200".to_string(),
+            guild_id: "10".to_string(),
+            parent_id: "100".to_string(),
+            owner_id: "1".to_string(),
+            created_at: 1_700_000_000,
+            archived: false,
+        };
+        store.create_thread(thread.clone()).await.unwrap();
+        assert_eq!(store.get_thread(200).await.unwrap(), Some(thread));
+        assert!(store.set_thread_archived(200, true).await.unwrap());
+        assert!(!store.set_thread_archived(200, true).await.unwrap());
+        assert!(store.get_thread(200).await.unwrap().unwrap().archived);
+        assert_eq!(store.find_open_thread(1, 100).await.unwrap(), None);
+        assert!(store.set_thread_archived(200, false).await.unwrap());
+        assert!(!store.set_thread_archived(201, true).await.unwrap());
+        assert_eq!(store.find_open_thread(1, 100).await.unwrap().map(|t| t.thread_id), Some("200".to_string()));
+        assert_eq!(store.find_open_thread(2, 100).await.unwrap(), None);
+        assert_eq!(store.find_open_thread(1, 101).await.unwrap(), None);
+        assert_eq!(store.thread_ids().await.unwrap(), vec![200]);
+        assert!(store.delete_thread(200).await.unwrap());
+        assert!(!store.delete_thread(200).await.unwrap());
+        assert!(store.thread_ids().await.unwrap().is_empty());
+        assert_eq!(store.get_thread(200).await.unwrap(), None);
+    }
+
+    async fn conversations(store: &dyn Store) {
+        for n in 1..=5 {
+            store.push_conversation(1, turn(n)).await.unwrap();
+        }
+
+        let recent = store.recent_conversations(1, 3).await.unwrap();
+        assert_eq!(recent, vec![turn(3), turn(4), turn(5)]);
+        assert!(store.recent_conversations(2, 3).await.unwrap().is_empty());
+
+        let query = ConversationQuery::user(1);
+        assert_eq!(store.count_conversations(&query).await.unwrap(), 5);
+        assert_eq!(store.conversation_page(&query, 0, 2).await.unwrap(), vec![turn(5), turn(4)]);
+        assert_eq!(store.conversation_page(&query, 4, 2).await.unwrap(), vec![turn(1)]);
+
+        let in_channel = ConversationQuery {
+            channel_id: Some(20),
+            ..ConversationQuery::user(1)
+        };
+        assert_eq!(store.conversation_page(&in_channel, 0, 10).await.unwrap(), vec![turn(4), turn(2)]);
+        assert_eq!(store.count_conversations(&in_channel).await.unwrap(), 2);
+
+        store.push_conversations(2, vec![turn(9)]).await.unwrap();
+        let everything = store.export_conversations(None).await.unwrap();
+        assert_eq!(everything.len(), 6);
+        assert_eq!(everything.first(), Some(&(1, turn(1))));
+        assert_eq!(everything.last(), Some(&(2, turn(9))));
+        assert_eq!(store.export_conversations(Some(2)).await.unwrap(), vec![(2, turn(9))]);
+
+        assert_eq!(store.delete_conversations(1, Some(2)).await.unwrap(), 2);
+        assert_eq!(store.recent_conversations(1, 10).await.unwrap(), vec![turn(1), turn(2), turn(3)]);
+        assert_eq!(store.delete_conversations(1, None).await.unwrap(), 3);
+        assert_eq!(store.count_conversations(&query).await.unwrap(), 0);
+
+        let in_thread = Conversation {
+            session_id: Some("200".to_string()),
+            ..turn(7)
+        };
+        store.push_conversations(1, vec![turn(6), in_thread.clone(), turn(8)]).await.unwrap();
+        assert_eq!(store.recent_session_conversations(1, "200", 5).await.unwrap(), vec![in_thread]);
+        assert!(store.recent_session_conversations(1, "201", 5).await.unwrap().is_empty());
+        assert_eq!(store.recent_sessionless_conversations(1, 5).await.unwrap(), vec![turn(6), turn(8)]);
+        assert_eq!(store.recent_sessionless_conversations(1, 1).await.unwrap(), vec![turn(8)]);
+    }
+
+    async fn conversation_order(store: &dyn Store) {
+        // An import appends turns older than the ones already stored
+        store.push_conversations(1, vec![turn(10), turn(20)]).await.unwrap();
+        store.push_conversations(1, vec![turn(5), turn(15)]).await.unwrap();
+        let same_time = Conversation {
+            prompt: "same time, added later".to_string(),
+            ..turn(20)
+        };
+        store.push_conversation(1, same_time.clone()).await.unwrap();
+
+        let query = ConversationQuery::user(1);
+        assert_eq!(
+            store.conversation_page(&query, 0, 10).await.unwrap(),
+            vec![same_time.clone(), turn(20), turn(15), turn(10), turn(5)]
+        );
+        assert_eq!(store.recent_conversations(1, 2).await.unwrap(), vec![turn(20), same_time.clone()]);
+        let exported: Vec<i64> = store
+            .export_conversations(Some(1))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, c)| c.timestamp)
+            .collect();
+        assert_eq!(exported, vec![5, 10, 15, 20, 20]);
+
+        assert_eq!(store.delete_conversations(1, Some(2)).await.unwrap(), 2);
+        assert_eq!(store.recent_conversations(1, 10).await.unwrap(), vec![turn(5), turn(10), turn(15)]);
+    }
+
+    async fn audit(store: &dyn Store) {
+        store
+            .record_audit(AuditEntry {
+                actor_id: "1".to_string(),
+                target_id: "1".to_string(),
+                action: "unregister".to_string(),
+                detail: String::new(),
+                timestamp: 0,
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn onboarding(store: &dyn Store) {
+        let record = |id: &str, expires_at| OnboardingRecord {
+            discord_id: id.to_string(),
+            state: OnboardingState::AwaitingConfirmation {
+                nickname: "spud".to_string(),
+            },
+            expires_at,
+        };
+        store.save_onboarding(record("5", 100), 0).await.unwrap();
+        assert_eq!(store.get_onboarding(5, 50).await.unwrap(), Some(record("5", 100)));
+        assert_eq!(store.get_onboarding(5, 100).await.unwrap(), None);
+        store.save_onboarding(record("6", 300), 200).await.unwrap();
+        assert_eq!(store.get_onboarding(5, 0).await.unwrap(), None);
+        store.clear_onboarding(6).await.unwrap();
+        assert_eq!(store.get_onboarding(6, 0).await.unwrap(), None);
+    }
+
+    async fn access(store: &dyn Store) {
+        let role = AccessGrant::new(10, AccessKind::Role, 7);
+        let member = AccessGrant::new(10, AccessKind::User, 7);
+        assert!(store.grant_access(role.clone()).await.unwrap());
+        assert!(!store.grant_access(role.clone()).await.unwrap());
+        assert!(store.grant_access(member.clone()).await.unwrap());
+        assert_eq!(store.list_access(10).await.unwrap(), vec![role.clone(), member.clone()]);
+        assert!(store.list_access(11).await.unwrap().is_empty());
+        assert!(store.revoke_access(role.clone()).await.unwrap());
+        assert!(!store.revoke_access(role).await.unwrap());
+        assert_eq!(store.list_access(10).await.unwrap(), vec![member]);
+        // The allowlist lives apart from guild settings
+        assert!(store.get_guild_settings(10).await.unwrap().is_none());
+    }
+
+    async fn guild_settings(store: &dyn Store) {
+        assert!(store.get_guild_settings(10).await.unwrap().is_none());
+        assert!(store.ensure_guild_settings(10, &[300]).await.unwrap());
+        assert!(!store.ensure_guild_settings(10, &[400]).await.unwrap());
+        assert_eq!(store.get_guild_settings(10).await.unwrap().unwrap().ai_channel_ids, vec!["300"]);
+        assert!(store.remove_ai_channel(10, 300).await.unwrap());
+        store.add_ai_channel(10, 100).await.unwrap();
+        store.add_ai_channel(10, 100).await.unwrap();
+        store.add_ai_channel(10, 200).await.unwrap();
+        let settings = store.get_guild_settings(10).await.unwrap().unwrap();
+        assert_eq!(settings.ai_channel_ids, vec!["100", "200"]);
+        assert!(settings.features.ai_chat);
+        assert!(settings.listens_in(200));
+
+        assert!(store.remove_ai_channel(10, 100).await.unwrap());
+        assert!(!store.remove_ai_channel(10, 100).await.unwrap());
+        assert!(!store.get_guild_settings(10).await.unwrap().unwrap().listens_in(100));
+
+        store.set_system_prompt(10, Some("Be kind.".to_string())).await.unwrap();
+        store.set_ai_chat(10, false).await.unwrap();
+        let settings = store.get_guild_settings(10).await.unwrap().unwrap();
+        assert_eq!(settings.system_prompt.as_deref(), Some("Be kind."));
+        assert!(!settings.features.ai_chat);
+        assert_eq!(settings.ai_channel_ids, vec!["200"]);
+        store.set_system_prompt(10, None).await.unwrap();
+        store.set_ai_chat(10, true).await.unwrap();
+        let settings = store.get_guild_settings(10).await.unwrap().unwrap();
+        assert_eq!(settings.system_prompt, None);
+        assert!(settings.features.ai_chat);
+    }
+
+    /// One test per store trait, each run against every backend that needs no server
+    macro_rules! store_tests {
+        ($($name:ident),* $(,)?) => {
+            mod memory_store {
+                $(
+                    #[tokio::test]
+                    async fn $name() {
+                        super::$name(&crate::db::memory::MemoryStore::default()).await;
+                    }
+                )*
+            }
+
+            mod sqlite_store {
+                $(
+                    #[tokio::test]
+                    async fn $name() {
+                        super::$name(&crate::db::sqlite::SqliteStore::open(":memory:").unwrap()).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    store_tests!(
+        users,
+        user_deletion,
+        personas,
+        channel_personas,
+        threads,
+        conversations,
+        conversation_order,
+        audit,
+        onboarding,
+        access,
+        guild_settings,
+    );
+}
@@ -0,0 +1,753 @@
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::options::{ClientOptions, FindOneOptions, FindOptions, IndexOptions, ReplaceOptions, UpdateOptions};
+use mongodb::{Client as MongoClient, Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use serenity::async_trait;
+use crate::config::MongoConfig;
+use crate::db::access::AccessGrant;
+use crate::db::audit::AuditEntry;
+use crate::db::channel_persona::ChannelPersona;
+use crate::db::guild_settings::GuildSettings;
+use crate::db::onboarding::OnboardingRecord;
+use crate::db::persona::Persona;
+use crate::db::thread::ChatThread;
+use crate::db::user::{Conversation, User};
+use crate::db::{
+    AccessStore, AuditStore, ChannelPersonaStore, ConversationQuery, ConversationStore, GuildSettingsStore,
+    OnboardingStore, PersonaStore, StoreResult, ThreadStore, UserStore,
+};
+
+/// A conversation as stored in the `conversations` collection
+#[derive(Debug, Serialize, Deserialize)]
+struct ConversationDoc {
+    discord_id: String,
+    #[serde(flatten)]
+    conversation: Conversation,
+}
+
+/// MongoDB-backed storage (the default)
+pub struct MongoStore {
+    users: Collection<User>,
+    conversations: Collection<ConversationDoc>,
+    guild_settings: Collection<GuildSettings>,
+    audit_log: Collection<AuditEntry>,
+    onboarding: Collection<OnboardingRecord>,
+    access: Collection<AccessGrant>,
+    personas: Collection<Persona>,
+    channel_personas: Collection<ChannelPersona>,
+    threads: Collection<ChatThread>,
+}
+
+impl MongoStore {
+    pub async fn connect(config: &MongoConfig) -> StoreResult<Self> {
+        let client_options = ClientOptions::parse(&config.uri)
+            .await
+            .map_err(|e| format!("Failed to parse MongoDB URI: {}", e))?;
+        let client = MongoClient::with_options(client_options)
+            .map_err(|e| format!("Failed to connect to MongoDB: {}", e))?;
+        let database = client.database(&config.database);
+
+        let store = Self {
+            users: database.collection::<User>("users"),
+            conversations: database.collection::<ConversationDoc>("conversations"),
+            guild_settings: database.collection::<GuildSettings>("guild_settings"),
+            audit_log: database.collection::<AuditEntry>("audit_log"),
+            onboarding: database.collection::<OnboardingRecord>("onboarding"),
+            access: database.collection::<AccessGrant>("chatbot_access"),
+            personas: database.collection::<Persona>("personas"),
+            channel_personas: database.collection::<ChannelPersona>("channel_personas"),
+            threads: database.collection::<ChatThread>("threads"),
+        };
+        store.ensure_indexes().await?;
+        store.migrate_embedded_conversations().await?;
+        Ok(store)
+    }
+
+    /// Create the indexes the bot's queries rely on (no-op if they already exist)
+    async fn ensure_indexes(&self) -> StoreResult<()> {
+        let unique = IndexOptions::builder().unique(true).build();
+        // Older databases may already hold duplicate registrations; keep running without the index
+        if let Err(e) = self
+            .users
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"discord_id": 1})
+                    .options(unique.clone())
+                    .build(),
+                None,
+            )
+            .await
+        {
+            println!("[WARN] Could not create unique users.discord_id index: {:?}", e);
+        }
+
+        self.guild_settings
+            .create_index(
+                IndexModel::builder().keys(doc! {"guild_id": 1}).options(unique.clone()).build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to create guild settings index: {:?}", e))?;
+
+        self.onboarding
+            .create_index(
+                IndexModel::builder().keys(doc! {"discord_id": 1}).options(unique.clone()).build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to create onboarding index: {:?}", e))?;
+
+        self.access
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"guild_id": 1, "kind": 1, "target_id": 1})
+                    .options(unique.clone())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to create chatbot access index: {:?}", e))?;
+
+        self.personas
+            .create_index(
+                IndexModel::builder().keys(doc! {"name": 1}).options(unique.clone()).build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to create personas index: {:?}", e))?;
+
+        self.channel_personas
+            .create_index(
+                IndexModel::builder().keys(doc! {"channel_id": 1}).options(unique.clone()).build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to create channel personas index: {:?}", e))?;
+
+        self.threads
+            .create_index(
+                IndexModel::builder().keys(doc! {"thread_id": 1}).options(unique).build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to create threads index: {:?}", e))?;
+        self.threads
+            .create_index(IndexModel::builder().keys(doc! {"owner_id": 1, "parent_id": 1}).build(), None)
+            .await
+            .map_err(|e| format!("Failed to create threads index: {:?}", e))?;
+
+        let conversation_indexes = vec![
+            IndexModel::builder()
+                .keys(doc! {"discord_id": 1, "timestamp": -1})
+                .build(),
+            IndexModel::builder()
+                .keys(doc! {"discord_id": 1, "channel_id": 1, "timestamp": -1})
+                .build(),
+            IndexModel::builder()
+                .keys(doc! {"discord_id": 1, "session_id": 1, "timestamp": -1})
+                .build(),
+        ];
+        self.conversations
+            .create_indexes(conversation_indexes, None)
+            .await
+            .map_err(|e| format!("Failed to create conversation indexes: {:?}", e))?;
+
+        println!("[LOG] MongoDB indexes ensured");
+        Ok(())
+    }
+
+    /// Move conversations embedded in user documents (the old layout) into their own collection.
+    ///
+    /// Each user's array is copied first and only unset afterwards, so an interrupted
+    /// migration can at worst duplicate that user's turns, never lose them. Users with
+    /// entries that don't decode are left untouched and reported for manual repair.
+    async fn migrate_embedded_conversations(&self) -> StoreResult<()> {
+        let raw_users = self.users.clone_with_type::<Document>();
+        let mut cursor = raw_users
+            .find(doc! {"conversations.0": {"$exists": true}}, None)
+            .await
+            .map_err(|e| format!("Failed to scan users for migration: {:?}", e))?;
+
+        let mut migrated_users = 0;
+        let mut migrated_turns = 0;
+        while let Some(user) = cursor
+            .try_next()
+            .await
+            .map_err(|e| format!("Failed to read user during migration: {:?}", e))?
+        {
+            let (Some(user_id), Ok(discord_id)) = (user.get("_id").cloned(), user.get_str("discord_id")) else {
+                continue;
+            };
+            let discord_id = discord_id.to_string();
+            let embedded = user.get_array("conversations").cloned().unwrap_or_default();
+
+            let mut docs = Vec::with_capacity(embedded.len());
+            let mut malformed = 0;
+            for value in embedded {
+                match mongodb::bson::from_bson::<Conversation>(value) {
+                    Ok(conversation) => docs.push(ConversationDoc {
+                        discord_id: discord_id.clone(),
+                        conversation,
+                    }),
+                    Err(e) => {
+                        malformed += 1;
+                        eprintln!("[ERROR] Malformed embedded conversation for {}: {}", discord_id, e);
+                    }
+                }
+            }
+            if malformed > 0 {
+                println!(
+                    "[WARN] Not migrating {} ({} malformed conversations); fix them in the users collection",
+                    discord_id, malformed
+                );
+                continue;
+            }
+
+            if !docs.is_empty() {
+                migrated_turns += docs.len();
+                self.conversations
+                    .insert_many(docs, None)
+                    .await
+                    .map_err(|e| format!("Failed to copy conversations for {}: {:?}", discord_id, e))?;
+            }
+
+            raw_users
+                .update_one(
+                    // By _id: older databases may hold several documents with this discord_id
+                    doc! {"_id": user_id},
+                    doc! {"$unset": {"conversations": ""}},
+                    None,
+                )
+                .await
+                .map_err(|e| format!("Failed to clear embedded conversations for {}: {:?}", discord_id, e))?;
+            migrated_users += 1;
+        }
+
+        if migrated_users > 0 {
+            println!(
+                "[LOG] Migrated {} conversations from {} users into the conversations collection",
+                migrated_turns, migrated_users
+            );
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserStore for MongoStore {
+    async fn find_user(&self, discord_id: u64) -> StoreResult<Option<User>> {
+        self.users
+            .find_one(doc! {"discord_id": discord_id.to_string()}, None)
+            .await
+            .map_err(|e| format!("Failed to query user collection: {:?}", e))
+    }
+
+    async fn insert_user(&self, user: User) -> StoreResult<()> {
+        self.users
+            .insert_one(user, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to insert user: {:?}", e))
+    }
+
+    async fn delete_user(&self, discord_id: u64) -> StoreResult<bool> {
+        // Transactions need a replica set, so the related records go first and the user
+        // document (with its embedded guild nicknames) last: if a step fails the user
+        // still exists and running /unregister again finishes the job
+        let owner = discord_id.to_string();
+        self.conversations
+            .delete_many(doc! {"discord_id": &owner}, None)
+            .await
+            .map_err(|e| format!("Failed to delete conversations: {:?}", e))?;
+        self.onboarding
+            .delete_one(doc! {"discord_id": &owner}, None)
+            .await
+            .map_err(|e| format!("Failed to clear onboarding: {:?}", e))?;
+        self.threads
+            .delete_many(doc! {"owner_id": &owner}, None)
+            .await
+            .map_err(|e| format!("Failed to delete threads: {:?}", e))?;
+        self.access
+            .delete_many(doc! {"kind": "user", "target_id": &owner}, None)
+            .await
+            .map_err(|e| format!("Failed to revoke chatbot access: {:?}", e))?;
+
+        let owned: Vec<Persona> = self
+            .personas
+            .find(doc! {"owner_id": &owner}, None)
+            .await
+            .map_err(|e| format!("Failed to fetch personas: {:?}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to read personas: {:?}", e))?;
+        let names: Vec<String> = owned.into_iter().map(|p| p.name).collect();
+        if !names.is_empty() {
+            self.users
+                .update_many(
+                    doc! {"active_persona": {"$in": &names}},
+                    doc! {"$unset": {"active_persona": ""}},
+                    None,
+                )
+                .await
+                .map_err(|e| format!("Failed to update active personas: {:?}", e))?;
+            self.personas
+                .delete_many(doc! {"owner_id": &owner}, None)
+                .await
+                .map_err(|e| format!("Failed to delete personas: {:?}", e))?;
+        }
+
+        self.users
+            .delete_one(doc! {"discord_id": discord_id.to_string()}, None)
+            .await
+            .map(|res| res.deleted_count > 0)
+            .map_err(|e| format!("Failed to delete user: {:?}", e))
+    }
+
+    async fn set_nickname(&self, discord_id: u64, nickname: String) -> StoreResult<bool> {
+        self.users
+            .update_one(
+                doc! {"discord_id": discord_id.to_string()},
+                doc! {"$set": {"nickname": nickname}},
+                None,
+            )
+            .await
+            .map(|res| res.matched_count > 0)
+            .map_err(|e| format!("Failed to update nickname: {:?}", e))
+    }
+
+    async fn set_guild_nickname(
+        &self,
+        discord_id: u64,
+        guild_id: u64,
+        nickname: Option<String>,
+    ) -> StoreResult<bool> {
+        let field = format!("guild_nicknames.{}", guild_id);
+        let update = match nickname {
+            Some(nickname) => doc! {"$set": {field: nickname}},
+            None => doc! {"$unset": {field: ""}},
+        };
+        self.users
+            .update_one(doc! {"discord_id": discord_id.to_string()}, update, None)
+            .await
+            .map(|res| res.matched_count > 0)
+            .map_err(|e| format!("Failed to update guild nickname: {:?}", e))
+    }
+
+    async fn set_active_persona(&self, discord_id: u64, persona: Option<String>) -> StoreResult<bool> {
+        let update = match persona {
+            Some(name) => doc! {"$set": {"active_persona": name}},
+            None => doc! {"$unset": {"active_persona": ""}},
+        };
+        self.users
+            .update_one(doc! {"discord_id": discord_id.to_string()}, update, None)
+            .await
+            .map(|res| res.matched_count > 0)
+            .map_err(|e| format!("Failed to update active persona: {:?}", e))
+    }
+}
+
+#[async_trait]
+impl ConversationStore for MongoStore {
+    async fn push_conversation(&self, discord_id: u64, conversation: Conversation) -> StoreResult<()> {
+        self.conversations
+            .insert_one(
+                ConversationDoc {
+                    discord_id: discord_id.to_string(),
+                    conversation,
+                },
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to save conversation: {:?}", e))
+    }
+
+    async fn push_conversations(&self, discord_id: u64, conversations: Vec<Conversation>) -> StoreResult<()> {
+        if conversations.is_empty() {
+            return Ok(());
+        }
+        let docs = conversations.into_iter().map(|conversation| ConversationDoc {
+            discord_id: discord_id.to_string(),
+            conversation,
+        });
+        self.conversations
+            .insert_many(docs, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to save conversations: {:?}", e))
+    }
+
+    async fn conversation_page(
+        &self,
+        query: &ConversationQuery,
+        offset: u64,
+        limit: u64,
+    ) -> StoreResult<Vec<Conversation>> {
+        let options = FindOptions::builder()
+            .sort(doc! {"timestamp": -1, "_id": -1})
+            .skip(offset)
+            .limit(limit as i64)
+            .build();
+
+        self.conversations
+            .find(conversation_filter(query), options)
+            .await
+            .map_err(|e| format!("Failed to fetch conversations: {:?}", e))?
+            .map_ok(|doc| doc.conversation)
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to read conversations: {:?}", e))
+    }
+
+    async fn count_conversations(&self, query: &ConversationQuery) -> StoreResult<u64> {
+        self.conversations
+            .count_documents(conversation_filter(query), None)
+            .await
+            .map_err(|e| format!("Failed to count conversations: {:?}", e))
+    }
+
+    async fn export_conversations(&self, discord_id: Option<u64>) -> StoreResult<Vec<(u64, Conversation)>> {
+        let filter = match discord_id {
+            Some(id) => doc! {"discord_id": id.to_string()},
+            None => doc! {},
+        };
+        let options = FindOptions::builder().sort(doc! {"timestamp": 1, "_id": 1}).build();
+
+        self.conversations
+            .find(filter, options)
+            .await
+            .map_err(|e| format!("Failed to fetch conversations: {:?}", e))?
+            .map_ok(|doc| (doc.discord_id.parse::<u64>().unwrap_or(0), doc.conversation))
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to read conversations: {:?}", e))
+    }
+
+    async fn delete_conversations(&self, discord_id: u64, count: Option<u64>) -> StoreResult<u64> {
+        let filter = doc! {"discord_id": discord_id.to_string()};
+        let filter = match count {
+            None => filter,
+            Some(count) => {
+                // Look up the newest `count` ids, then delete exactly those
+                let options = FindOptions::builder()
+                    .sort(doc! {"timestamp": -1, "_id": -1})
+                    .limit(count as i64)
+                    .projection(doc! {"_id": 1})
+                    .build();
+                let ids: Vec<mongodb::bson::Bson> = self
+                    .conversations
+                    .clone_with_type::<Document>()
+                    .find(filter, options)
+                    .await
+                    .map_err(|e| format!("Failed to find conversations to delete: {:?}", e))?
+                    .try_filter_map(|doc| async move { Ok(doc.get("_id").cloned()) })
+                    .try_collect()
+                    .await
+                    .map_err(|e| format!("Failed to read conversations to delete: {:?}", e))?;
+                doc! {"_id": {"$in": ids}}
+            }
+        };
+
+        self.conversations
+            .delete_many(filter, None)
+            .await
+            .map(|res| res.deleted_count)
+            .map_err(|e| format!("Failed to delete conversations: {:?}", e))
+    }
+}
+
+fn conversation_filter(query: &ConversationQuery) -> Document {
+    let mut filter = doc! {"discord_id": query.discord_id.to_string()};
+    if let Some(channel_id) = query.channel_id {
+        filter.insert("channel_id", channel_id.to_string());
+    }
+    if let Some(session_id) = &query.session_id {
+        filter.insert("session_id", session_id.clone());
+    } else if query.sessionless {
+        // Matches both a null and a missing session_id
+        filter.insert("session_id", Bson::Null);
+    }
+    filter
+}
+
+#[async_trait]
+impl GuildSettingsStore for MongoStore {
+    async fn get_guild_settings(&self, guild_id: u64) -> StoreResult<Option<GuildSettings>> {
+        self.guild_settings
+            .find_one(doc! {"guild_id": guild_id.to_string()}, None)
+            .await
+            .map_err(|e| format!("Failed to fetch guild settings: {:?}", e))
+    }
+
+    async fn ensure_guild_settings(&self, guild_id: u64, ai_channel_ids: &[u64]) -> StoreResult<bool> {
+        let ai_channel_ids: Vec<String> = ai_channel_ids.iter().map(|id| id.to_string()).collect();
+        self.guild_settings
+            .update_one(
+                doc! {"guild_id": guild_id.to_string()},
+                doc! {"$setOnInsert": {"ai_channel_ids": ai_channel_ids}},
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map(|res| res.upserted_id.is_some())
+            .map_err(|e| format!("Failed to create guild settings: {:?}", e))
+    }
+
+    async fn add_ai_channel(&self, guild_id: u64, channel_id: u64) -> StoreResult<()> {
+        self.guild_settings
+            .update_one(
+                doc! {"guild_id": guild_id.to_string()},
+                doc! {"$addToSet": {"ai_channel_ids": channel_id.to_string()}},
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to add AI channel: {:?}", e))
+    }
+
+    async fn set_system_prompt(&self, guild_id: u64, prompt: Option<String>) -> StoreResult<()> {
+        self.guild_settings
+            .update_one(
+                doc! {"guild_id": guild_id.to_string()},
+                doc! {"$set": {"system_prompt": prompt}},
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to set system prompt: {:?}", e))
+    }
+
+    async fn set_ai_chat(&self, guild_id: u64, enabled: bool) -> StoreResult<()> {
+        self.guild_settings
+            .update_one(
+                doc! {"guild_id": guild_id.to_string()},
+                doc! {"$set": {"features.ai_chat": enabled}},
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to set AI chat: {:?}", e))
+    }
+
+    async fn remove_ai_channel(&self, guild_id: u64, channel_id: u64) -> StoreResult<bool> {
+        self.guild_settings
+            .update_one(
+                doc! {"guild_id": guild_id.to_string()},
+                doc! {"$pull": {"ai_channel_ids": channel_id.to_string()}},
+                None,
+            )
+            .await
+            .map(|res| res.modified_count > 0)
+            .map_err(|e| format!("Failed to remove AI channel: {:?}", e))
+    }
+}
+
+#[async_trait]
+impl AuditStore for MongoStore {
+    async fn record_audit(&self, entry: AuditEntry) -> StoreResult<()> {
+        self.audit_log
+            .insert_one(entry, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write audit record: {:?}", e))
+    }
+}
+
+#[async_trait]
+impl OnboardingStore for MongoStore {
+    async fn get_onboarding(&self, discord_id: u64, now: i64) -> StoreResult<Option<OnboardingRecord>> {
+        self.onboarding
+            .find_one(doc! {"discord_id": discord_id.to_string(), "expires_at": {"$gt": now}}, None)
+            .await
+            .map_err(|e| format!("Failed to query onboarding: {:?}", e))
+    }
+
+    async fn save_onboarding(&self, record: OnboardingRecord, now: i64) -> StoreResult<()> {
+        self.onboarding
+            .delete_many(doc! {"expires_at": {"$lte": now}}, None)
+            .await
+            .map_err(|e| format!("Failed to purge expired onboarding: {:?}", e))?;
+
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.onboarding
+            .replace_one(doc! {"discord_id": &record.discord_id}, &record, options)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to save onboarding: {:?}", e))
+    }
+
+    async fn clear_onboarding(&self, discord_id: u64) -> StoreResult<()> {
+        self.onboarding
+            .delete_one(doc! {"discord_id": discord_id.to_string()}, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to clear onboarding: {:?}", e))
+    }
+}
+
+/// Filter matching exactly one grant
+fn grant_filter(grant: &AccessGrant) -> StoreResult<Document> {
+    mongodb::bson::to_document(grant).map_err(|e| format!("Failed to encode access grant: {:?}", e))
+}
+
+#[async_trait]
+impl AccessStore for MongoStore {
+    async fn list_access(&self, guild_id: u64) -> StoreResult<Vec<AccessGrant>> {
+        self.access
+            .find(doc! {"guild_id": guild_id.to_string()}, FindOptions::builder().sort(doc! {"_id": 1}).build())
+            .await
+            .map_err(|e| format!("Failed to fetch chatbot access: {:?}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to read chatbot access: {:?}", e))
+    }
+
+    async fn grant_access(&self, grant: AccessGrant) -> StoreResult<bool> {
+        let filter = grant_filter(&grant)?;
+        self.access
+            .update_one(
+                filter.clone(),
+                doc! {"$setOnInsert": filter},
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map(|res| res.upserted_id.is_some())
+            .map_err(|e| format!("Failed to grant chatbot access: {:?}", e))
+    }
+
+    async fn revoke_access(&self, grant: AccessGrant) -> StoreResult<bool> {
+        self.access
+            .delete_one(grant_filter(&grant)?, None)
+            .await
+            .map(|res| res.deleted_count > 0)
+            .map_err(|e| format!("Failed to revoke chatbot access: {:?}", e))
+    }
+}
+
+#[async_trait]
+impl PersonaStore for MongoStore {
+    async fn get_persona(&self, name: &str) -> StoreResult<Option<Persona>> {
+        self.personas
+            .find_one(doc! {"name": name}, None)
+            .await
+            .map_err(|e| format!("Failed to fetch persona: {:?}", e))
+    }
+
+    async fn list_personas(&self) -> StoreResult<Vec<Persona>> {
+        self.personas
+            .find(None, FindOptions::builder().sort(doc! {"name": 1}).build())
+            .await
+            .map_err(|e| format!("Failed to fetch personas: {:?}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to read personas: {:?}", e))
+    }
+
+    async fn create_persona(&self, persona: Persona) -> StoreResult<bool> {
+        let document =
+            mongodb::bson::to_document(&persona).map_err(|e| format!("Failed to encode persona: {:?}", e))?;
+        self.personas
+            .update_one(
+                doc! {"name": &persona.name},
+                doc! {"$setOnInsert": document},
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map(|res| res.upserted_id.is_some())
+            .map_err(|e| format!("Failed to create persona: {:?}", e))
+    }
+
+    async fn update_persona(&self, persona: Persona) -> StoreResult<bool> {
+        self.personas
+            .replace_one(doc! {"name": &persona.name}, &persona, None)
+            .await
+            .map(|res| res.matched_count > 0)
+            .map_err(|e| format!("Failed to update persona: {:?}", e))
+    }
+}
+
+#[async_trait]
+impl ChannelPersonaStore for MongoStore {
+    async fn get_channel_persona(&self, channel_id: u64) -> StoreResult<Option<ChannelPersona>> {
+        self.channel_personas
+            .find_one(doc! {"channel_id": channel_id.to_string()}, None)
+            .await
+            .map_err(|e| format!("Failed to fetch channel persona: {:?}", e))
+    }
+
+    async fn save_channel_persona(&self, binding: ChannelPersona) -> StoreResult<()> {
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.channel_personas
+            .replace_one(doc! {"channel_id": &binding.channel_id}, &binding, options)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to save channel persona: {:?}", e))
+    }
+
+    async fn remove_channel_persona(&self, channel_id: u64) -> StoreResult<Option<ChannelPersona>> {
+        self.channel_personas
+            .find_one_and_delete(doc! {"channel_id": channel_id.to_string()}, None)
+            .await
+            .map_err(|e| format!("Failed to remove channel persona: {:?}", e))
+    }
+}
+
+#[async_trait]
+impl ThreadStore for MongoStore {
+    async fn get_thread(&self, thread_id: u64) -> StoreResult<Option<ChatThread>> {
+        self.threads
+            .find_one(doc! {"thread_id": thread_id.to_string()}, None)
+            .await
+            .map_err(|e| format!("Failed to fetch thread: {:?}", e))
+    }
+
+    async fn create_thread(&self, thread: ChatThread) -> StoreResult<()> {
+        self.threads
+            .insert_one(&thread, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to save thread: {:?}", e))
+    }
+
+    async fn set_thread_archived(&self, thread_id: u64, archived: bool) -> StoreResult<bool> {
+        self.threads
+            .update_one(
+                doc! {"thread_id": thread_id.to_string(), "archived": !archived},
+                doc! {"$set": {"archived": archived}},
+                None,
+            )
+            .await
+            .map(|res| res.modified_count > 0)
+            .map_err(|e| format!("Failed to update thread: {:?}", e))
+    }
+
+    async fn delete_thread(&self, thread_id: u64) -> StoreResult<bool> {
+        self.threads
+            .delete_one(doc! {"thread_id": thread_id.to_string()}, None)
+            .await
+            .map(|res| res.deleted_count > 0)
+            .map_err(|e| format!("Failed to delete thread: {:?}", e))
+    }
+
+    async fn find_open_thread(&self, owner_id: u64, parent_id: u64) -> StoreResult<Option<ChatThread>> {
+        self.threads
+            .find_one(
+                doc! {"owner_id": owner_id.to_string(), "parent_id": parent_id.to_string(), "archived": false},
+                FindOneOptions::builder().sort(doc! {"created_at": -1}).build(),
+            )
+            .await
+            .map_err(|e| format!("Failed to fetch thread: {:?}", e))
+    }
+
+    async fn thread_ids(&self) -> StoreResult<Vec<u64>> {
+        let threads: Vec<ChatThread> = self
+            .threads
+            .find(doc! {}, None)
+            .await
+            .map_err(|e| format!("Failed to fetch threads: {:?}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to read threads: {:?}", e))?;
+        Ok(threads.iter().filter_map(|t| t.thread_id.parse().ok()).collect())
+    }
+}
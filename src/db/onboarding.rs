@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// Where an unregistered user is in the onboarding flow
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum OnboardingState {
+    /// Started, waiting for a nickname
+    AwaitingNickname,
+    /// Nickname chosen, waiting for the user to confirm it
+    AwaitingConfirmation { nickname: String },
+}
+
+/// A user's unfinished registration
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OnboardingRecord {
+    pub discord_id: String,
+    #[serde(flatten)]
+    pub state: OnboardingState,
+    /// Unix time after which the record is ignored
+    pub expires_at: i64,
+}
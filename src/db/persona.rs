@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// A named character users can switch the AI into with /persona use
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Persona {
+    /// Lowercase identifier, unique across the bot
+    pub name: String,
+    /// Discord id of the creator; only they (or an admin) may edit it
+    pub owner_id: String,
+    /// Replaces the default system prompt, or follows a guild's own system prompt
+    pub system_prompt: String,
+    /// Overrides `backend.temperature` while the persona is active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Said by the AI when a user switches to the persona
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub greeting: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    pub created_at: i64,
+}
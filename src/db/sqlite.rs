@@ -0,0 +1,879 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serenity::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::db::access::{AccessGrant, AccessKind};
+use crate::db::audit::AuditEntry;
+use crate::db::channel_persona::ChannelPersona;
+use crate::db::guild_settings::{GuildFeatures, GuildSettings};
+use crate::db::onboarding::{OnboardingRecord, OnboardingState};
+use crate::db::persona::Persona;
+use crate::db::thread::ChatThread;
+use crate::db::user::{Conversation, User};
+use crate::db::{
+    AccessStore, AuditStore, ChannelPersonaStore, ConversationQuery, ConversationStore, GuildSettingsStore,
+    OnboardingStore, PersonaStore, StoreResult, ThreadStore, UserStore,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS users (
+    discord_id     TEXT PRIMARY KEY,
+    nickname       TEXT NOT NULL,
+    active_persona TEXT
+);
+CREATE TABLE IF NOT EXISTS user_guild_nicknames (
+    discord_id TEXT NOT NULL,
+    guild_id   TEXT NOT NULL,
+    nickname   TEXT NOT NULL,
+    PRIMARY KEY (discord_id, guild_id)
+);
+CREATE TABLE IF NOT EXISTS conversations (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    discord_id TEXT NOT NULL,
+    prompt     TEXT NOT NULL,
+    response   TEXT NOT NULL,
+    timestamp  INTEGER NOT NULL,
+    channel_id TEXT,
+    session_id TEXT
+);
+CREATE INDEX IF NOT EXISTS conversations_by_user ON conversations (discord_id, timestamp, id);
+CREATE INDEX IF NOT EXISTS conversations_by_session ON conversations (discord_id, session_id, id);
+CREATE TABLE IF NOT EXISTS guild_settings (
+    guild_id      TEXT PRIMARY KEY,
+    system_prompt TEXT,
+    ai_chat       INTEGER NOT NULL DEFAULT 1
+);
+CREATE TABLE IF NOT EXISTS guild_ai_channels (
+    guild_id   TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
+CREATE TABLE IF NOT EXISTS onboarding (
+    discord_id TEXT PRIMARY KEY,
+    state      TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS chatbot_access (
+    guild_id  TEXT NOT NULL,
+    kind      TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    PRIMARY KEY (guild_id, kind, target_id)
+);
+CREATE TABLE IF NOT EXISTS personas (
+    name          TEXT PRIMARY KEY,
+    owner_id      TEXT NOT NULL,
+    system_prompt TEXT NOT NULL,
+    temperature   REAL,
+    greeting      TEXT,
+    avatar_url    TEXT,
+    created_at    INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS channel_personas (
+    channel_id    TEXT PRIMARY KEY,
+    guild_id      TEXT NOT NULL,
+    persona       TEXT NOT NULL,
+    system_prompt TEXT NOT NULL,
+    temperature   REAL,
+    avatar_url    TEXT,
+    webhook_id    TEXT,
+    webhook_token TEXT
+);
+CREATE TABLE IF NOT EXISTS threads (
+    thread_id  TEXT PRIMARY KEY,
+    guild_id   TEXT NOT NULL,
+    parent_id  TEXT NOT NULL,
+    owner_id   TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    archived   INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS threads_by_owner ON threads (owner_id, parent_id);
+CREATE TABLE IF NOT EXISTS audit_log (
+    id        INTEGER PRIMARY KEY AUTOINCREMENT,
+    actor_id  TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    action    TEXT NOT NULL,
+    detail    TEXT NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+";
+
+/// Single-file SQLite storage for small self-hosted installs
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database at `path`; ":memory:" gives a throwaway database
+    pub fn open(path: &str) -> StoreResult<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open SQLite database '{}': {}", path, e))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to create SQLite schema: {}", e))?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Run a blocking query off the async runtime
+    async fn with_conn<T, F>(&self, f: F) -> StoreResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().map_err(|_| "SQLite connection poisoned".to_string())?;
+            f(&mut conn).map_err(|e| format!("SQLite error: {}", e))
+        })
+        .await
+        .map_err(|e| format!("SQLite task failed: {}", e))?
+    }
+}
+
+#[async_trait]
+impl UserStore for SqliteStore {
+    async fn find_user(&self, discord_id: u64) -> StoreResult<Option<User>> {
+        self.with_conn(move |conn| {
+            let user = conn
+                .query_row(
+                    "SELECT discord_id, nickname, active_persona FROM users WHERE discord_id = ?1",
+                    params![discord_id.to_string()],
+                    |row| {
+                        Ok(User {
+                            id: None,
+                            discord_id: row.get(0)?,
+                            nickname: row.get(1)?,
+                            guild_nicknames: HashMap::new(),
+                            active_persona: row.get(2)?,
+                        })
+                    },
+                )
+                .optional()?;
+            let Some(mut user) = user else {
+                return Ok(None);
+            };
+
+            let mut stmt =
+                conn.prepare("SELECT guild_id, nickname FROM user_guild_nicknames WHERE discord_id = ?1")?;
+            user.guild_nicknames = stmt
+                .query_map(params![discord_id.to_string()], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(Some(user))
+        })
+        .await
+    }
+
+    async fn insert_user(&self, user: User) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO users (discord_id, nickname, active_persona) VALUES (?1, ?2, ?3)",
+                params![user.discord_id, user.nickname, user.active_persona],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    async fn delete_user(&self, discord_id: u64) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "DELETE FROM conversations WHERE discord_id = ?1",
+                params![discord_id.to_string()],
+            )?;
+            tx.execute(
+                "DELETE FROM user_guild_nicknames WHERE discord_id = ?1",
+                params![discord_id.to_string()],
+            )?;
+            tx.execute("DELETE FROM onboarding WHERE discord_id = ?1", params![discord_id.to_string()])?;
+            tx.execute("DELETE FROM threads WHERE owner_id = ?1", params![discord_id.to_string()])?;
+            tx.execute(
+                "DELETE FROM chatbot_access WHERE kind = ?1 AND target_id = ?2",
+                params![access_kind_name(AccessKind::User), discord_id.to_string()],
+            )?;
+            tx.execute(
+                "UPDATE users SET active_persona = NULL
+                 WHERE active_persona IN (SELECT name FROM personas WHERE owner_id = ?1)",
+                params![discord_id.to_string()],
+            )?;
+            tx.execute("DELETE FROM personas WHERE owner_id = ?1", params![discord_id.to_string()])?;
+            let removed = tx.execute(
+                "DELETE FROM users WHERE discord_id = ?1",
+                params![discord_id.to_string()],
+            )?;
+            tx.commit()?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    async fn set_nickname(&self, discord_id: u64, nickname: String) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE users SET nickname = ?2 WHERE discord_id = ?1",
+                params![discord_id.to_string(), nickname],
+            )
+            .map(|updated| updated > 0)
+        })
+        .await
+    }
+
+    async fn set_guild_nickname(
+        &self,
+        discord_id: u64,
+        guild_id: u64,
+        nickname: Option<String>,
+    ) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            let exists = conn
+                .query_row(
+                    "SELECT 1 FROM users WHERE discord_id = ?1",
+                    params![discord_id.to_string()],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !exists {
+                return Ok(false);
+            }
+            match nickname {
+                Some(nickname) => conn.execute(
+                    "INSERT INTO user_guild_nicknames (discord_id, guild_id, nickname) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (discord_id, guild_id) DO UPDATE SET nickname = excluded.nickname",
+                    params![discord_id.to_string(), guild_id.to_string(), nickname],
+                )?,
+                None => conn.execute(
+                    "DELETE FROM user_guild_nicknames WHERE discord_id = ?1 AND guild_id = ?2",
+                    params![discord_id.to_string(), guild_id.to_string()],
+                )?,
+            };
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn set_active_persona(&self, discord_id: u64, persona: Option<String>) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE users SET active_persona = ?2 WHERE discord_id = ?1",
+                params![discord_id.to_string(), persona],
+            )
+            .map(|updated| updated > 0)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl ConversationStore for SqliteStore {
+    async fn push_conversation(&self, discord_id: u64, conversation: Conversation) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO conversations (discord_id, prompt, response, timestamp, channel_id, session_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    discord_id.to_string(),
+                    conversation.prompt,
+                    conversation.response,
+                    conversation.timestamp,
+                    conversation.channel_id,
+                    conversation.session_id
+                ],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    async fn push_conversations(&self, discord_id: u64, conversations: Vec<Conversation>) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT INTO conversations (discord_id, prompt, response, timestamp, channel_id, session_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                for conversation in conversations {
+                    stmt.execute(params![
+                        discord_id.to_string(),
+                        conversation.prompt,
+                        conversation.response,
+                        conversation.timestamp,
+                        conversation.channel_id,
+                        conversation.session_id
+                    ])?;
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn conversation_page(
+        &self,
+        query: &ConversationQuery,
+        offset: u64,
+        limit: u64,
+    ) -> StoreResult<Vec<Conversation>> {
+        let query = query.clone();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT prompt, response, timestamp, channel_id, session_id FROM conversations
+                 WHERE discord_id = ?1
+                   AND (?2 IS NULL OR channel_id = ?2)
+                   AND (?3 IS NULL OR session_id = ?3)
+                   AND (?4 = 0 OR session_id IS NULL)
+                 ORDER BY timestamp DESC, id DESC LIMIT ?5 OFFSET ?6",
+            )?;
+            let rows = stmt.query_map(
+                params![
+                    query.discord_id.to_string(),
+                    query.channel_id.map(|id| id.to_string()),
+                    query.session_id,
+                    query.sessionless,
+                    limit as i64,
+                    offset as i64
+                ],
+                row_to_conversation,
+            )?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn count_conversations(&self, query: &ConversationQuery) -> StoreResult<u64> {
+        let query = query.clone();
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM conversations
+                 WHERE discord_id = ?1
+                   AND (?2 IS NULL OR channel_id = ?2)
+                   AND (?3 IS NULL OR session_id = ?3)
+                   AND (?4 = 0 OR session_id IS NULL)",
+                params![
+                    query.discord_id.to_string(),
+                    query.channel_id.map(|id| id.to_string()),
+                    query.session_id,
+                    query.sessionless
+                ],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as u64)
+        })
+        .await
+    }
+
+    async fn export_conversations(&self, discord_id: Option<u64>) -> StoreResult<Vec<(u64, Conversation)>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT prompt, response, timestamp, channel_id, session_id, discord_id FROM conversations
+                 WHERE ?1 IS NULL OR discord_id = ?1
+                 ORDER BY timestamp ASC, id ASC",
+            )?;
+            let rows = stmt.query_map(params![discord_id.map(|id| id.to_string())], |row| {
+                let owner: String = row.get(5)?;
+                Ok((owner.parse::<u64>().unwrap_or(0), row_to_conversation(row)?))
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn delete_conversations(&self, discord_id: u64, count: Option<u64>) -> StoreResult<u64> {
+        self.with_conn(move |conn| {
+            // LIMIT -1 means "no limit" in SQLite
+            let limit = count.map(|c| c as i64).unwrap_or(-1);
+            conn.execute(
+                "DELETE FROM conversations WHERE id IN (
+                     SELECT id FROM conversations WHERE discord_id = ?1
+                     ORDER BY timestamp DESC, id DESC LIMIT ?2
+                 )",
+                params![discord_id.to_string(), limit],
+            )
+            .map(|deleted| deleted as u64)
+        })
+        .await
+    }
+}
+
+fn row_to_conversation(row: &rusqlite::Row) -> rusqlite::Result<Conversation> {
+    Ok(Conversation {
+        prompt: row.get(0)?,
+        response: row.get(1)?,
+        timestamp: row.get(2)?,
+        channel_id: row.get(3)?,
+        session_id: row.get(4)?,
+    })
+}
+
+#[async_trait]
+impl GuildSettingsStore for SqliteStore {
+    async fn get_guild_settings(&self, guild_id: u64) -> StoreResult<Option<GuildSettings>> {
+        self.with_conn(move |conn| {
+            let guild = guild_id.to_string();
+            let settings = conn
+                .query_row(
+                    "SELECT system_prompt, ai_chat FROM guild_settings WHERE guild_id = ?1",
+                    params![guild],
+                    |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, bool>(1)?)),
+                )
+                .optional()?;
+            let Some((system_prompt, ai_chat)) = settings else {
+                return Ok(None);
+            };
+
+            let mut stmt = conn.prepare(
+                "SELECT channel_id FROM guild_ai_channels WHERE guild_id = ?1 ORDER BY rowid",
+            )?;
+            let ai_channel_ids = stmt
+                .query_map(params![guild], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+
+            Ok(Some(GuildSettings {
+                id: None,
+                guild_id: guild,
+                ai_channel_ids,
+                system_prompt,
+                features: GuildFeatures { ai_chat },
+            }))
+        })
+        .await
+    }
+
+    async fn ensure_guild_settings(&self, guild_id: u64, ai_channel_ids: &[u64]) -> StoreResult<bool> {
+        let ai_channel_ids = ai_channel_ids.to_vec();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let created = tx.execute(
+                "INSERT OR IGNORE INTO guild_settings (guild_id) VALUES (?1)",
+                params![guild_id.to_string()],
+            )? > 0;
+            if created {
+                for channel_id in ai_channel_ids {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO guild_ai_channels (guild_id, channel_id) VALUES (?1, ?2)",
+                        params![guild_id.to_string(), channel_id.to_string()],
+                    )?;
+                }
+            }
+            tx.commit()?;
+            Ok(created)
+        })
+        .await
+    }
+
+    async fn add_ai_channel(&self, guild_id: u64, channel_id: u64) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT OR IGNORE INTO guild_settings (guild_id) VALUES (?1)",
+                params![guild_id.to_string()],
+            )?;
+            tx.execute(
+                "INSERT OR IGNORE INTO guild_ai_channels (guild_id, channel_id) VALUES (?1, ?2)",
+                params![guild_id.to_string(), channel_id.to_string()],
+            )?;
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn set_system_prompt(&self, guild_id: u64, prompt: Option<String>) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE guild_settings SET system_prompt = ?2 WHERE guild_id = ?1",
+                params![guild_id.to_string(), prompt],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    async fn set_ai_chat(&self, guild_id: u64, enabled: bool) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE guild_settings SET ai_chat = ?2 WHERE guild_id = ?1",
+                params![guild_id.to_string(), enabled],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    async fn remove_ai_channel(&self, guild_id: u64, channel_id: u64) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "DELETE FROM guild_ai_channels WHERE guild_id = ?1 AND channel_id = ?2",
+                params![guild_id.to_string(), channel_id.to_string()],
+            )
+            .map(|removed| removed > 0)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl AuditStore for SqliteStore {
+    async fn record_audit(&self, entry: AuditEntry) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO audit_log (actor_id, target_id, action, detail, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![entry.actor_id, entry.target_id, entry.action, entry.detail, entry.timestamp],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl OnboardingStore for SqliteStore {
+    async fn get_onboarding(&self, discord_id: u64, now: i64) -> StoreResult<Option<OnboardingRecord>> {
+        let row = self
+            .with_conn(move |conn| {
+                conn.query_row(
+                    "SELECT state, expires_at FROM onboarding WHERE discord_id = ?1 AND expires_at > ?2",
+                    params![discord_id.to_string(), now],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+                )
+                .optional()
+            })
+            .await?;
+
+        match row {
+            Some((state, expires_at)) => {
+                let state: OnboardingState = serde_json::from_str(&state)
+                    .map_err(|e| format!("Corrupt onboarding state for {}: {}", discord_id, e))?;
+                Ok(Some(OnboardingRecord {
+                    discord_id: discord_id.to_string(),
+                    state,
+                    expires_at,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn save_onboarding(&self, record: OnboardingRecord, now: i64) -> StoreResult<()> {
+        let state = serde_json::to_string(&record.state)
+            .map_err(|e| format!("Failed to serialize onboarding state: {}", e))?;
+        self.with_conn(move |conn| {
+            conn.execute("DELETE FROM onboarding WHERE expires_at <= ?1", params![now])?;
+            conn.execute(
+                "INSERT INTO onboarding (discord_id, state, expires_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (discord_id) DO UPDATE SET state = excluded.state, expires_at = excluded.expires_at",
+                params![record.discord_id, state, record.expires_at],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    async fn clear_onboarding(&self, discord_id: u64) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            conn.execute("DELETE FROM onboarding WHERE discord_id = ?1", params![discord_id.to_string()])
+                .map(|_| ())
+        })
+        .await
+    }
+}
+
+fn access_kind_name(kind: AccessKind) -> &'static str {
+    match kind {
+        AccessKind::Role => "role",
+        AccessKind::User => "user",
+    }
+}
+
+#[async_trait]
+impl AccessStore for SqliteStore {
+    async fn list_access(&self, guild_id: u64) -> StoreResult<Vec<AccessGrant>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT kind, target_id FROM chatbot_access WHERE guild_id = ?1 ORDER BY rowid",
+            )?;
+            let rows = stmt.query_map(params![guild_id.to_string()], |row| {
+                let kind = match row.get::<_, String>(0)?.as_str() {
+                    "role" => AccessKind::Role,
+                    _ => AccessKind::User,
+                };
+                Ok(AccessGrant {
+                    guild_id: guild_id.to_string(),
+                    kind,
+                    target_id: row.get(1)?,
+                })
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn grant_access(&self, grant: AccessGrant) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO chatbot_access (guild_id, kind, target_id) VALUES (?1, ?2, ?3)",
+                params![grant.guild_id, access_kind_name(grant.kind), grant.target_id],
+            )
+            .map(|inserted| inserted > 0)
+        })
+        .await
+    }
+
+    async fn revoke_access(&self, grant: AccessGrant) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "DELETE FROM chatbot_access WHERE guild_id = ?1 AND kind = ?2 AND target_id = ?3",
+                params![grant.guild_id, access_kind_name(grant.kind), grant.target_id],
+            )
+            .map(|removed| removed > 0)
+        })
+        .await
+    }
+}
+
+fn row_to_persona(row: &rusqlite::Row) -> rusqlite::Result<Persona> {
+    Ok(Persona {
+        name: row.get(0)?,
+        owner_id: row.get(1)?,
+        system_prompt: row.get(2)?,
+        temperature: row.get::<_, Option<f64>>(3)?.map(|t| t as f32),
+        greeting: row.get(4)?,
+        avatar_url: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+#[async_trait]
+impl PersonaStore for SqliteStore {
+    async fn get_persona(&self, name: &str) -> StoreResult<Option<Persona>> {
+        let name = name.to_string();
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT name, owner_id, system_prompt, temperature, greeting, avatar_url, created_at
+                 FROM personas WHERE name = ?1",
+                params![name],
+                row_to_persona,
+            )
+            .optional()
+        })
+        .await
+    }
+
+    async fn list_personas(&self) -> StoreResult<Vec<Persona>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT name, owner_id, system_prompt, temperature, greeting, avatar_url, created_at
+                 FROM personas ORDER BY name",
+            )?;
+            let rows = stmt.query_map([], row_to_persona)?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn create_persona(&self, persona: Persona) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO personas
+                     (name, owner_id, system_prompt, temperature, greeting, avatar_url, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    persona.name,
+                    persona.owner_id,
+                    persona.system_prompt,
+                    persona.temperature.map(|t| t as f64),
+                    persona.greeting,
+                    persona.avatar_url,
+                    persona.created_at
+                ],
+            )
+            .map(|inserted| inserted > 0)
+        })
+        .await
+    }
+
+    async fn update_persona(&self, persona: Persona) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE personas SET owner_id = ?2, system_prompt = ?3, temperature = ?4, greeting = ?5,
+                     avatar_url = ?6, created_at = ?7
+                 WHERE name = ?1",
+                params![
+                    persona.name,
+                    persona.owner_id,
+                    persona.system_prompt,
+                    persona.temperature.map(|t| t as f64),
+                    persona.greeting,
+                    persona.avatar_url,
+                    persona.created_at
+                ],
+            )
+            .map(|updated| updated > 0)
+        })
+        .await
+    }
+}
+
+fn row_to_channel_persona(row: &rusqlite::Row) -> rusqlite::Result<ChannelPersona> {
+    Ok(ChannelPersona {
+        channel_id: row.get(0)?,
+        guild_id: row.get(1)?,
+        persona: row.get(2)?,
+        system_prompt: row.get(3)?,
+        temperature: row.get(4)?,
+        avatar_url: row.get(5)?,
+        webhook_id: row.get(6)?,
+        webhook_token: row.get(7)?,
+    })
+}
+
+#[async_trait]
+impl ChannelPersonaStore for SqliteStore {
+    async fn get_channel_persona(&self, channel_id: u64) -> StoreResult<Option<ChannelPersona>> {
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT channel_id, guild_id, persona, system_prompt, temperature, avatar_url, webhook_id, webhook_token
+                 FROM channel_personas WHERE channel_id = ?1",
+                params![channel_id.to_string()],
+                row_to_channel_persona,
+            )
+            .optional()
+        })
+        .await
+    }
+
+    async fn save_channel_persona(&self, binding: ChannelPersona) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO channel_personas (channel_id, guild_id, persona, system_prompt, temperature, avatar_url,
+                     webhook_id, webhook_token)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT (channel_id) DO UPDATE SET guild_id = excluded.guild_id, persona = excluded.persona,
+                     system_prompt = excluded.system_prompt, temperature = excluded.temperature,
+                     avatar_url = excluded.avatar_url, webhook_id = excluded.webhook_id,
+                     webhook_token = excluded.webhook_token",
+                params![
+                    binding.channel_id,
+                    binding.guild_id,
+                    binding.persona,
+                    binding.system_prompt,
+                    binding.temperature,
+                    binding.avatar_url,
+                    binding.webhook_id,
+                    binding.webhook_token
+                ],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    async fn remove_channel_persona(&self, channel_id: u64) -> StoreResult<Option<ChannelPersona>> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let binding = tx
+                .query_row(
+                    "SELECT channel_id, guild_id, persona, system_prompt, temperature, avatar_url, webhook_id, webhook_token
+                     FROM channel_personas WHERE channel_id = ?1",
+                    params![channel_id.to_string()],
+                    row_to_channel_persona,
+                )
+                .optional()?;
+            tx.execute("DELETE FROM channel_personas WHERE channel_id = ?1", params![channel_id.to_string()])?;
+            tx.commit()?;
+            Ok(binding)
+        })
+        .await
+    }
+}
+
+fn row_to_thread(row: &rusqlite::Row) -> rusqlite::Result<ChatThread> {
+    Ok(ChatThread {
+        thread_id: row.get(0)?,
+        guild_id: row.get(1)?,
+        parent_id: row.get(2)?,
+        owner_id: row.get(3)?,
+        created_at: row.get(4)?,
+        archived: row.get(5)?,
+    })
+}
+
+#[async_trait]
+impl ThreadStore for SqliteStore {
+    async fn get_thread(&self, thread_id: u64) -> StoreResult<Option<ChatThread>> {
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT thread_id, guild_id, parent_id, owner_id, created_at, archived
+                 FROM threads WHERE thread_id = ?1",
+                params![thread_id.to_string()],
+                row_to_thread,
+            )
+            .optional()
+        })
+        .await
+    }
+
+    async fn create_thread(&self, thread: ChatThread) -> StoreResult<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO threads (thread_id, guild_id, parent_id, owner_id, created_at, archived)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    thread.thread_id,
+                    thread.guild_id,
+                    thread.parent_id,
+                    thread.owner_id,
+                    thread.created_at,
+                    thread.archived
+                ],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    async fn set_thread_archived(&self, thread_id: u64, archived: bool) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE threads SET archived = ?2 WHERE thread_id = ?1 AND archived != ?2",
+                params![thread_id.to_string(), archived],
+            )
+            .map(|changed| changed > 0)
+        })
+        .await
+    }
+
+    async fn delete_thread(&self, thread_id: u64) -> StoreResult<bool> {
+        self.with_conn(move |conn| {
+            conn.execute("DELETE FROM threads WHERE thread_id = ?1", params![thread_id.to_string()])
+                .map(|deleted| deleted > 0)
+        })
+        .await
+    }
+
+    async fn find_open_thread(&self, owner_id: u64, parent_id: u64) -> StoreResult<Option<ChatThread>> {
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT thread_id, guild_id, parent_id, owner_id, created_at, archived
+                 FROM threads WHERE owner_id = ?1 AND parent_id = ?2 AND archived = 0
+                 ORDER BY created_at DESC LIMIT 1",
+                params![owner_id.to_string(), parent_id.to_string()],
+                row_to_thread,
+            )
+            .optional()
+        })
+        .await
+    }
+
+    async fn thread_ids(&self) -> StoreResult<Vec<u64>> {
+        let ids: Vec<String> = self
+            .with_conn(|conn| {
+                let mut stmt = conn.prepare("SELECT thread_id FROM threads")?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                rows.collect()
+            })
+            .await?;
+        Ok(ids.iter().filter_map(|id| id.parse().ok()).collect())
+    }
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// A thread the bot opened for one user's conversation (see `[threads]` in the config)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChatThread {
+    pub thread_id: String,
+    pub guild_id: String,
+    /// The AI channel the thread was started in
+    pub parent_id: String,
+    /// The user whose message started the thread; only they are answered in it
+    pub owner_id: String,
+    pub created_at: i64,
+    /// Set once the thread has been archived and summarised
+    #[serde(default)]
+    pub archived: bool,
+}
@@ -1,12 +1,20 @@
-use mongodb::bson::{doc, oid::ObjectId};
-use mongodb::Collection;
+use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Conversation {
     pub prompt: String,
     pub response: String,
     pub timestamp: i64,
+
+    /// Channel the exchange happened in (missing on records migrated from the old embedded array)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+
+    /// Groups turns that share a context, e.g. a thread
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,31 +25,20 @@ pub struct User {
     pub discord_id: String,
     pub nickname: String,
 
-    #[serde(default)]
-    pub conversations: Vec<Conversation>,
-}
+    /// Per-guild overrides of `nickname`, keyed by guild id
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub guild_nicknames: HashMap<String, String>,
 
-/// Returns the Mongo collection for users
-pub fn get_user_collection(client: &mongodb::Client) -> Collection<User> {
-    client
-        .database("discord_bot")
-        .collection::<User>("users")
+    /// Name of the persona the AI plays when talking to this user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_persona: Option<String>,
 }
 
-/// Fetch nickname by Discord ID
-pub async fn get_nickname_by_discord_id(
-    collection: &Collection<User>,
-    discord_id: u64,
-) -> Option<String> {
-    match collection
-        .find_one(doc! {"discord_id": discord_id.to_string()}, None)
-        .await
-    {
-        Ok(Some(user)) => Some(user.nickname),
-        Ok(None) => None,
-        Err(e) => {
-            eprintln!("[ERROR] Failed to fetch nickname: {:?}", e);
-            None
-        }
+impl User {
+    /// The nickname to use in a guild, falling back to the global one
+    pub fn nickname_in(&self, guild_id: Option<u64>) -> &str {
+        guild_id
+            .and_then(|id| self.guild_nicknames.get(&id.to_string()))
+            .unwrap_or(&self.nickname)
     }
 }
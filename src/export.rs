@@ -0,0 +1,291 @@
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::db::user::Conversation;
+
+/// File formats /export can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Our own format: a JSON array of conversations, importable with /import
+    Json,
+    /// Human-readable transcript
+    Markdown,
+    /// JSONL, one `{"conversations": [...]}` record per user/channel/session
+    ShareGpt,
+    /// JSONL, one `{"instruction", "input", "output"}` record per turn
+    Alpaca,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "json" => Some(ExportFormat::Json),
+            "markdown" => Some(ExportFormat::Markdown),
+            "sharegpt" => Some(ExportFormat::ShareGpt),
+            "alpaca" => Some(ExportFormat::Alpaca),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Markdown => "md",
+            ExportFormat::ShareGpt | ExportFormat::Alpaca => "jsonl",
+        }
+    }
+}
+
+/// One conversation in our own export format
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExportedConversation {
+    /// Ignored on import; turns always go to the caller's history
+    #[serde(default)]
+    pub discord_id: String,
+    #[serde(flatten)]
+    pub conversation: Conversation,
+}
+
+/// Serialize conversations (oldest first) in the requested format
+pub fn render(format: ExportFormat, conversations: &[(u64, Conversation)]) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => to_json(conversations),
+        ExportFormat::Markdown => Ok(to_markdown(conversations)),
+        ExportFormat::ShareGpt => to_sharegpt(conversations),
+        ExportFormat::Alpaca => to_alpaca(conversations),
+    }
+}
+
+fn to_json(conversations: &[(u64, Conversation)]) -> Result<String, String> {
+    let exported: Vec<ExportedConversation> = conversations
+        .iter()
+        .map(|(id, c)| ExportedConversation {
+            discord_id: id.to_string(),
+            conversation: c.clone(),
+        })
+        .collect();
+    serde_json::to_string_pretty(&exported).map_err(|e| format!("Failed to serialize export: {}", e))
+}
+
+fn to_markdown(conversations: &[(u64, Conversation)]) -> String {
+    let mut out = String::from("# Conversation export\n");
+    let mut current_user = None;
+    for (id, c) in conversations {
+        if current_user != Some(*id) {
+            out.push_str(&format!("\n## User {}\n", id));
+            current_user = Some(*id);
+        }
+        let when = Utc
+            .timestamp_opt(c.timestamp, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_else(|| c.timestamp.to_string());
+        out.push_str(&format!(
+            "\n### {}\n\n**User:** {}\n\n**AI:** {}\n",
+            when,
+            c.prompt.trim(),
+            c.response.trim()
+        ));
+    }
+    out
+}
+
+/// A conversation thread: same user, channel and session
+type ThreadKey<'a> = (u64, Option<&'a str>, Option<&'a str>);
+
+fn to_sharegpt(conversations: &[(u64, Conversation)]) -> Result<String, String> {
+    // Group turns into threads so the model sees them as one multi-turn conversation
+    let mut threads: Vec<(ThreadKey, Vec<serde_json::Value>)> = Vec::new();
+    for (id, c) in conversations {
+        let key = (*id, c.channel_id.as_deref(), c.session_id.as_deref());
+        let messages = match threads.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, messages)) => messages,
+            None => {
+                threads.push((key, Vec::new()));
+                &mut threads.last_mut().unwrap().1
+            }
+        };
+        messages.push(json!({"from": "human", "value": c.prompt}));
+        messages.push(json!({"from": "gpt", "value": c.response}));
+    }
+
+    to_jsonl(threads.into_iter().map(|(_, messages)| json!({"conversations": messages})))
+}
+
+fn to_alpaca(conversations: &[(u64, Conversation)]) -> Result<String, String> {
+    to_jsonl(
+        conversations
+            .iter()
+            .map(|(_, c)| json!({"instruction": c.prompt, "input": "", "output": c.response})),
+    )
+}
+
+fn to_jsonl(records: impl Iterator<Item = serde_json::Value>) -> Result<String, String> {
+    let mut out = String::new();
+    for record in records {
+        let line = serde_json::to_string(&record).map_err(|e| format!("Failed to serialize export: {}", e))?;
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Parse an uploaded export back into conversations, oldest first.
+///
+/// Accepts our own JSON array (or JSONL of the same objects) and ShareGPT records,
+/// either as a JSON array or one per line. `now` stamps ShareGPT turns, which carry no time.
+pub fn parse_import(text: &str, now: i64) -> Result<Vec<Conversation>, String> {
+    let text = text.trim_start_matches('\u{feff}').trim();
+    if text.is_empty() {
+        return Err("The file is empty.".to_string());
+    }
+
+    let records: Vec<(usize, serde_json::Value)> = match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(serde_json::Value::Array(items)) => items.into_iter().enumerate().map(|(i, v)| (i + 1, v)).collect(),
+        Ok(single) => vec![(1, single)],
+        Err(_) => text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map(|v| (i + 1, v))
+                    .map_err(|e| format!("Line {} is not valid JSON: {}", i + 1, e))
+            })
+            .collect::<Result<_, _>>()?,
+    };
+
+    let mut conversations = Vec::new();
+    for (n, record) in records {
+        if record.get("conversations").is_some() {
+            conversations.extend(parse_sharegpt(n, &record, now)?);
+        } else {
+            let exported: ExportedConversation = serde_json::from_value(record)
+                .map_err(|e| format!("Entry {} is not a conversation: {}", n, e))?;
+            let conversation = exported.conversation;
+            if conversation.prompt.trim().is_empty() || conversation.response.trim().is_empty() {
+                return Err(format!("Entry {} has an empty prompt or response.", n));
+            }
+            conversations.push(conversation);
+        }
+    }
+
+    if conversations.is_empty() {
+        return Err("The file contains no conversations.".to_string());
+    }
+    Ok(conversations)
+}
+
+/// Turn a ShareGPT record into prompt/response pairs, skipping system messages
+fn parse_sharegpt(n: usize, record: &serde_json::Value, now: i64) -> Result<Vec<Conversation>, String> {
+    let messages = record["conversations"]
+        .as_array()
+        .ok_or_else(|| format!("Entry {}: `conversations` must be a list.", n))?;
+
+    let mut turns = Vec::new();
+    let mut prompt: Option<String> = None;
+    for message in messages {
+        let from = message["from"].as_str().unwrap_or_default();
+        let value = message["value"]
+            .as_str()
+            .ok_or_else(|| format!("Entry {}: every message needs a text `value`.", n))?;
+        match (from, prompt.take()) {
+            ("system", pending) => prompt = pending,
+            ("human" | "user", None) => prompt = Some(value.to_string()),
+            ("gpt" | "assistant", Some(p)) => turns.push(Conversation {
+                prompt: p,
+                response: value.to_string(),
+                timestamp: now,
+                channel_id: None,
+                session_id: None,
+            }),
+            ("human" | "user", Some(_)) | ("gpt" | "assistant", None) => {
+                return Err(format!("Entry {}: messages must alternate between human and gpt.", n))
+            }
+            (other, _) => return Err(format!("Entry {}: unknown speaker `{}`.", n, other)),
+        }
+    }
+
+    if prompt.is_some() {
+        return Err(format!("Entry {}: the last human message has no reply.", n));
+    }
+    Ok(turns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(prompt: &str, response: &str, channel: &str) -> Conversation {
+        Conversation {
+            prompt: prompt.to_string(),
+            response: response.to_string(),
+            timestamp: 0,
+            channel_id: Some(channel.to_string()),
+            session_id: None,
+        }
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let conversations = vec![(1, turn("hi", "hello", "10"))];
+        let text = render(ExportFormat::Json, &conversations).unwrap();
+        let parsed: Vec<ExportedConversation> = serde_json::from_str(&text).unwrap();
+        assert_eq!(parsed[0].discord_id, "1");
+        assert_eq!(parsed[0].conversation, conversations[0].1);
+    }
+
+    #[test]
+    fn sharegpt_groups_by_channel() {
+        let conversations = vec![
+            (1, turn("a", "b", "10")),
+            (1, turn("c", "d", "20")),
+            (1, turn("e", "f", "10")),
+        ];
+        let text = render(ExportFormat::ShareGpt, &conversations).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        let first = lines[0]["conversations"].as_array().unwrap();
+        assert_eq!(first.len(), 4);
+        assert_eq!(first[2], json!({"from": "human", "value": "e"}));
+    }
+
+    #[test]
+    fn alpaca_has_one_line_per_turn() {
+        let conversations = vec![(1, turn("a", "b", "10")), (2, turn("c", "d", "10"))];
+        let text = render(ExportFormat::Alpaca, &conversations).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(lines[1]).unwrap(),
+            json!({"instruction": "c", "input": "", "output": "d"})
+        );
+    }
+
+    #[test]
+    fn own_export_imports_back() {
+        let conversations = vec![(1, turn("hi", "hello", "10")), (1, turn("a", "b", "20"))];
+        let json = render(ExportFormat::Json, &conversations).unwrap();
+        let imported = parse_import(&json, 99).unwrap();
+        assert_eq!(imported, vec![conversations[0].1.clone(), conversations[1].1.clone()]);
+    }
+
+    #[test]
+    fn sharegpt_jsonl_imports() {
+        let conversations = vec![(1, turn("a", "b", "10")), (1, turn("c", "d", "10"))];
+        let jsonl = render(ExportFormat::ShareGpt, &conversations).unwrap();
+        let imported = parse_import(&jsonl, 99).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!((imported[1].prompt.as_str(), imported[1].response.as_str()), ("c", "d"));
+        assert_eq!(imported[1].timestamp, 99);
+    }
+
+    #[test]
+    fn invalid_imports_are_rejected() {
+        assert!(parse_import("", 0).is_err());
+        assert!(parse_import("[]", 0).is_err());
+        assert!(parse_import("{\"conversations\": [{\"from\": \"gpt\", \"value\": \"x\"}]}", 0).is_err());
+        assert!(parse_import("[{\"prompt\": \"\", \"response\": \"x\", \"timestamp\": 0}]", 0).is_err());
+        assert!(parse_import("not json\n", 0).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+/// Discord rejects messages longer than this many characters
+pub const MESSAGE_LIMIT: usize = 2000;
+
+const FENCE: &str = "```";
+
+/// Appended to a chunk that ends inside a code block
+const CLOSE_FENCE: &str = "\n```";
+
+/// Fence openers longer than this (odd info strings) are reopened as a bare fence
+const MAX_OPENER_LEN: usize = 24;
+
+/// How a finished response is delivered
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    /// Consecutive messages, each within `MESSAGE_LIMIT`
+    Messages(Vec<String>),
+    /// Too long for chat; sent as a markdown file
+    Attachment { notice: String, body: String },
+}
+
+/// Decide how to deliver `text`: split into messages, or attach it once it is longer
+/// than `attach_over` characters (0 never attaches)
+pub fn format_reply(text: &str, attach_over: usize) -> Reply {
+    let length = text.chars().count();
+    if attach_over > 0 && length > attach_over {
+        return Reply::Attachment {
+            notice: format!("📄 The response was {} characters long, so it is attached as a file.", length),
+            body: text.to_string(),
+        };
+    }
+    Reply::Messages(split_message(text, MESSAGE_LIMIT))
+}
+
+/// Split `text` into chunks of at most `limit` characters.
+///
+/// Cuts prefer paragraph breaks, then line breaks, then sentence ends, then spaces,
+/// and only split a word when nothing else fits. A chunk that ends inside a fenced code
+/// block is closed with a fence and the next chunk reopens it with the same language.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    // Room for the closing fence plus a reopened fence line has to remain
+    let limit = limit.max(MAX_OPENER_LEN * 4);
+    let mut chunks = Vec::new();
+    let mut rest = text.trim().to_string();
+
+    while !rest.is_empty() {
+        if rest.chars().count() <= limit {
+            chunks.push(rest);
+            break;
+        }
+
+        let window = &rest[..byte_offset(&rest, limit - CLOSE_FENCE.len())];
+        let (head, tail) = rest.split_at(find_cut(window));
+        let mut chunk = head.trim_end().to_string();
+
+        let next = match open_fence(head) {
+            Some(opener) => {
+                chunk.push_str(CLOSE_FENCE);
+                format!("{}\n{}", opener, tail.strip_prefix('\n').unwrap_or(tail))
+            }
+            None => tail.trim_start().to_string(),
+        };
+
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk);
+        }
+        rest = next;
+    }
+
+    chunks
+}
+
+/// Cut `text` to at most `limit` characters, ending in '…' when anything was dropped
+pub fn shorten(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(limit.saturating_sub(1)).collect();
+    short.push('…');
+    short
+}
+
+/// Byte offset of the `chars`-th character, or the end of `text`
+fn byte_offset(text: &str, chars: usize) -> usize {
+    text.char_indices().nth(chars).map_or(text.len(), |(i, _)| i)
+}
+
+/// Best place to end the first chunk, as a byte offset into `window`
+fn find_cut(window: &str) -> usize {
+    // Cuts in the first half would leave lots of tiny chunks behind
+    let min = window.len() / 2;
+
+    let mut in_code = false;
+    let mut line_start = 0;
+    let mut paragraph = None;
+    let mut line = None;
+    let mut code_line = None;
+
+    for (i, _) in window.match_indices('\n') {
+        let current = &window[line_start..i];
+        if current.trim_start().starts_with(FENCE) {
+            in_code = !in_code;
+        }
+        let cut = i + 1;
+        if cut > min {
+            if in_code {
+                code_line = Some(cut);
+            } else {
+                if current.trim().is_empty() {
+                    paragraph = Some(cut);
+                }
+                line = Some(cut);
+            }
+        }
+        line_start = cut;
+    }
+
+    let sentence = window
+        .rmatch_indices(['.', '!', '?'])
+        .map(|(i, _)| i + 1)
+        .find(|&end| end > min && window[end..].starts_with(' '))
+        .map(|end| end + 1);
+
+    let space = window.rfind(' ').filter(|&i| i > 0).map(|i| i + 1);
+
+    paragraph
+        .or(line)
+        .or(code_line)
+        .or(sentence)
+        .or(space)
+        .unwrap_or(window.len())
+}
+
+/// The fence line of a code block left open at the end of `text`
+fn open_fence(text: &str) -> Option<String> {
+    let mut open = None;
+    for line in text.lines() {
+        let line = line.trim_start();
+        if !line.starts_with(FENCE) {
+            continue;
+        }
+        open = match open {
+            Some(_) => None,
+            None => {
+                let opener = line.split_whitespace().next().unwrap_or(FENCE);
+                Some(if opener.len() <= MAX_OPENER_LEN { opener } else { FENCE }.to_string())
+            }
+        };
+    }
+    open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fences_balanced(chunk: &str) -> bool {
+        chunk.lines().filter(|l| l.trim_start().starts_with(FENCE)).count() % 2 == 0
+    }
+
+    #[test]
+    fn short_text_is_one_message() {
+        assert_eq!(split_message("  hello  ", 2000), vec!["hello"]);
+        assert_eq!(format_reply("hello", 6000), Reply::Messages(vec!["hello".to_string()]));
+    }
+
+    #[test]
+    fn shorten_counts_the_ellipsis() {
+        assert_eq!(shorten("hello", 5), "hello");
+        assert_eq!(shorten("hello!", 5), "hell…");
+        assert_eq!(shorten("ééééé", 3), "éé…");
+        assert_eq!(shorten("", 0), "");
+        assert_eq!(shorten("abc", 0).chars().count(), 1);
+    }
+
+    #[test]
+    fn prefers_paragraphs_then_sentences() {
+        let para = "word ".repeat(30).trim_end().to_string() + ".";
+        let text = format!("{}\n\n{}\n\n{}", para, para, para);
+        let chunks = split_message(&text, 350);
+        assert_eq!(chunks, vec![format!("{}\n\n{}", para, para), para.clone()]);
+
+        let text = "One sentence here. ".repeat(30);
+        for chunk in split_message(&text, 200) {
+            assert!(chunk.chars().count() <= 200);
+            assert!(chunk.ends_with('.'), "{:?} should end on a sentence", chunk);
+        }
+    }
+
+    #[test]
+    fn reopens_code_fences_across_chunks() {
+        let code: String = (0..80).map(|i| format!("let x{} = {};\n", i, i)).collect();
+        let text = format!("Here you go:\n\n```rust\n{}```\nDone.", code);
+        let chunks = split_message(&text, 400);
+
+        assert!(chunks.len() > 2);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 400);
+            assert!(fences_balanced(chunk), "unbalanced chunk: {:?}", chunk);
+        }
+        assert!(chunks[1].starts_with("```rust\n"));
+        // Every line of code survives intact
+        let joined = chunks.join("\n");
+        for i in 0..80 {
+            assert!(joined.contains(&format!("let x{} = {};\n", i, i)));
+        }
+    }
+
+    #[test]
+    fn hard_splits_unbroken_text_and_attaches_huge_replies() {
+        let text = "é".repeat(5000);
+        let chunks = split_message(&text, 2000);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 2000));
+        assert_eq!(chunks.concat(), text);
+
+        assert!(matches!(format_reply(&text, 4000), Reply::Attachment { .. }));
+        assert!(matches!(format_reply(&text, 0), Reply::Messages(_)));
+    }
+}
@@ -2,53 +2,239 @@ use serenity::async_trait;
 use serenity::model::prelude::*;
 use serenity::model::application::interaction::Interaction;
 use serenity::prelude::*;
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
-use mongodb::{Client as MongoClient, bson::doc};
-use tokio::sync::Mutex;
+use std::time::Duration;
 use chrono::Utc;
-use crate::db::user::{self, User, Conversation, get_user_collection, get_nickname_by_discord_id};
+use crate::backend::{ChatBackend, ChatRequest, TextStream};
+use crate::backend::image::ImageClient;
+use crate::config::Config;
+use crate::db::channel_persona::ChannelPersona;
+use crate::db::persona::Persona;
+use crate::db::thread::ChatThread;
+use crate::db::Store;
+use crate::db::user::Conversation;
+use crate::history::build_history;
+use crate::permissions;
+use crate::permissions::AdminRoleCache;
+use crate::postprocess::Pipeline;
+use crate::queue::{GenerationQueue, QueueFull};
+use crate::rate_limit::RateLimiter;
+use crate::streaming::{relay_stream, ReplyTarget};
+use crate::threads;
+use crate::webhooks::WebhookManager;
 
 pub struct Handler {
-    pub db_client: MongoClient,
-    pub pending_nicknames: Arc<Mutex<HashMap<u64, String>>>,
+    pub store: Arc<dyn Store>,
+    pub config: Arc<Config>,
+    pub backend: Arc<dyn ChatBackend>,
+    pub image_client: Arc<ImageClient>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub queue: Arc<GenerationQueue>,
+    pub postprocess: Arc<Pipeline>,
+    pub webhooks: Arc<WebhookManager>,
+    pub admin_roles: AdminRoleCache,
+    /// Ids of the threads the bot opened, checked before looking a channel up in the store
+    pub thread_ids: Mutex<HashSet<u64>>,
 }
 
 impl Handler {
-    /// Fetch nickname from DB
-    pub async fn fetch_nickname(&self, discord_id: u64) -> Option<String> {
-        let collection = get_user_collection(&self.db_client);
-        let nickname = get_nickname_by_discord_id(&collection, discord_id).await;
+    /// Fetch nickname from DB, preferring the user's nickname for this guild
+    pub async fn fetch_nickname(&self, discord_id: u64, guild_id: Option<u64>) -> Option<String> {
+        let nickname = match self.store.get_nickname(discord_id, guild_id).await {
+            Ok(nickname) => nickname,
+            Err(e) => {
+                eprintln!("[ERROR] Failed to fetch nickname: {}", e);
+                None
+            }
+        };
         println!(
             "[DEBUG] Fetched nickname from DB for Discord ID {}: {:?}",
             discord_id, nickname
         );
         nickname
     }
-}
 
-// -------------------------
-// Helper to clean AI response
-// -------------------------
-pub fn clean_ai_response(response: &str) -> String {
-    let mut text = response.to_string();
+    /// The user's active persona, if they picked one and it still exists
+    pub async fn fetch_persona(&self, discord_id: u64) -> Option<Persona> {
+        let name = match self.store.find_user(discord_id).await {
+            Ok(user) => user?.active_persona?,
+            Err(e) => {
+                eprintln!("[ERROR] Failed to fetch active persona: {}", e);
+                return None;
+            }
+        };
+        match self.store.get_persona(&name).await {
+            Ok(Some(persona)) => Some(persona),
+            Ok(None) => {
+                println!("[WARN] User {} has unknown persona '{}' selected", discord_id, name);
+                None
+            }
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                None
+            }
+        }
+    }
 
-    let re_role = regex::Regex::new(r"\[\w+\]\s*[!?,.\-–]*\s*").unwrap();
-    text = re_role.replace_all(&text, "").to_string();
+    /// The bot's record of a conversation thread, if `channel_id` is one
+    pub async fn fetch_thread(&self, channel_id: u64) -> Option<ChatThread> {
+        match self.store.get_thread(channel_id).await {
+            Ok(thread) => thread,
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                None
+            }
+        }
+    }
 
-    let re_action = regex::Regex::new(r"!\s*[^.!?]*").unwrap();
-    text = re_action.replace_all(&text, "").to_string();
+    /// Start a thread from the user's message in the AI channel and remember who it belongs to.
+    /// Returns None (answer in the channel) if Discord refuses, e.g. without Create Public Threads.
+    async fn open_thread(&self, ctx: &Context, msg: &Message, nickname: &str) -> Option<ChannelId> {
+        let minutes = self.config.threads.auto_archive_minutes;
+        let created = msg
+            .channel_id
+            .create_public_thread(&ctx.http, msg.id, |t| {
+                t.name(threads::thread_name(nickname, &msg.content)).auto_archive_duration(minutes)
+            })
+            .await;
+        let thread = match created {
+            Ok(thread) => thread,
+            Err(e) => {
+                eprintln!("[ERROR] Failed to create thread in channel {}: {:?}", msg.channel_id, e);
+                return None;
+            }
+        };
 
-    let re_leading_punct = regex::Regex::new(r"^[!?,.\-–\s]+").unwrap();
-    text = re_leading_punct.replace_all(&text, "").to_string();
+        let record = ChatThread {
+            thread_id: thread.id.0.to_string(),
+            guild_id: thread.guild_id.0.to_string(),
+            parent_id: msg.channel_id.0.to_string(),
+            owner_id: msg.author.id.0.to_string(),
+            created_at: Utc::now().timestamp(),
+            archived: false,
+        };
+        if let Err(e) = self.store.create_thread(record).await {
+            eprintln!("[ERROR] {}", e);
+        }
+        self.thread_ids.lock().await.insert(thread.id.0);
+        println!("[LOG] Opened thread {} for user {}", thread.id, msg.author.id);
+        Some(thread.id)
+    }
 
-    let re_space = regex::Regex::new(r"\s{2,}").unwrap();
-    text = re_space.replace_all(&text, " ").to_string();
+    /// The user's open thread in `parent`. Records of threads deleted or archived while
+    /// the bot was offline are cleaned up on the way.
+    async fn existing_thread(&self, ctx: &Context, owner: u64, parent: ChannelId) -> Option<ChannelId> {
+        let record = match self.store.find_open_thread(owner, parent.0).await {
+            Ok(record) => record?,
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                return None;
+            }
+        };
+        let thread_id = ChannelId(record.thread_id.parse().ok()?);
+        match ctx.http.get_channel(thread_id.0).await {
+            Ok(Channel::Guild(thread)) => match thread.thread_metadata {
+                Some(meta) if meta.archived => {
+                    if let Ok(true) = self.store.set_thread_archived(thread_id.0, true).await {
+                        self.summarize_thread(record, thread.name).await;
+                    }
+                    None
+                }
+                _ => Some(thread_id),
+            },
+            Ok(_) => None,
+            Err(SerenityError::Http(e)) if e.status_code().map(|s| s.as_u16()) == Some(404) => {
+                println!("[LOG] Thread {} was deleted, forgetting it", thread_id);
+                self.thread_ids.lock().await.remove(&thread_id.0);
+                if let Err(e) = self.store.delete_thread(thread_id.0).await {
+                    eprintln!("[ERROR] {}", e);
+                }
+                None
+            }
+            Err(e) => {
+                // Can't tell whether it still exists; point the user to it rather than open another
+                eprintln!("[ERROR] Failed to fetch thread {}: {:?}", thread_id, e);
+                Some(thread_id)
+            }
+        }
+    }
+
+    /// Queue a summary of an archived thread and save it to the owner's history
+    async fn summarize_thread(&self, thread: ChatThread, name: String) {
+        let Ok(owner) = thread.owner_id.parse::<u64>() else {
+            return;
+        };
+        let conversations = match self
+            .store
+            .recent_session_conversations(owner, &thread.thread_id, threads::SUMMARY_MAX_TURNS)
+            .await
+        {
+            Ok(conversations) if !conversations.is_empty() => conversations,
+            Ok(_) => return,
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                return;
+            }
+        };
+
+        let guild_id = thread.guild_id.parse::<u64>().ok();
+        let request = ChatRequest {
+            message: threads::SUMMARY_PROMPT.to_string(),
+            nickname: self
+                .fetch_nickname(owner, guild_id)
+                .await
+                .unwrap_or_else(|| "the user".to_string()),
+            system_prompt: None,
+            persona: None,
+            temperature: None,
+            history: build_history(&conversations, &self.config.history),
+        };
+        let fallback = threads::fallback_summary(&conversations);
+
+        let store = self.store.clone();
+        let config = self.config.clone();
+        let backend = self.backend.clone();
+        let postprocess = self.postprocess.clone();
+        let record = thread.clone();
+        let title = name.clone();
+        let job = Box::pin(async move {
+            let summary = match backend.chat(&request).await {
+                Ok(text) => postprocess.for_context(guild_id, &config.backend.model).apply(&text),
+                Err(e) => {
+                    eprintln!("[ERROR] Failed to summarise thread {}: {}", record.thread_id, e);
+                    String::new()
+                }
+            };
+            let summary = if summary.trim().is_empty() { fallback } else { summary };
+            let conversation = threads::summary_conversation(&record, &title, summary, Utc::now().timestamp());
+            match store.push_conversation(owner, conversation).await {
+                Ok(()) => println!("[LOG] Saved summary of thread {} for user {}", record.thread_id, owner),
+                Err(e) => eprintln!("[ERROR] {}", e),
+            }
+        });
 
-    let re_newline = regex::Regex::new(r"\s*\n\s*").unwrap();
-    text = re_newline.replace_all(&text, "\n").to_string();
+        if self.queue.submit(owner, job).is_err() {
+            // Keep a record of the thread even when the backend is too busy to summarise it
+            println!("[LOG] Queue full, saving thread {} without a summary", thread.thread_id);
+            let summary = threads::fallback_summary(&conversations);
+            let conversation = threads::summary_conversation(&thread, &name, summary, Utc::now().timestamp());
+            if let Err(e) = self.store.push_conversation(owner, conversation).await {
+                eprintln!("[ERROR] {}", e);
+            }
+        }
+    }
 
-    text.trim().to_string()
+    /// The persona bound to this channel with /channel-persona
+    pub async fn fetch_channel_persona(&self, channel_id: u64) -> Option<ChannelPersona> {
+        match self.store.get_channel_persona(channel_id).await {
+            Ok(binding) => binding,
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                None
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -58,70 +244,96 @@ impl EventHandler for Handler {
             return;
         }
 
-        // Only process messages from the specified channel
-        const CHAT_CHANNEL_ID: u64 = 1413865642053992459;
-        if msg.channel_id.0 != CHAT_CHANNEL_ID {
+        // Only process messages from this guild's AI channels. Guilds without stored
+        // settings fall back to the channels listed in the config file.
+        let guild_settings = match msg.guild_id {
+            Some(guild_id) => match self.store.get_guild_settings(guild_id.0).await {
+                Ok(settings) => settings,
+                Err(e) => {
+                    eprintln!("[ERROR] {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let listening = match &guild_settings {
+            Some(settings) => settings.features.ai_chat && settings.listens_in(msg.channel_id.0),
+            None => self.config.discord.ai_channel_ids.contains(&msg.channel_id.0),
+        };
+
+        // Messages in a thread the bot opened are answered too, but only for its owner
+        let ai_chat = guild_settings.as_ref().is_none_or(|settings| settings.features.ai_chat);
+        let thread = if listening
+            || !self.config.threads.enabled
+            || !ai_chat
+            || !self.thread_ids.lock().await.contains(&msg.channel_id.0)
+        {
+            None
+        } else {
+            self.fetch_thread(msg.channel_id.0).await
+        };
+        if !listening && thread.is_none() {
             return;
         }
 
-    let discord_id = msg.author.id.0;
-    let collection = get_user_collection(&self.db_client);
+        let discord_id = msg.author.id.0;
+        if thread.as_ref().is_some_and(|thread| thread.owner_id != discord_id.to_string()) {
+            return;
+        }
 
         // Step 1: check DB
-        let user_exists = collection
-            .find_one(doc! {"discord_id": discord_id.to_string()}, None)
+        let user_exists = self
+            .store
+            .find_user(discord_id)
             .await
             .unwrap_or(None)
             .is_some();
 
         // Step 2: onboarding
         if !user_exists {
-            if msg.content.starts_with("!start") {
-                let _ = msg.channel_id.say(&ctx.http, "Welcome! Please reply with your desired bot nickname.").await;
-            } else if msg.content.starts_with("!nickname ") {
-                let nickname = msg.content[10..].trim().to_string();
-                {
-                    let mut pending = self.pending_nicknames.lock().await;
-                    pending.insert(discord_id, nickname.clone());
-                }
-                println!("[LOG] Pending nickname stored: {}", nickname);
-                let _ = msg.channel_id.say(&ctx.http, format!("You chose '{}'. Type !confirm to register.", nickname)).await;
-            } else if msg.content.starts_with("!confirm") {
-                let nickname_opt = {
-                    let mut pending = self.pending_nicknames.lock().await;
-                    pending.remove(&discord_id)
-                };
-
-                if let Some(nickname) = nickname_opt {
-                    let user = User {
-                        id: None,
-                        discord_id: discord_id.to_string(),
-                        nickname: nickname.clone(),
-                        conversations: Vec::new(),
-                    };
-                    match collection.insert_one(user, None).await {
-                        Ok(_) => {
-                            println!("[LOG] User {} added to DB with nickname '{}'", discord_id, nickname);
-                            let _ = msg.channel_id.say(&ctx.http, format!("You have been added as '{}'. You can now chat with the AI!", nickname)).await;
-                        }
-                        Err(e) => {
-                            eprintln!("[ERROR] Failed to insert user: {:?}", e);
-                            let _ = msg.channel_id.say(&ctx.http, "Failed to add to DB.").await;
-                        }
-                    }
-                } else {
-                    let _ = msg.channel_id.say(&ctx.http, "No pending nickname found. Use !nickname <your_nickname> first.").await;
-                }
-            } else {
-                let _ = msg.channel_id.say(&ctx.http, "You must complete onboarding first! Use !start or /setup-bot.").await;
+            let reply = crate::onboarding::handle_text(
+                self.store.as_ref(),
+                discord_id,
+                &msg.content,
+                &self.config.onboarding,
+            )
+            .await;
+            let result = msg
+                .channel_id
+                .send_message(&ctx.http, |m| m.content(&reply.content).set_components(reply.components()))
+                .await;
+            if let Err(e) = result {
+                eprintln!("[ERROR] Failed to send onboarding prompt: {:?}", e);
             }
 
             println!("[LOG] AI blocked for user {} because they are not confirmed.", discord_id);
             return;
         }
 
+        // Step 2b: one backend request per token; admins may be exempt
+        let roles = msg.member.as_ref().map(|m| m.roles.clone()).unwrap_or_default();
+        if let Err(cooldown) = self.rate_limiter.check(msg.author.id, msg.guild_id, &roles) {
+            let exempt = self.rate_limiter.exempts_admins()
+                && match msg.guild_id {
+                    Some(guild_id) => {
+                        self.admin_roles
+                            .is_admin_by_roles(&ctx.http, guild_id, &roles, &self.config.discord)
+                            .await
+                    }
+                    None => false,
+                };
+            if !exempt {
+                println!("[LOG] Rate limited user {} for {:?}", discord_id, cooldown.retry_after);
+                // Only tell them once per cooldown so the limiter itself can't be used to spam
+                if cooldown.first_notice {
+                    let _ = msg.reply(&ctx.http, cooldown.message()).await;
+                }
+                return;
+            }
+        }
+
         // Step 3: fetch nickname from DB
-        let nickname = match self.fetch_nickname(discord_id).await {
+        let nickname = match self.fetch_nickname(discord_id, msg.guild_id.map(|id| id.0)).await {
             Some(name) => name,
             None => {
                 eprintln!("[ERROR] User {} exists in DB but nickname not found!", discord_id);
@@ -130,94 +342,348 @@ impl EventHandler for Handler {
             }
         };
 
-        let channel = msg.channel_id;
+        // Step 3a: in thread mode a new message in the AI channel opens a thread for the reply
+        let thread_id = match &thread {
+            Some(_) => Some(msg.channel_id),
+            None if self.config.threads.enabled => {
+                // One thread per user and channel; a second one would split the conversation
+                if let Some(existing) = self.existing_thread(&ctx, discord_id, msg.channel_id).await {
+                    let notice = format!("💬 You already have a conversation going in <#{}>. Continue there.", existing);
+                    if let Err(e) = msg.reply(&ctx.http, notice).await {
+                        eprintln!("[ERROR] Failed to point user {} to their thread: {:?}", discord_id, e);
+                    }
+                    return;
+                }
+                self.open_thread(&ctx, &msg, &nickname).await
+            }
+            None => None,
+        };
+        let parent = match &thread {
+            Some(thread) => thread.parent_id.parse().map(ChannelId).unwrap_or(msg.channel_id),
+            None => msg.channel_id,
+        };
+
+        // Step 3b: assemble a bounded window of previous turns as context; a thread
+        // sees its own turns after the summaries of the user's earlier threads
+        let max_turns = self.config.history.max_turns;
+        let recent = match thread_id {
+            Some(thread_id) => {
+                // The summaries take part of the turn budget so the newest thread turns can't crowd them out
+                let summaries = self
+                    .store
+                    .recent_sessionless_conversations(discord_id, threads::SUMMARY_CONTEXT.min(max_turns / 2))
+                    .await;
+                match summaries {
+                    Ok(summaries) => self
+                        .store
+                        .recent_session_conversations(
+                            discord_id,
+                            &thread_id.0.to_string(),
+                            max_turns - summaries.len(),
+                        )
+                        .await
+                        .map(|turns| [summaries, turns].concat()),
+                    Err(e) => Err(e),
+                }
+            }
+            None => self.store.recent_conversations(discord_id, max_turns).await,
+        };
+        let recent = match recent {
+            Ok(recent) => recent,
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                Vec::new()
+            }
+        };
+        let history = build_history(&recent, &self.config.history);
+
+        // Step 3c: a persona bound to the channel speaks through the channel's webhook and
+        // takes precedence over the one the user picked with /persona use
+        let channel_persona = self.fetch_channel_persona(parent.0).await;
+        let (persona_prompt, temperature) = match &channel_persona {
+            Some(binding) => (Some(binding.system_prompt.clone()), binding.temperature),
+            None => match self.fetch_persona(discord_id).await {
+                Some(persona) => (Some(persona.system_prompt), persona.temperature),
+                None => (None, None),
+            },
+        };
+
+        let channel = thread_id.unwrap_or(msg.channel_id);
         let http = ctx.http.clone();
         let user_message = msg.content.clone();
-        let nickname_clone = nickname.clone();
-        let db_client = Arc::new(self.db_client.clone());
+        let store = self.store.clone();
+        let config = self.config.clone();
+        let backend = self.backend.clone();
+        let postprocess = self.postprocess.clone();
+        let webhooks = self.webhooks.clone();
+        let guild_id = msg.guild_id.map(|id| id.0);
+        let request = ChatRequest {
+            message: user_message.clone(),
+            nickname,
+            system_prompt: guild_settings.and_then(|settings| settings.system_prompt),
+            persona: persona_prompt,
+            temperature,
+            history,
+        };
 
-        // Step 4: spawn AI request and save conversation safely
-        tokio::spawn(async move {
-            let client = reqwest::Client::new();
+        // Step 4: queue the AI request; the job saves the conversation when done
+        let job = Box::pin(async move {
             // Check if chatbot server is reachable before sending message
-            let server_online = match client.get("http://127.0.0.1:5005/healthcheck").send().await {
-                Ok(resp) => resp.status().is_success(),
-                Err(_) => false,
-            };
-
-            if !server_online {
+            if !backend.health().await {
                 // let _ = channel.say(&http, "Chatbot server is offline. Please try again later.").await;
                 return;
             }
 
-            let payload = serde_json::json!({
-                "message": user_message,
-                "nickname": nickname_clone
-            });
+            let stream = if config.backend.stream {
+                backend.stream(&request).await
+            } else {
+                // Single-shot: the placeholder stays up until the whole response is ready
+                let backend = backend.clone();
+                let once = futures_util::stream::once(async move { backend.chat(&request).await });
+                Ok(Box::pin(once) as TextStream)
+            };
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("[ERROR] Failed to call {} backend: {}", backend.name(), e);
+                    let _ = channel.say(&http, "Failed to reach chatbot server.").await;
+                    return;
+                }
+            };
 
-            match client.post("http://127.0.0.1:5005/chat")
-                .json(&payload)
-                .send()
-                .await
-            {
-                Ok(resp) => match resp.text().await {
-                    Ok(mut text) => {
-                        text = text.trim().to_string();
-                        if text.is_empty() {
-                            text = "The chatbot returned nothing.".to_string();
-                        }
-
-                        // Clean AI response
-                        text = crate::handler::clean_ai_response(&text);
-
-                        println!("[LOG] AI response: {}", text);
-
-                        // Save conversation
-                        let collection = get_user_collection(&db_client);
-                        let conversation = Conversation {
-                            prompt: user_message.clone(),
-                            response: text.clone(),
-                            timestamp: Utc::now().timestamp(),
-                        };
-
-                        if let Err(e) = collection.update_one(
-                            doc! {"discord_id": discord_id.to_string()},
-                            doc! { "$push": { "conversations": mongodb::bson::to_bson(&conversation).unwrap() }} ,
-                            None
-                        ).await {
-                            eprintln!("[ERROR] Failed to save conversation: {:?}", e);
-                        }
-
-                        if let Err(e) = channel.say(&http, text).await {
-                            eprintln!("[ERROR] Failed to send AI response: {:?}", e);
-                        }
-                    }
+            // Webhooks can't post into threads with this serenity version, so threads are
+            // answered by the bot itself (still speaking as the channel's persona)
+            let target = match channel_persona.filter(|_| thread_id.is_none()) {
+                Some(binding) => match webhooks.webhook_for(&http, &binding).await {
+                    Ok(webhook) => ReplyTarget::Webhook {
+                        webhook: Box::new(webhook),
+                        username: binding.persona,
+                        avatar_url: binding.avatar_url,
+                    },
                     Err(e) => {
-                        eprintln!("[ERROR] Failed to read AI response text: {:?}", e);
-                        let _ = channel.say(&http, "Failed to read chatbot response.").await;
+                        eprintln!("[ERROR] {}; replying as the bot instead", e);
+                        ReplyTarget::Bot(channel)
                     }
                 },
+                None => ReplyTarget::Bot(channel),
+            };
+
+            let edit_interval = Duration::from_millis(config.backend.stream_edit_interval_ms);
+            let filters = postprocess.for_context(guild_id, &config.backend.model);
+            let attach_over = config.responses.attach_over_chars;
+            match relay_stream(&http, &target, stream, &filters, edit_interval, attach_over).await {
+                Ok(text) => {
+                    println!("[LOG] AI response: {}", text);
+
+                    // Save conversation
+                    let conversation = Conversation {
+                        prompt: user_message,
+                        response: text,
+                        timestamp: Utc::now().timestamp(),
+                        channel_id: Some(parent.0.to_string()),
+                        session_id: thread_id.map(|id| id.0.to_string()),
+                    };
+
+                    if let Err(e) = store.push_conversation(discord_id, conversation).await {
+                        eprintln!("[ERROR] {}", e);
+                    }
+                }
                 Err(e) => {
-                    eprintln!("[ERROR] Failed to call chatbot server: {:?}", e);
-                    let _ = channel.say(&http, "Failed to reach chatbot server.").await;
+                    eprintln!("[ERROR] Failed to stream AI response: {}", e);
+                    if let ReplyTarget::Webhook { .. } = target {
+                        // Look the webhook up again next time in case it was deleted
+                        webhooks.forget(channel.0).await;
+                    }
                 }
             }
         });
+
+        match self.queue.submit(discord_id, job) {
+            Ok(status) => {
+                tokio::spawn(crate::queue::report_position(ctx.http.clone(), msg.channel_id, msg.id, status));
+            }
+            Err(QueueFull) => {
+                println!("[LOG] Queue full, turned away request from user {}", discord_id);
+                let _ = msg
+                    .reply(&ctx.http, "🚦 The AI is busy with other requests. Please try again in a moment.")
+                    .await;
+            }
+        }
+    }
+
+    async fn channel_delete(&self, _ctx: Context, channel: &GuildChannel) {
+        // Discord deletes the channel's webhooks with it; only the binding is left over
+        match self.store.remove_channel_persona(channel.id.0).await {
+            Ok(Some(binding)) => {
+                self.webhooks.forget(channel.id.0).await;
+                println!("[LOG] Removed persona '{}' from deleted channel {}", binding.persona, channel.id);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("[ERROR] {}", e),
+        }
+    }
+
+    async fn thread_update(&self, _ctx: Context, thread: GuildChannel) {
+        let Some(archived) = thread.thread_metadata.map(|meta| meta.archived) else {
+            return;
+        };
+        let Some(record) = self.fetch_thread(thread.id.0).await else {
+            return;
+        };
+        // Discord archives the thread after `threads.auto_archive_minutes` of inactivity;
+        // posting in it again unarchives it, and the next archive is summarised again
+        match self.store.set_thread_archived(thread.id.0, archived).await {
+            Ok(true) if archived => self.summarize_thread(record, thread.name).await,
+            Ok(_) => {}
+            Err(e) => eprintln!("[ERROR] {}", e),
+        }
+    }
+
+    async fn thread_delete(&self, _ctx: Context, thread: PartialGuildChannel) {
+        self.thread_ids.lock().await.remove(&thread.id.0);
+        if let Err(e) = self.store.delete_thread(thread.id.0).await {
+            eprintln!("[ERROR] {}", e);
+        }
+    }
+
+    async fn guild_role_create(&self, _ctx: Context, new: Role) {
+        self.admin_roles.forget(new.guild_id).await;
+    }
+
+    async fn guild_role_update(&self, _ctx: Context, new_data: Role) {
+        self.admin_roles.forget(new_data.guild_id).await;
+    }
+
+    async fn guild_role_delete(&self, _ctx: Context, guild_id: GuildId, _removed_role_id: RoleId) {
+        self.admin_roles.forget(guild_id).await;
+    }
+
+    async fn webhook_update(&self, _ctx: Context, _guild_id: GuildId, belongs_to_channel_id: ChannelId) {
+        // A webhook in the channel was created, edited or deleted, possibly ours
+        self.webhooks.forget(belongs_to_channel_id.0).await;
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::MessageComponent(component) = &interaction {
+            match component.data.custom_id.split(':').next() {
+                Some(crate::commands::history::CUSTOM_ID_PREFIX) => {
+                    crate::commands::history::handle_component(&ctx, component, self.store.as_ref(), &self.config.discord)
+                        .await;
+                }
+                Some(crate::commands::forget::CUSTOM_ID_PREFIX) => {
+                    crate::commands::forget::handle_component(&ctx, component, self.store.as_ref()).await;
+                }
+                Some(crate::onboarding::CUSTOM_ID_PREFIX) => {
+                    crate::onboarding::handle_component(&ctx, component, self.store.as_ref(), &self.config.onboarding)
+                        .await;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if let Interaction::ModalSubmit(modal) = &interaction {
+            match modal.data.custom_id.split(':').next() {
+                Some(crate::commands::history::CUSTOM_ID_PREFIX) => {
+                    crate::commands::history::handle_modal(&ctx, modal, self.store.as_ref(), &self.config.discord)
+                        .await;
+                }
+                Some(crate::onboarding::CUSTOM_ID_PREFIX) => {
+                    crate::onboarding::handle_modal(&ctx, modal, self.store.as_ref(), &self.config.onboarding).await;
+                }
+                _ => {}
+            }
+            return;
+        }
+
         if let Interaction::ApplicationCommand(command) = interaction {
             let command_name = command.data.name.as_str();
 
+            if let Err(reason) = permissions::check(&command, self.store.as_ref(), &self.config.discord).await {
+                println!(
+                    "[WARN] Denied /{} for user {} in guild {:?}: {}",
+                    command_name,
+                    command.user.id,
+                    command.guild_id.map(|id| id.0),
+                    reason
+                );
+                let _ = command
+                    .create_interaction_response(&ctx.http, |r| {
+                        r.kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|d| d.content(reason).ephemeral(true))
+                    })
+                    .await;
+                return;
+            }
+
+            if self.rate_limiter.limits_command(command_name) {
+                let roles = command.member.as_ref().map(|m| m.roles.clone()).unwrap_or_default();
+                if let Err(cooldown) = self.rate_limiter.check(command.user.id, command.guild_id, &roles) {
+                    let exempt = self.rate_limiter.exempts_admins()
+                        && permissions::is_admin(command.member.as_ref(), &self.config.discord);
+                    if !exempt {
+                        println!(
+                            "[LOG] Rate limited /{} for user {} ({:?})",
+                            command_name, command.user.id, cooldown.retry_after
+                        );
+                        let _ = command
+                            .create_interaction_response(&ctx.http, |r| {
+                                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                                    .interaction_response_data(|d| d.content(cooldown.message()).ephemeral(true))
+                            })
+                            .await;
+                        return;
+                    }
+                }
+            }
+
             match command_name {
                 "setup-bot" => {
-                    crate::commands::setup_bot::handle_setup_bot(&ctx, &command, &self.db_client).await;
+                    crate::commands::setup_bot::handle_setup_bot(&ctx, &command, self.store.as_ref(), &self.config.onboarding)
+                        .await;
                 }
                 "run-chatbot" => {
-                    crate::commands::start_chatbot::run_chatbot(&ctx, &command).await;
+                    crate::commands::start_chatbot::run_chatbot(&ctx, &command, &self.config.chatbot, &self.backend).await;
                 }
                 "stop-chatbot" => {
-                    crate::commands::start_chatbot::stop_chatbot(&ctx, &command).await;
+                    crate::commands::start_chatbot::stop_chatbot(&ctx, &command, &self.config.chatbot, &self.backend).await;
+                }
+                "history" => {
+                    crate::commands::history::handle_history(&ctx, &command, self.store.as_ref(), &self.config.discord).await;
+                }
+                "forget" => {
+                    crate::commands::forget::handle_forget(&ctx, &command).await;
+                }
+                "unregister" => {
+                    crate::commands::forget::handle_unregister(&ctx, &command).await;
+                }
+                "export" => {
+                    crate::commands::export::handle_export(&ctx, &command, self.store.as_ref(), &self.config.discord).await;
+                }
+                "import" => {
+                    crate::commands::import::handle_import(&ctx, &command, self.store.as_ref()).await;
+                }
+                "nickname" => {
+                    crate::commands::nickname::handle_nickname(&ctx, &command, self.store.as_ref()).await;
+                }
+                "persona" => {
+                    crate::commands::persona::handle_persona(&ctx, &command, self.store.as_ref(), &self.config.discord).await;
+                }
+                "imagine" => {
+                    crate::commands::imagine::handle_imagine(&ctx, &command, &self.image_client, &self.config.image).await;
+                }
+                "chatbot-logs" => {
+                    crate::commands::chatbot_logs::handle_chatbot_logs(&ctx, &command).await;
+                }
+                "chatbot-access" => {
+                    crate::commands::chatbot_access::handle_chatbot_access(&ctx, &command, self.store.as_ref()).await;
+                }
+                "ai-channel" => {
+                    crate::commands::ai_channel::handle_ai_channel(&ctx, &command, self.store.as_ref(), &self.config).await;
+                }
+                "channel-persona" => {
+                    crate::commands::channel_persona::handle_channel_persona(&ctx, &command, self.store.as_ref(), &self.webhooks)
+                        .await;
                 }
                 _ => {}
             }
@@ -0,0 +1,113 @@
+use serde::Serialize;
+use crate::config::HistoryConfig;
+use crate::db::user::Conversation;
+
+/// One message of prior context sent to the chat backend
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryMessage {
+    /// "user" or "assistant"
+    pub role: &'static str,
+    pub content: String,
+}
+
+/// Rough token estimate (~4 characters per token), good enough for budgeting
+pub fn approx_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Build the history window from stored conversations (oldest first).
+///
+/// Walks backwards from the newest turn and stops once either `max_turns`
+/// or the approximate `max_tokens` budget would be exceeded, so the most
+/// recent context always wins. The result is in chronological order.
+pub fn build_history(conversations: &[Conversation], config: &HistoryConfig) -> Vec<HistoryMessage> {
+    let mut used_tokens = 0;
+    let mut turns = Vec::new();
+
+    for conversation in conversations.iter().rev().take(config.max_turns) {
+        let cost = approx_tokens(&conversation.prompt) + approx_tokens(&conversation.response);
+        if used_tokens + cost > config.max_tokens {
+            break;
+        }
+        used_tokens += cost;
+        turns.push(conversation);
+    }
+
+    turns
+        .into_iter()
+        .rev()
+        .flat_map(|conversation| {
+            [
+                HistoryMessage { role: "user", content: conversation.prompt.clone() },
+                HistoryMessage { role: "assistant", content: conversation.response.clone() },
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(n: usize, len: usize) -> Conversation {
+        Conversation {
+            prompt: format!("{}{}", n, "p".repeat(len.saturating_sub(1))),
+            response: format!("{}{}", n, "r".repeat(len.saturating_sub(1))),
+            timestamp: n as i64,
+            channel_id: None,
+            session_id: None,
+        }
+    }
+
+    fn contents(history: &[HistoryMessage]) -> Vec<(&str, String)> {
+        history.iter().map(|m| (m.role, m.content.chars().take(1).collect())).collect()
+    }
+
+    #[test]
+    fn approximates_four_chars_per_token() {
+        assert_eq!(approx_tokens(""), 0);
+        assert_eq!(approx_tokens("abcd"), 1);
+        assert_eq!(approx_tokens("abcde"), 2);
+        assert_eq!(approx_tokens("éééé"), 1);
+    }
+
+    #[test]
+    fn keeps_newest_turns_oldest_first() {
+        let turns: Vec<Conversation> = (1..=5).map(|n| turn(n, 4)).collect();
+        let config = HistoryConfig { max_turns: 3, max_tokens: 1000 };
+        assert_eq!(
+            contents(&build_history(&turns, &config)),
+            vec![
+                ("user", "3".to_string()),
+                ("assistant", "3".to_string()),
+                ("user", "4".to_string()),
+                ("assistant", "4".to_string()),
+                ("user", "5".to_string()),
+                ("assistant", "5".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_token_budget() {
+        // Each turn costs 2 tokens (4 chars each side)
+        let turns: Vec<Conversation> = (1..=5).map(|n| turn(n, 4)).collect();
+        let config = HistoryConfig { max_turns: 10, max_tokens: 5 };
+        let history = build_history(&turns, &config);
+        assert_eq!(history.len(), 4);
+        assert_eq!(contents(&history)[0], ("user", "4".to_string()));
+
+        // An oversized newest turn leaves no room for anything older
+        let mut turns = turns;
+        turns.push(turn(6, 40));
+        assert!(build_history(&turns, &config).is_empty());
+    }
+
+    #[test]
+    fn zero_limits_send_nothing() {
+        let turns = vec![turn(1, 4)];
+        assert!(build_history(&turns, &HistoryConfig { max_turns: 0, max_tokens: 100 }).is_empty());
+        assert!(build_history(&turns, &HistoryConfig { max_turns: 5, max_tokens: 0 }).is_empty());
+        assert!(build_history(&[], &HistoryConfig::default()).is_empty());
+    }
+}
@@ -1,58 +1,109 @@
-use serenity::prelude::*;
-use serenity::model::application::command::Command;
 use serenity::prelude::GatewayIntents;
-use std::path::Path;
-use std::env;
+use serenity::model::id::GuildId;
 use std::sync::Arc;
-use std::collections::HashMap;
-use mongodb::{Client as MongoClient, options::ClientOptions};
-use std::process::Command as StdCommand;
-use tokio::sync::Mutex;
 
+mod backend;
+mod chatbot_logs;
+mod config;
 mod db;       // must come before `use db::...`
 mod commands;
+mod export;
+mod formatting;
 mod handler;
+mod history;
+mod nickname;
+mod onboarding;
+mod permissions;
+mod postprocess;
+mod queue;
+mod rate_limit;
+mod streaming;
+mod supervisor;
+mod threads;
+mod webhooks;
 
+use crate::backend::image::ImageClient;
+use crate::config::Config;
 use crate::handler::Handler;
-use crate::commands::start_chatbot; // so we can register chatbot commands
+use crate::webhooks::WebhookManager;
+use crate::postprocess::Pipeline;
+use crate::queue::GenerationQueue;
+use crate::permissions::AdminRoleCache;
+use crate::rate_limit::RateLimiter;
+use crate::commands::{ai_channel, imagine, start_chatbot}; // so we can register chatbot commands
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
 
+    // Load and validate config (file + env overrides)
+    let config = match Config::load() {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    // Get bot token
-    let token = env::var("DISCORD_TOKEN").expect("Expected DISCORD_TOKEN in the environment");
+    // Storage setup (MongoDB, SQLite or in-memory)
+    let store = match db::from_config(&config).await {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    // MongoDB setup
-    let mongo_uri = env::var("MONGODB_URI").unwrap_or("mongodb://localhost:27017".to_string());
-    let client_options = ClientOptions::parse(&mongo_uri)
-        .await
-        .expect("Failed to parse MongoDB URI");
-    let db_client = MongoClient::with_options(client_options).expect("Failed to connect to MongoDB");
+    // Response filters were already compiled once by Config::validate
+    let postprocess = match Pipeline::new(&config.postprocess) {
+        Ok(pipeline) => Arc::new(pipeline),
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Threads the bot opened before this run
+    let thread_ids = match store.thread_ids().await {
+        Ok(ids) => ids.into_iter().collect(),
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // Setup handler
     let handler = Handler {
-        db_client,
-        pending_nicknames: Arc::new(Mutex::new(HashMap::new())),
+        webhooks: Arc::new(WebhookManager::new(store.clone())),
+        store,
+        config: config.clone(),
+        backend: backend::from_config(&config.backend),
+        image_client: Arc::new(ImageClient::new(&config.image)),
+        rate_limiter: Arc::new(RateLimiter::new(&config.rate_limit)),
+        queue: GenerationQueue::new(&config.queue),
+        postprocess,
+        admin_roles: AdminRoleCache::default(),
+        thread_ids: tokio::sync::Mutex::new(thread_ids),
     };
 
     let intents = GatewayIntents::all();
-    let application_id: u64 = env::var("APPLICATION_ID")
-        .expect("Expected APPLICATION_ID in environment")
-        .parse()
-        .expect("APPLICATION_ID must be a valid u64");
 
-    let mut client = serenity::Client::builder(&token, intents)
+    let mut client = match serenity::Client::builder(&config.discord.token, intents)
         .event_handler(handler)
-        .application_id(application_id)
+        .application_id(config.discord.application_id)
         .await
-        .expect("Error creating client");
+    {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("[ERROR] Error creating client: {:?}", e);
+            std::process::exit(1);
+        }
+    };
 
     println!("[LOG] Bot is running...");
 
     // Register all slash commands in one place (only once)
-    register_slash_commands(&client.cache_and_http.http).await;
+    register_slash_commands(&client.cache_and_http.http, &config).await;
 
     if let Err(why) = client.start().await {
         println!("Client error: {:?}", why);
@@ -60,41 +111,168 @@ async fn main() {
 }
 
 /// Central place for all slash command registration
-async fn register_slash_commands(http: &serenity::http::Http) {
-    // Replace with your target guild ID
-    let guild_id: serenity::model::id::GuildId = serenity::model::id::GuildId(1413865613474140211); // <-- CHANGE THIS
-
-    // Register /setup-bot
-    let setup_bot = guild_id.create_application_command(http, |command| {
-        command
-            .name("setup-bot")
-            .description("Register yourself with the bot and set your nickname")
-            .create_option(|opt| {
-                opt.name("nickname")
-                    .description("Your desired bot nickname")
-                    .kind(serenity::model::prelude::command::CommandOptionType::String)
-                    .required(true)
-            })
-    })
-    .await
-    .expect("Failed to register /setup-bot");
-    println!("[LOG] Registered guild command: /setup-bot");
-
-    // Register /run-chatbot
-    let run_chatbot = guild_id.create_application_command(http, |c| {
-        start_chatbot::register_commands(c)
-    })
-    .await
-    .expect("Failed to register /run-chatbot");
-    println!("[LOG] Registered guild command: /run-chatbot");
-
-    // Register /stop-chatbot
-    let stop_chatbot = guild_id.create_application_command(http, |c| {
-        start_chatbot::register_stop_commands(c)
-    })
-    .await
-    .expect("Failed to register /stop-chatbot");
-    println!("[LOG] Registered guild command: /stop-chatbot");
+async fn register_slash_commands(http: &serenity::http::Http, config: &Config) {
+    for &id in &config.discord.guild_ids {
+        let guild_id = GuildId(id);
+
+        // Register /setup-bot
+        match guild_id.create_application_command(http, |command| {
+            command
+                .name("setup-bot")
+                .description("Register yourself with the bot and set your nickname")
+                .create_option(|opt| {
+                    opt.name("nickname")
+                        .description("Your desired bot nickname (leave out to pick one with buttons)")
+                        .kind(serenity::model::prelude::command::CommandOptionType::String)
+                        .required(false)
+                })
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /setup-bot", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /setup-bot in {}: {:?}", id, e),
+        }
+
+        // Register /run-chatbot
+        match guild_id.create_application_command(http, |c| {
+            start_chatbot::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /run-chatbot", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /run-chatbot in {}: {:?}", id, e),
+        }
+
+        // Register /stop-chatbot
+        match guild_id.create_application_command(http, |c| {
+            start_chatbot::register_stop_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /stop-chatbot", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /stop-chatbot in {}: {:?}", id, e),
+        }
+
+        // Register /ai-channel
+        match guild_id.create_application_command(http, |c| {
+            ai_channel::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /ai-channel", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /ai-channel in {}: {:?}", id, e),
+        }
+
+        // Register /channel-persona
+        match guild_id.create_application_command(http, |c| {
+            commands::channel_persona::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /channel-persona", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /channel-persona in {}: {:?}", id, e),
+        }
+
+        // Register /chatbot-logs
+        match guild_id.create_application_command(http, |c| {
+            commands::chatbot_logs::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /chatbot-logs", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /chatbot-logs in {}: {:?}", id, e),
+        }
+
+        // Register /chatbot-access
+        match guild_id.create_application_command(http, |c| {
+            commands::chatbot_access::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /chatbot-access", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /chatbot-access in {}: {:?}", id, e),
+        }
+
+        // Register /history
+        match guild_id.create_application_command(http, |c| {
+            commands::history::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /history", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /history in {}: {:?}", id, e),
+        }
+
+        // Register /forget
+        match guild_id.create_application_command(http, |c| {
+            commands::forget::register_forget(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /forget", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /forget in {}: {:?}", id, e),
+        }
+
+        // Register /unregister
+        match guild_id.create_application_command(http, |c| {
+            commands::forget::register_unregister(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /unregister", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /unregister in {}: {:?}", id, e),
+        }
+
+        // Register /export
+        match guild_id.create_application_command(http, |c| {
+            commands::export::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /export", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /export in {}: {:?}", id, e),
+        }
+
+        // Register /import
+        match guild_id.create_application_command(http, |c| {
+            commands::import::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /import", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /import in {}: {:?}", id, e),
+        }
+
+        // Register /nickname
+        match guild_id.create_application_command(http, |c| {
+            commands::nickname::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /nickname", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /nickname in {}: {:?}", id, e),
+        }
+
+        // Register /persona
+        match guild_id.create_application_command(http, |c| {
+            commands::persona::register_commands(c)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /persona", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /persona in {}: {:?}", id, e),
+        }
+
+        // Register /imagine
+        match guild_id.create_application_command(http, |c| {
+            imagine::register_commands(c, config.image.max_steps)
+        })
+        .await
+        {
+            Ok(_) => println!("[LOG] Registered guild command in {}: /imagine", id),
+            Err(e) => eprintln!("[ERROR] Failed to register /imagine in {}: {:?}", id, e),
+        }
+    }
 
     println!("[LOG] All guild slash commands registered.");
 }
@@ -0,0 +1,64 @@
+/// Shortest and longest nickname we accept, in characters
+pub const MIN_LEN: usize = 2;
+pub const MAX_LEN: usize = 32;
+
+/// Punctuation allowed besides letters, digits and single spaces
+const ALLOWED_PUNCTUATION: &[char] = &['-', '.', '\''];
+
+/// Normalise and validate a requested nickname.
+///
+/// Nicknames are echoed back in chat and sent to the model, so anything that could
+/// ping (`@everyone`, `<@id>`), format (`*`, `` ` ``, `_`, `~`, `|`) or hide characters
+/// is rejected rather than escaped.
+pub fn validate(raw: &str) -> Result<String, String> {
+    let nickname = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    let len = nickname.chars().count();
+
+    if !(MIN_LEN..=MAX_LEN).contains(&len) {
+        return Err(format!(
+            "Nicknames must be between {} and {} characters.",
+            MIN_LEN, MAX_LEN
+        ));
+    }
+
+    if let Some(bad) = nickname
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || *c == ' ' || ALLOWED_PUNCTUATION.contains(c)))
+    {
+        return Err(format!(
+            "`{}` is not allowed. Use letters, numbers, spaces and `- . '` only.",
+            bad.escape_default()
+        ));
+    }
+
+    if !nickname.chars().any(|c| c.is_alphanumeric()) {
+        return Err("Nicknames need at least one letter or number.".to_string());
+    }
+
+    Ok(nickname)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_and_normalises() {
+        assert_eq!(validate("  Ada   Lovelace ").unwrap(), "Ada Lovelace");
+        assert_eq!(validate("Zoë-O'Neil.2").unwrap(), "Zoë-O'Neil.2");
+    }
+
+    #[test]
+    fn rejects_mentions_and_markdown() {
+        for bad in ["@everyone", "<@1234>", "**bold**", "`code`", "a_b_", "~~x~~", "||spoiler||", "a\u{200b}b"] {
+            assert!(validate(bad).is_err(), "{} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        assert!(validate("a").is_err());
+        assert!(validate(&"a".repeat(MAX_LEN + 1)).is_err());
+        assert!(validate("--").is_err());
+    }
+}
@@ -0,0 +1,399 @@
+use chrono::Utc;
+use serenity::builder::CreateComponents;
+use serenity::model::application::component::{ActionRowComponent, ButtonStyle, InputTextStyle};
+use serenity::model::application::interaction::{
+    message_component::MessageComponentInteraction,
+    modal::ModalSubmitInteraction,
+    InteractionResponseType,
+};
+use serenity::prelude::*;
+use crate::config::OnboardingConfig;
+use crate::db::onboarding::{OnboardingRecord, OnboardingState};
+use crate::db::user::User;
+use crate::db::Store;
+use crate::nickname;
+
+/// Prefix of every custom_id this module owns
+pub const CUSTOM_ID_PREFIX: &str = "onboarding";
+
+/// Something the user did, from a button, the nickname modal or a `!` text command
+#[derive(Debug, Clone, PartialEq)]
+pub enum OnboardingEvent {
+    Start,
+    ChooseNickname(String),
+    Confirm,
+    Cancel,
+}
+
+/// What an event leads to
+#[derive(Debug, PartialEq)]
+enum Next {
+    Save(OnboardingState),
+    Register(String),
+    Clear,
+}
+
+/// The onboarding state machine. `None` means no (unexpired) onboarding in progress.
+fn transition(current: Option<&OnboardingState>, event: OnboardingEvent) -> Result<Next, String> {
+    match (current, event) {
+        (_, OnboardingEvent::Start) => Ok(Next::Save(OnboardingState::AwaitingNickname)),
+        (_, OnboardingEvent::Cancel) => Ok(Next::Clear),
+        // Picking a nickname also works without starting first, and can be repeated to change it
+        (_, OnboardingEvent::ChooseNickname(raw)) => nickname::validate(&raw)
+            .map(|nickname| Next::Save(OnboardingState::AwaitingConfirmation { nickname })),
+        (Some(OnboardingState::AwaitingConfirmation { nickname }), OnboardingEvent::Confirm) => {
+            Ok(Next::Register(nickname.clone()))
+        }
+        (Some(OnboardingState::AwaitingNickname), OnboardingEvent::Confirm) => {
+            Err("Choose a nickname before confirming.".to_string())
+        }
+        (None, OnboardingEvent::Confirm) => {
+            Err("No pending nickname found. Press Start or use `!nickname <your_nickname>` first.".to_string())
+        }
+    }
+}
+
+/// Buttons shown under an onboarding message
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Button {
+    Start,
+    ChooseNickname,
+    Confirm,
+    Cancel,
+}
+
+/// Message to show the user after an event
+pub struct Reply {
+    pub content: String,
+    buttons: Vec<Button>,
+    discord_id: u64,
+    /// A valid nickname was just chosen and awaits confirmation
+    awaiting_confirmation: bool,
+}
+
+impl Reply {
+    fn new(discord_id: u64, content: impl Into<String>, buttons: Vec<Button>) -> Self {
+        Self {
+            content: content.into(),
+            buttons,
+            discord_id,
+            awaiting_confirmation: false,
+        }
+    }
+
+    /// The prompt for a state, prefixed with `note` when something went wrong
+    fn for_state(discord_id: u64, state: Option<&OnboardingState>, note: Option<String>) -> Self {
+        let (text, buttons) = match state {
+            None => (
+                "You must complete onboarding before chatting with the AI.".to_string(),
+                vec![Button::Start],
+            ),
+            Some(OnboardingState::AwaitingNickname) => (
+                "Welcome! Choose the nickname the AI should call you (or reply with it).".to_string(),
+                vec![Button::ChooseNickname, Button::Cancel],
+            ),
+            Some(OnboardingState::AwaitingConfirmation { nickname }) => (
+                format!("You chose **{}**. Confirm to register.", nickname),
+                vec![Button::Confirm, Button::ChooseNickname, Button::Cancel],
+            ),
+        };
+        let awaiting_confirmation =
+            note.is_none() && matches!(state, Some(OnboardingState::AwaitingConfirmation { .. }));
+        let content = match note {
+            Some(note) => format!("⚠️ {}\n{}", note, text),
+            None => text,
+        };
+        Self {
+            awaiting_confirmation,
+            ..Self::new(discord_id, content, buttons)
+        }
+    }
+
+    /// Whether the user was just asked to confirm a valid nickname
+    pub fn awaits_confirmation(&self) -> bool {
+        self.awaiting_confirmation
+    }
+
+    pub fn components(&self) -> CreateComponents {
+        let mut components = CreateComponents::default();
+        if self.buttons.is_empty() {
+            return components;
+        }
+        components.create_action_row(|row| {
+            for button in &self.buttons {
+                let (action, label, style) = match button {
+                    Button::Start => ("start", "Start", ButtonStyle::Primary),
+                    Button::ChooseNickname => ("nickname", "Choose nickname", ButtonStyle::Primary),
+                    Button::Confirm => ("confirm", "Confirm", ButtonStyle::Success),
+                    Button::Cancel => ("cancel", "Cancel", ButtonStyle::Secondary),
+                };
+                row.create_button(|b| {
+                    b.custom_id(format!("{}:{}:{}", CUSTOM_ID_PREFIX, action, self.discord_id))
+                        .label(label)
+                        .style(style)
+                });
+            }
+            row
+        });
+        components
+    }
+}
+
+/// Apply an event to the user's persisted onboarding state
+pub async fn advance(
+    store: &dyn Store,
+    discord_id: u64,
+    event: OnboardingEvent,
+    config: &OnboardingConfig,
+) -> Reply {
+    match store.find_user(discord_id).await {
+        Ok(Some(user)) => {
+            return Reply::new(discord_id, format!("You are already registered as **{}**!", user.nickname), vec![])
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            return Reply::new(discord_id, "❌ Failed to look up your profile.", vec![]);
+        }
+    }
+
+    let now = Utc::now().timestamp();
+    let current = match store.get_onboarding(discord_id, now).await {
+        Ok(record) => record.map(|r| r.state),
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            return Reply::new(discord_id, "❌ Failed to load your onboarding progress.", vec![]);
+        }
+    };
+
+    let next = match transition(current.as_ref(), event) {
+        Ok(next) => next,
+        Err(reason) => return Reply::for_state(discord_id, current.as_ref(), Some(reason)),
+    };
+
+    match next {
+        Next::Save(state) => {
+            let record = OnboardingRecord {
+                discord_id: discord_id.to_string(),
+                state: state.clone(),
+                expires_at: now + config.expiry_secs as i64,
+            };
+            if let Err(e) = store.save_onboarding(record, now).await {
+                eprintln!("[ERROR] {}", e);
+                return Reply::new(discord_id, "❌ Failed to save your onboarding progress.", vec![]);
+            }
+            println!("[LOG] Onboarding for {}: {:?}", discord_id, state);
+            Reply::for_state(discord_id, Some(&state), None)
+        }
+        Next::Register(nickname) => {
+            let user = User {
+                id: None,
+                discord_id: discord_id.to_string(),
+                nickname: nickname.clone(),
+                guild_nicknames: Default::default(),
+                active_persona: None,
+            };
+            if let Err(e) = store.insert_user(user).await {
+                eprintln!("[ERROR] {}", e);
+                return Reply::new(discord_id, "❌ Failed to register. Please try again later.", vec![]);
+            }
+            if let Err(e) = store.clear_onboarding(discord_id).await {
+                eprintln!("[ERROR] {}", e);
+            }
+            println!("[LOG] User {} added to DB with nickname '{}'", discord_id, nickname);
+            Reply::new(
+                discord_id,
+                format!("You have been added as **{}**. You can now chat with the AI!", nickname),
+                vec![],
+            )
+        }
+        Next::Clear => {
+            if let Err(e) = store.clear_onboarding(discord_id).await {
+                eprintln!("[ERROR] {}", e);
+            }
+            println!("[LOG] Onboarding for {} cancelled", discord_id);
+            Reply::new(discord_id, "Onboarding cancelled.", vec![Button::Start])
+        }
+    }
+}
+
+/// Parse the `!start`, `!nickname <name>`, `!confirm` and `!cancel` text fallback
+fn parse_text_command(content: &str) -> Option<OnboardingEvent> {
+    let content = content.trim();
+    let (command, rest) = content.split_once(char::is_whitespace).unwrap_or((content, ""));
+    match command {
+        "!start" => Some(OnboardingEvent::Start),
+        "!nickname" => Some(OnboardingEvent::ChooseNickname(rest.trim().to_string())),
+        "!confirm" => Some(OnboardingEvent::Confirm),
+        "!cancel" => Some(OnboardingEvent::Cancel),
+        _ => None,
+    }
+}
+
+/// Handle a message from an unregistered user in an AI channel.
+///
+/// Plain text is taken as the nickname while one is being asked for; otherwise the
+/// user is reminded where they are in the flow.
+pub async fn handle_text(store: &dyn Store, discord_id: u64, content: &str, config: &OnboardingConfig) -> Reply {
+    if let Some(event) = parse_text_command(content) {
+        return advance(store, discord_id, event, config).await;
+    }
+
+    let now = Utc::now().timestamp();
+    match store.get_onboarding(discord_id, now).await {
+        Ok(Some(record)) if record.state == OnboardingState::AwaitingNickname => {
+            advance(store, discord_id, OnboardingEvent::ChooseNickname(content.to_string()), config).await
+        }
+        Ok(record) => Reply::for_state(discord_id, record.map(|r| r.state).as_ref(), None),
+        Err(e) => {
+            eprintln!("[ERROR] {}", e);
+            Reply::for_state(discord_id, None, None)
+        }
+    }
+}
+
+/// Handle the Start / Choose nickname / Confirm / Cancel buttons
+pub async fn handle_component(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn Store,
+    config: &OnboardingConfig,
+) {
+    let (action, owner) = match component.data.custom_id.split(':').collect::<Vec<_>>().as_slice() {
+        [_, action, owner] => (action.to_string(), owner.parse::<u64>().unwrap_or(0)),
+        _ => return,
+    };
+
+    // Onboarding prompts in AI channels are public; only their owner may press the buttons
+    if owner != component.user.id.0 {
+        let _ = component
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.content("These buttons belong to someone else. Use /setup-bot to register.")
+                            .ephemeral(true)
+                    })
+            })
+            .await;
+        return;
+    }
+
+    let event = match action.as_str() {
+        "start" => OnboardingEvent::Start,
+        "confirm" => OnboardingEvent::Confirm,
+        "cancel" => OnboardingEvent::Cancel,
+        "nickname" => {
+            let _ = component
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::Modal).interaction_response_data(|d| {
+                        d.custom_id(format!("{}:nickname:{}", CUSTOM_ID_PREFIX, owner))
+                            .title("Choose your nickname")
+                            .components(|c| {
+                                c.create_action_row(|row| {
+                                    row.create_input_text(|t| {
+                                        t.custom_id("nickname")
+                                            .label("Nickname")
+                                            .style(InputTextStyle::Short)
+                                            .min_length(nickname::MIN_LEN as u64)
+                                            .max_length(nickname::MAX_LEN as u64)
+                                            .required(true)
+                                    })
+                                })
+                            })
+                    })
+                })
+                .await;
+            return;
+        }
+        _ => return,
+    };
+
+    let reply = advance(store, owner, event, config).await;
+    let _ = component
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.content(&reply.content).set_components(reply.components()))
+        })
+        .await;
+}
+
+/// Handle the nickname modal
+pub async fn handle_modal(
+    ctx: &Context,
+    modal: &ModalSubmitInteraction,
+    store: &dyn Store,
+    config: &OnboardingConfig,
+) {
+    let nickname = modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|c| match c {
+            ActionRowComponent::InputText(input) if input.custom_id == "nickname" => Some(input.value.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let reply = advance(store, modal.user.id.0, OnboardingEvent::ChooseNickname(nickname), config).await;
+    let _ = modal
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.content(&reply.content).set_components(reply.components()))
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn awaiting(nickname: &str) -> OnboardingState {
+        OnboardingState::AwaitingConfirmation {
+            nickname: nickname.to_string(),
+        }
+    }
+
+    #[test]
+    fn happy_path() {
+        let started = transition(None, OnboardingEvent::Start).unwrap();
+        assert_eq!(started, Next::Save(OnboardingState::AwaitingNickname));
+
+        let chosen = transition(
+            Some(&OnboardingState::AwaitingNickname),
+            OnboardingEvent::ChooseNickname(" Spud ".to_string()),
+        )
+        .unwrap();
+        assert_eq!(chosen, Next::Save(awaiting("Spud")));
+
+        let confirmed = transition(Some(&awaiting("Spud")), OnboardingEvent::Confirm).unwrap();
+        assert_eq!(confirmed, Next::Register("Spud".to_string()));
+    }
+
+    #[test]
+    fn confirm_needs_a_nickname() {
+        assert!(transition(None, OnboardingEvent::Confirm).is_err());
+        assert!(transition(Some(&OnboardingState::AwaitingNickname), OnboardingEvent::Confirm).is_err());
+    }
+
+    #[test]
+    fn invalid_nickname_is_rejected() {
+        let result = transition(
+            Some(&OnboardingState::AwaitingNickname),
+            OnboardingEvent::ChooseNickname("@everyone".to_string()),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn text_commands() {
+        assert_eq!(parse_text_command("!start"), Some(OnboardingEvent::Start));
+        assert_eq!(
+            parse_text_command("!nickname  Big Spud "),
+            Some(OnboardingEvent::ChooseNickname("Big Spud".to_string()))
+        );
+        assert_eq!(parse_text_command("!nickname"), Some(OnboardingEvent::ChooseNickname(String::new())));
+        assert_eq!(parse_text_command("!nicknames"), None);
+        assert_eq!(parse_text_command("hello"), None);
+    }
+}
@@ -0,0 +1,239 @@
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::http::Http;
+use serenity::model::guild::Member;
+use serenity::model::id::{GuildId, RoleId, UserId};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use crate::config::DiscordConfig;
+use crate::db::access::{AccessGrant, AccessKind};
+use crate::db::Store;
+
+/// Who may run a slash command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    Anyone,
+    /// Manage Server, Administrator or a configured admin role
+    Admin,
+    /// An admin, or a role/user on the guild's chatbot allowlist
+    ChatbotControl,
+}
+
+/// The requirement for each command. Commands that only sometimes touch other
+/// users' data (e.g. `/history user:`) check [`is_bot_admin`] themselves.
+pub fn requirement(command_name: &str) -> Requirement {
+    match command_name {
+        "run-chatbot" | "stop-chatbot" => Requirement::ChatbotControl,
+        "chatbot-logs" | "ai-channel" | "channel-persona" | "chatbot-access" => Requirement::Admin,
+        _ => Requirement::Anyone,
+    }
+}
+
+/// Whether the member counts as a bot admin
+pub fn is_admin(member: Option<&Member>, config: &DiscordConfig) -> bool {
+    let Some(member) = member else {
+        return false;
+    };
+    let by_permission = member
+        .permissions
+        .map(|p| p.administrator() || p.manage_guild())
+        .unwrap_or(false);
+    by_permission || member.roles.iter().any(|role| config.admin_role_ids.contains(&role.0))
+}
+
+/// Whether the member holds one of the configured `admin_role_ids`. Unlike [`is_admin`],
+/// Manage Server in one guild is not enough, so this guards data shared by every guild.
+pub fn is_bot_admin(member: Option<&Member>, config: &DiscordConfig) -> bool {
+    member.is_some_and(|member| member.roles.iter().any(|role| config.admin_role_ids.contains(&role.0)))
+}
+
+/// How long a guild's roles are trusted before they are fetched again
+const GUILD_ROLES_TTL: Duration = Duration::from_secs(300);
+
+/// Per guild, the roles granting Administrator or Manage Server, so admin checks on
+/// message events don't fetch the guild's roles every time
+#[derive(Default)]
+pub struct AdminRoleCache {
+    guilds: Mutex<HashMap<u64, (Instant, HashSet<RoleId>)>>,
+}
+
+impl AdminRoleCache {
+    /// Admin check for message events, which carry the author's roles but not their permissions
+    pub async fn is_admin_by_roles(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        roles: &[RoleId],
+        config: &DiscordConfig,
+    ) -> bool {
+        if roles.iter().any(|role| config.admin_role_ids.contains(&role.0)) {
+            return true;
+        }
+        let Some(admin_roles) = self.admin_roles(http, guild_id).await else {
+            return false;
+        };
+        // The @everyone role shares the guild's id
+        roles
+            .iter()
+            .chain(std::iter::once(&RoleId(guild_id.0)))
+            .any(|role| admin_roles.contains(role))
+    }
+
+    /// Drop the guild's roles, e.g. after one of them changed
+    pub async fn forget(&self, guild_id: GuildId) {
+        self.guilds.lock().await.remove(&guild_id.0);
+    }
+
+    async fn admin_roles(&self, http: &Http, guild_id: GuildId) -> Option<HashSet<RoleId>> {
+        if let Some((fetched, admin_roles)) = self.guilds.lock().await.get(&guild_id.0) {
+            if fetched.elapsed() < GUILD_ROLES_TTL {
+                return Some(admin_roles.clone());
+            }
+        }
+
+        let guild_roles = match guild_id.roles(http).await {
+            Ok(guild_roles) => guild_roles,
+            Err(e) => {
+                eprintln!("[ERROR] Failed to fetch roles of guild {}: {:?}", guild_id, e);
+                return None;
+            }
+        };
+        let admin_roles: HashSet<RoleId> = guild_roles
+            .into_values()
+            .filter(|role| role.permissions.administrator() || role.permissions.manage_guild())
+            .map(|role| role.id)
+            .collect();
+        self.guilds
+            .lock()
+            .await
+            .insert(guild_id.0, (Instant::now(), admin_roles.clone()));
+        Some(admin_roles)
+    }
+}
+
+/// Whether one of the guild's chatbot access grants names the user or one of their roles
+pub fn allowlisted(grants: &[AccessGrant], user_id: UserId, roles: &[RoleId]) -> bool {
+    grants.iter().any(|grant| match grant.kind {
+        AccessKind::User => grant.target_id == user_id.to_string(),
+        AccessKind::Role => roles.iter().any(|role| grant.target_id == role.to_string()),
+    })
+}
+
+/// Shared guard run before every command handler; `Err` carries the reason shown to the user
+pub async fn check(
+    command: &ApplicationCommandInteraction,
+    store: &dyn Store,
+    config: &DiscordConfig,
+) -> Result<(), String> {
+    let required = requirement(&command.data.name);
+    if required == Requirement::Anyone {
+        return Ok(());
+    }
+
+    let (Some(guild_id), Some(member)) = (command.guild_id, command.member.as_ref()) else {
+        return Err("This command can only be used in a server.".to_string());
+    };
+
+    if is_admin(Some(member), config) {
+        return Ok(());
+    }
+
+    if required == Requirement::ChatbotControl {
+        let grants = store.list_access(guild_id.0).await.map_err(|e| {
+            eprintln!("[ERROR] {}", e);
+            "Could not check your permissions. Please try again later.".to_string()
+        })?;
+        if allowlisted(&grants, member.user.id, &member.roles) {
+            return Ok(());
+        }
+        return Err("You are not allowed to control the chatbot. Ask an admin for /chatbot-access.".to_string());
+    }
+
+    Err("Only admins can use this command.".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_command_has_its_requirement() {
+        for name in ["run-chatbot", "stop-chatbot"] {
+            assert_eq!(requirement(name), Requirement::ChatbotControl, "/{}", name);
+        }
+        for name in ["chatbot-logs", "ai-channel", "channel-persona", "chatbot-access"] {
+            assert_eq!(requirement(name), Requirement::Admin, "/{}", name);
+        }
+        // These guard other users' data inside the handler instead
+        for name in [
+            "setup-bot", "history", "forget", "unregister", "export", "import", "nickname", "persona", "imagine",
+        ] {
+            assert_eq!(requirement(name), Requirement::Anyone, "/{}", name);
+        }
+    }
+
+    #[test]
+    fn allowlist_matches_users_and_roles() {
+        let grants = vec![
+            AccessGrant::new(10, AccessKind::User, 1),
+            AccessGrant::new(10, AccessKind::Role, 500),
+        ];
+        assert!(allowlisted(&grants, UserId(1), &[]));
+        assert!(allowlisted(&grants, UserId(2), &[RoleId(400), RoleId(500)]));
+        assert!(!allowlisted(&grants, UserId(2), &[RoleId(400)]));
+        assert!(!allowlisted(&[], UserId(1), &[RoleId(500)]));
+    }
+
+    #[test]
+    fn grant_kinds_do_not_cross() {
+        // A role grant must not match a user whose id happens to be the same, and vice versa
+        let role_grant = vec![AccessGrant::new(10, AccessKind::Role, 7)];
+        assert!(!allowlisted(&role_grant, UserId(7), &[]));
+        let user_grant = vec![AccessGrant::new(10, AccessKind::User, 7)];
+        assert!(!allowlisted(&user_grant, UserId(8), &[RoleId(7)]));
+    }
+
+    fn role_json(id: u64, permissions: u64) -> serde_json::Value {
+        serde_json::json!({
+            "id": id.to_string(),
+            "name": format!("role {}", id),
+            "color": 0,
+            "hoist": false,
+            "managed": false,
+            "mentionable": false,
+            "permissions": permissions.to_string(),
+            "position": 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn guild_roles_are_fetched_once() {
+        use serenity::http::HttpBuilder;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        // @everyone (the guild's id) has no rights, role 20 has Manage Server
+        Mock::given(method("GET"))
+            .and(path("/api/v10/guilds/10/roles"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![role_json(10, 0), role_json(20, 0x20)]))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let http = HttpBuilder::new("token")
+            .proxy(server.uri())
+            .unwrap()
+            .ratelimiter_disabled(true)
+            .build();
+        let config = DiscordConfig::default();
+        let cache = AdminRoleCache::default();
+
+        assert!(cache.is_admin_by_roles(&http, GuildId(10), &[RoleId(20)], &config).await);
+        assert!(!cache.is_admin_by_roles(&http, GuildId(10), &[RoleId(30)], &config).await);
+        assert!(!cache.is_admin_by_roles(&http, GuildId(10), &[], &config).await);
+
+        // A role change drops the cached roles, so the next check fetches them again
+        cache.forget(GuildId(10)).await;
+        assert!(cache.is_admin_by_roles(&http, GuildId(10), &[RoleId(20)], &config).await);
+    }
+}
@@ -0,0 +1,303 @@
+use regex::Regex;
+use std::collections::HashSet;
+use crate::config::{FilterOverride, PostprocessConfig};
+
+/// Built-in filters, by the names used in `[postprocess]`
+pub const STRIP_ROLE_TOKENS: &str = "strip_role_tokens";
+pub const STRIP_ACTION_MARKERS: &str = "strip_action_markers";
+pub const COLLAPSE_WHITESPACE: &str = "collapse_whitespace";
+pub const BUILTIN_FILTERS: [&str; 3] = [STRIP_ROLE_TOKENS, STRIP_ACTION_MARKERS, COLLAPSE_WHITESPACE];
+
+const FENCE: &str = "```";
+
+enum Kind {
+    /// Prompt-format tokens and speaker labels the model echoes back
+    RoleTokens { tokens: Regex, labels: Regex },
+    /// Roleplay actions such as "*smiles*" on their own at the start or end of a line
+    ActionMarkers { leading: Regex, trailing: Regex },
+    /// Runs of spaces, trailing spaces and extra blank lines; code blocks are left alone
+    Whitespace { spaces: Regex, trailing: Regex, blank_lines: Regex },
+    /// A `[[postprocess.rules]]` entry
+    Custom { pattern: Regex, replacement: String },
+}
+
+struct Filter {
+    name: String,
+    on_by_default: bool,
+    kind: Kind,
+}
+
+impl Filter {
+    fn apply(&self, text: &str) -> String {
+        match &self.kind {
+            Kind::RoleTokens { tokens, labels } => {
+                let text = tokens.replace_all(text, "");
+                labels.replace_all(&text, "").into_owned()
+            }
+            Kind::ActionMarkers { leading, trailing } => outside_code(text, |prose| {
+                let prose = leading.replace_all(prose, "");
+                trailing.replace_all(&prose, "").into_owned()
+            }),
+            Kind::Whitespace { spaces, trailing, blank_lines } => outside_code(text, |prose| {
+                let prose = spaces.replace_all(prose, " ");
+                let prose = trailing.replace_all(&prose, "");
+                blank_lines.replace_all(&prose, "\n\n").into_owned()
+            }),
+            Kind::Custom { pattern, replacement } => {
+                pattern.replace_all(text, replacement.as_str()).into_owned()
+            }
+        }
+    }
+}
+
+/// Ordered, precompiled post-processing applied to every AI response before it is sent
+pub struct Pipeline {
+    filters: Vec<Filter>,
+    overrides: Vec<FilterOverride>,
+}
+
+/// The filters that apply to one response
+pub struct ActiveFilters<'a> {
+    pipeline: &'a Pipeline,
+    enabled: HashSet<&'a str>,
+}
+
+impl Pipeline {
+    /// Compile the configured pipeline. Fails on unknown filter names and invalid regexes.
+    pub fn new(config: &PostprocessConfig) -> Result<Pipeline, String> {
+        let mut names: Vec<&str> = BUILTIN_FILTERS.to_vec();
+        for rule in &config.rules {
+            if names.contains(&rule.name.as_str()) {
+                return Err(format!("postprocess rule name '{}' is already taken", rule.name));
+            }
+            names.push(&rule.name);
+        }
+
+        let check = |name: &String| {
+            if names.contains(&name.as_str()) {
+                Ok(())
+            } else {
+                Err(format!("postprocess: unknown filter '{}'", name))
+            }
+        };
+        for name in &config.filters {
+            check(name)?;
+        }
+        for o in &config.overrides {
+            if o.guild_id.is_none() && o.model.is_none() {
+                return Err("postprocess overrides need a guild_id or a model".to_string());
+            }
+            for name in o.enable.iter().chain(&o.disable) {
+                check(name)?;
+            }
+        }
+
+        // Listed filters run in the listed order; the rest only where an override enables them
+        let mut order: Vec<&str> = Vec::new();
+        for name in config.filters.iter().map(String::as_str).chain(names.iter().copied()) {
+            if !order.contains(&name) {
+                order.push(name);
+            }
+        }
+
+        let mut filters = Vec::new();
+        for name in order {
+            let kind = match name {
+                STRIP_ROLE_TOKENS => Kind::RoleTokens {
+                    tokens: compile(r"(?i)(\[/?(INST|SYS|SYSTEM|USER|BOT|ASSISTANT)\]|<</?SYS>>|<\|start_header_id\|>\w*<\|end_header_id\|>|<\|[a-z_]+\|>|</?s>)[:!?,.\-–]*[ \t]*")?,
+                    labels: compile(r"(?im)^[ \t]*(user|bot|assistant|system)[ \t]*:[ \t]*")?,
+                },
+                STRIP_ACTION_MARKERS => Kind::ActionMarkers {
+                    leading: compile(r"(?m)^[ \t]*\*[^*\s][^*\n]*\*[ \t]*")?,
+                    trailing: compile(r"(?m)[ \t]*\*[^*\s][^*\n]*\*[ \t]*$")?,
+                },
+                COLLAPSE_WHITESPACE => Kind::Whitespace {
+                    spaces: compile(r"[ \t]{2,}")?,
+                    trailing: compile(r"(?m)[ \t]+$")?,
+                    blank_lines: compile(r"\n{3,}")?,
+                },
+                _ => {
+                    let rule = config.rules.iter().find(|r| r.name == name).expect("validated above");
+                    Kind::Custom {
+                        pattern: Regex::new(&rule.pattern)
+                            .map_err(|e| format!("postprocess rule '{}' has an invalid pattern: {}", rule.name, e))?,
+                        replacement: rule.replacement.clone(),
+                    }
+                }
+            };
+            filters.push(Filter {
+                name: name.to_string(),
+                on_by_default: config.filters.iter().any(|f| f == name),
+                kind,
+            });
+        }
+
+        Ok(Pipeline { filters, overrides: config.overrides.clone() })
+    }
+
+    /// Resolve the toggles for a guild and model. Model overrides apply first, so a
+    /// guild override has the final say.
+    pub fn for_context(&self, guild_id: Option<u64>, model: &str) -> ActiveFilters<'_> {
+        let mut enabled: HashSet<&str> = self
+            .filters
+            .iter()
+            .filter(|f| f.on_by_default)
+            .map(|f| f.name.as_str())
+            .collect();
+
+        let model_overrides = self.overrides.iter().filter(|o| o.guild_id.is_none());
+        let guild_overrides = self.overrides.iter().filter(|o| o.guild_id.is_some());
+        for o in model_overrides.chain(guild_overrides) {
+            let guild_matches = o.guild_id.is_none_or(|id| Some(id) == guild_id);
+            let model_matches = o.model.as_deref().is_none_or(|m| m.eq_ignore_ascii_case(model));
+            if !(guild_matches && model_matches) {
+                continue;
+            }
+            for name in &o.disable {
+                enabled.remove(name.as_str());
+            }
+            enabled.extend(o.enable.iter().map(String::as_str));
+        }
+
+        ActiveFilters { pipeline: self, enabled }
+    }
+}
+
+impl ActiveFilters<'_> {
+    /// Run the enabled filters in order
+    pub fn apply(&self, response: &str) -> String {
+        let mut text = response.to_string();
+        for filter in &self.pipeline.filters {
+            if self.enabled.contains(filter.name.as_str()) {
+                text = filter.apply(&text);
+            }
+        }
+        text.trim().to_string()
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid built-in pattern {}: {}", pattern, e))
+}
+
+/// Apply `f` to the text outside fenced code blocks
+fn outside_code(text: &str, f: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut prose = String::new();
+    let mut in_code = false;
+
+    for line in text.split_inclusive('\n') {
+        let is_fence = line.trim_start().starts_with(FENCE);
+        if in_code {
+            out.push_str(line);
+            in_code = !is_fence;
+        } else if is_fence {
+            out.push_str(&f(&prose));
+            prose.clear();
+            out.push_str(line);
+            in_code = true;
+        } else {
+            prose.push_str(line);
+        }
+    }
+    out.push_str(&f(&prose));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FilterOverride, RegexRule};
+
+    fn pipeline(config: PostprocessConfig) -> Pipeline {
+        Pipeline::new(&config).unwrap()
+    }
+
+    fn clean(text: &str) -> String {
+        pipeline(PostprocessConfig::default()).for_context(None, "").apply(text)
+    }
+
+    #[test]
+    fn keeps_what_the_old_cleanup_removed() {
+        assert_eq!(clean("[Bot]: Hello there"), "Hello there");
+        assert_eq!(clean("[INST] <<SYS>> Hi <</SYS>> [/INST] Sure thing"), "Hi Sure thing");
+        assert_eq!(clean("<|start_header_id|>assistant<|end_header_id|>\n\nHey<|eot_id|>"), "Hey");
+        assert_eq!(clean("User: hi\nBot: Hello!"), "hi\nHello!");
+        assert_eq!(clean("*smiles warmly* Nice to meet you!"), "Nice to meet you!");
+        assert_eq!(clean("Sure.   Let me   check.  \n\n\n\nDone"), "Sure. Let me check.\n\nDone");
+    }
+
+    #[test]
+    fn old_false_positives_survive() {
+        // The old `!\s*[^.!?]*` rule deleted everything after an exclamation mark
+        assert_eq!(clean("Wow! That is great news."), "Wow! That is great news.");
+        // `\[\w+\]` ate citations and checkboxes; `^[!?,.\-–\s]+` ate leading punctuation
+        assert_eq!(clean("See [1] and [x] done"), "See [1] and [x] done");
+        assert_eq!(clean("- first\n- second"), "- first\n- second");
+        assert_eq!(clean("...well, maybe"), "...well, maybe");
+        // `\s{2,}` flattened paragraphs and code indentation
+        assert_eq!(clean("One.\n\nTwo."), "One.\n\nTwo.");
+        let code = "```py\nif x:\n    print(\"*a*  b\")\n```";
+        assert_eq!(clean(code), code);
+        // Bold and emphasis mid-sentence are not actions
+        assert_eq!(clean("This is **really** *very* nice"), "This is **really** *very* nice");
+    }
+
+    #[test]
+    fn custom_rules_run_in_order_and_toggle_per_guild_and_model() {
+        let config = PostprocessConfig {
+            filters: vec!["signature".to_string(), COLLAPSE_WHITESPACE.to_string()],
+            rules: vec![
+                RegexRule {
+                    name: "signature".to_string(),
+                    pattern: r"(?m)^-- .*$".to_string(),
+                    replacement: String::new(),
+                },
+                RegexRule {
+                    name: "shout".to_string(),
+                    pattern: "hello".to_string(),
+                    replacement: "HELLO".to_string(),
+                },
+            ],
+            overrides: vec![
+                FilterOverride {
+                    guild_id: None,
+                    model: Some("llama3".to_string()),
+                    enable: vec!["shout".to_string()],
+                    disable: vec![],
+                },
+                FilterOverride {
+                    guild_id: Some(7),
+                    model: None,
+                    enable: vec![],
+                    disable: vec!["shout".to_string(), "signature".to_string()],
+                },
+            ],
+        };
+        let pipeline = pipeline(config);
+        let text = "hello  there\n-- bot";
+
+        assert_eq!(pipeline.for_context(None, "llama2").apply(text), "hello there");
+        assert_eq!(pipeline.for_context(Some(1), "Llama3").apply(text), "HELLO there");
+        assert_eq!(pipeline.for_context(Some(7), "llama3").apply(text), "hello there\n-- bot");
+    }
+
+    #[test]
+    fn rejects_unknown_filters_and_bad_patterns() {
+        let unknown = PostprocessConfig {
+            filters: vec!["nope".to_string()],
+            ..Default::default()
+        };
+        assert!(Pipeline::new(&unknown).is_err());
+
+        let bad = PostprocessConfig {
+            rules: vec![RegexRule {
+                name: "bad".to_string(),
+                pattern: "(".to_string(),
+                replacement: String::new(),
+            }],
+            ..Default::default()
+        };
+        assert!(Pipeline::new(&bad).is_err());
+    }
+}
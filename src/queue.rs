@@ -0,0 +1,285 @@
+use serenity::http::Http;
+use serenity::model::id::{ChannelId, MessageId};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use crate::config::QueueConfig;
+
+/// A unit of backend work, e.g. generating and relaying one chat reply
+pub type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Where a submitted job is; the sender is dropped once the job finishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueStatus {
+    /// Waiting, 1-based position in line
+    Queued(usize),
+    Running,
+}
+
+/// Per-user lanes served round-robin, so one user's backlog can't starve others
+struct Lanes<T> {
+    /// Users with waiting items, in the order they will next be served
+    order: VecDeque<u64>,
+    lanes: HashMap<u64, VecDeque<T>>,
+    len: usize,
+}
+
+impl<T> Lanes<T> {
+    fn new() -> Self {
+        Self {
+            order: VecDeque::new(),
+            lanes: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, user: u64, item: T) {
+        let lane = self.lanes.entry(user).or_default();
+        if lane.is_empty() {
+            self.order.push_back(user);
+        }
+        lane.push_back(item);
+        self.len += 1;
+    }
+
+    /// Take the next user's oldest item; that user moves to the back of the line
+    fn pop(&mut self) -> Option<T> {
+        let user = self.order.pop_front()?;
+        let lane = self.lanes.get_mut(&user)?;
+        let item = lane.pop_front()?;
+        if lane.is_empty() {
+            self.lanes.remove(&user);
+        } else {
+            self.order.push_back(user);
+        }
+        self.len -= 1;
+        Some(item)
+    }
+
+    /// Waiting items in the order `pop` will return them
+    fn in_service_order(&self) -> Vec<&T> {
+        let mut ordered = Vec::with_capacity(self.len);
+        let mut round = 0;
+        while ordered.len() < self.len {
+            for user in &self.order {
+                if let Some(item) = self.lanes.get(user).and_then(|lane| lane.get(round)) {
+                    ordered.push(item);
+                }
+            }
+            round += 1;
+        }
+        ordered
+    }
+}
+
+struct Waiting {
+    user: u64,
+    job: Job,
+    status: watch::Sender<QueueStatus>,
+}
+
+struct State {
+    waiting: Lanes<Waiting>,
+    running: usize,
+}
+
+/// Bounded queue in front of the backend with a fixed number of concurrent jobs
+pub struct GenerationQueue {
+    config: QueueConfig,
+    state: Mutex<State>,
+}
+
+/// Returned when the queue is at `max_length`
+#[derive(Debug)]
+pub struct QueueFull;
+
+impl GenerationQueue {
+    pub fn new(config: &QueueConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config: config.clone(),
+            state: Mutex::new(State {
+                waiting: Lanes::new(),
+                running: 0,
+            }),
+        })
+    }
+
+    /// Queue a job for `user`. The receiver reports its position until it runs.
+    pub fn submit(self: &Arc<Self>, user: u64, job: Job) -> Result<watch::Receiver<QueueStatus>, QueueFull> {
+        let mut state = self.lock();
+        if state.waiting.len >= self.config.max_length {
+            return Err(QueueFull);
+        }
+
+        let (status, receiver) = watch::channel(QueueStatus::Queued(state.waiting.len + 1));
+        state.waiting.push(user, Waiting { user, job, status });
+        self.dispatch(&mut state);
+        Ok(receiver)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // Jobs run outside the lock, so poisoning can only come from a bug in here
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Start waiting jobs while workers are free, then tell the rest where they stand
+    fn dispatch(self: &Arc<Self>, state: &mut State) {
+        while state.running < self.config.workers.max(1) {
+            let Some(next) = state.waiting.pop() else {
+                break;
+            };
+            state.running += 1;
+            let _ = next.status.send(QueueStatus::Running);
+
+            let slot = Slot(self.clone());
+            let timeout = Duration::from_secs(self.config.job_timeout_secs);
+            tokio::spawn(async move {
+                // Keep the sender alive until the job is done so watchers see it finish
+                let status = next.status;
+                if tokio::time::timeout(timeout, next.job).await.is_err() {
+                    eprintln!(
+                        "[ERROR] Job for user {} timed out after {}s; starting the next one",
+                        next.user,
+                        timeout.as_secs()
+                    );
+                }
+                drop(status);
+                drop(slot);
+            });
+        }
+
+        for (index, waiting) in state.waiting.in_service_order().into_iter().enumerate() {
+            waiting.status.send_if_modified(|status| {
+                let position = QueueStatus::Queued(index + 1);
+                let changed = *status != position;
+                *status = position;
+                changed
+            });
+        }
+    }
+}
+
+fn position_text(position: usize) -> String {
+    format!("⏳ You are #{} in queue.", position)
+}
+
+/// Post "you are #N in queue" in reply to the request while it waits, keep it current,
+/// and remove it once the job starts. Jobs that start right away post nothing.
+pub async fn report_position(
+    http: Arc<Http>,
+    channel: ChannelId,
+    request: MessageId,
+    mut status: watch::Receiver<QueueStatus>,
+) {
+    let position = match *status.borrow_and_update() {
+        QueueStatus::Queued(position) => position,
+        QueueStatus::Running => return,
+    };
+
+    let mut notice = match channel
+        .send_message(&http, |m| m.content(position_text(position)).reference_message((channel, request)))
+        .await
+    {
+        Ok(message) => message,
+        Err(e) => {
+            eprintln!("[ERROR] Failed to post queue position: {:?}", e);
+            return;
+        }
+    };
+
+    while status.changed().await.is_ok() {
+        let current = *status.borrow_and_update();
+        match current {
+            QueueStatus::Queued(position) => {
+                let _ = notice.edit(&http, |m| m.content(position_text(position))).await;
+            }
+            QueueStatus::Running => break,
+        }
+    }
+
+    let _ = notice.delete(&http).await;
+}
+
+/// A running job's worker slot, given back even if the job panics
+struct Slot(Arc<GenerationQueue>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.running -= 1;
+        self.0.dispatch(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes_are_served_round_robin() {
+        let mut lanes = Lanes::new();
+        for item in ["a1", "a2", "a3"] {
+            lanes.push(1, item);
+        }
+        lanes.push(2, "b1");
+
+        let preview: Vec<&str> = lanes.in_service_order().into_iter().copied().collect();
+        assert_eq!(preview, vec!["a1", "b1", "a2", "a3"]);
+
+        assert_eq!(lanes.pop(), Some("a1"));
+        lanes.push(3, "c1");
+        let preview: Vec<&str> = lanes.in_service_order().into_iter().copied().collect();
+        assert_eq!(preview, vec!["b1", "a2", "c1", "a3"]);
+
+        let drained: Vec<&str> = std::iter::from_fn(|| lanes.pop()).collect();
+        assert_eq!(drained, preview);
+        assert_eq!(lanes.len, 0);
+    }
+
+    #[tokio::test]
+    async fn queue_limits_concurrency_and_length() {
+        let queue = GenerationQueue::new(&QueueConfig {
+            workers: 1,
+            max_length: 1,
+            ..QueueConfig::default()
+        });
+        let (release, hold) = tokio::sync::oneshot::channel::<()>();
+
+        let first = queue
+            .submit(1, Box::pin(async move {
+                let _ = hold.await;
+            }))
+            .unwrap();
+        assert_eq!(*first.borrow(), QueueStatus::Running);
+
+        let mut second = queue.submit(2, Box::pin(async {})).unwrap();
+        assert_eq!(*second.borrow(), QueueStatus::Queued(1));
+        assert!(queue.submit(3, Box::pin(async {})).is_err());
+
+        release.send(()).unwrap();
+        second.changed().await.unwrap();
+        assert_eq!(*second.borrow(), QueueStatus::Running);
+    }
+
+    #[tokio::test]
+    async fn hung_job_times_out() {
+        let queue = GenerationQueue::new(&QueueConfig {
+            workers: 1,
+            job_timeout_secs: 1,
+            ..QueueConfig::default()
+        });
+        let mut hung = queue.submit(1, Box::pin(std::future::pending())).unwrap();
+        assert_eq!(*hung.borrow_and_update(), QueueStatus::Running);
+        let mut next = queue.submit(2, Box::pin(async {})).unwrap();
+        assert_eq!(*next.borrow(), QueueStatus::Queued(1));
+
+        // The hung job's status sender is dropped once it is given up on
+        let waited = tokio::time::timeout(Duration::from_secs(3), hung.changed()).await;
+        assert!(waited.unwrap().is_err());
+        next.changed().await.unwrap();
+        assert_eq!(*next.borrow(), QueueStatus::Running);
+    }
+}
@@ -0,0 +1,244 @@
+use serenity::model::id::{GuildId, RoleId, UserId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::config::RateLimitConfig;
+
+/// Buckets are pruned once the map grows past this many users
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BucketKey {
+    user: u64,
+    guild: Option<u64>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Whether the user was already told about the current cooldown
+    notified: bool,
+    /// The user's limit as of their last request; other users' requests prune by it
+    limit: Limit,
+}
+
+impl Bucket {
+    /// Tokens available at `now`, refilled at the bucket's own rate
+    fn refilled(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * self.limit.per_second).min(self.limit.capacity)
+    }
+}
+
+/// Limits that apply to one request
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Limit {
+    capacity: f64,
+    per_second: f64,
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cooldown {
+    pub retry_after: Duration,
+    /// False if the user has already been told about this cooldown
+    pub first_notice: bool,
+}
+
+impl Cooldown {
+    pub fn message(&self) -> String {
+        format!(
+            "⏳ Slow down! You can send another request in {}s.",
+            self.retry_after.as_secs().max(1)
+        )
+    }
+}
+
+/// Per-user token buckets shared by chat messages and expensive slash commands
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<BucketKey, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            config: config.clone(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a slash command draws from the bucket
+    pub fn limits_command(&self, name: &str) -> bool {
+        self.config.enabled && self.config.commands.iter().any(|c| c == name)
+    }
+
+    /// Whether admins skip the limiter entirely
+    pub fn exempts_admins(&self) -> bool {
+        self.config.exempt_admins
+    }
+
+    /// Take one token for the user, or report how long until one is available
+    pub fn check(&self, user: UserId, guild: Option<GuildId>, roles: &[RoleId]) -> Result<(), Cooldown> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let key = BucketKey {
+            user: user.0,
+            guild: if self.config.per_guild { guild.map(|g| g.0) } else { None },
+        };
+        self.check_at(key, self.limit_for(roles), Instant::now())
+    }
+
+    fn limit_for(&self, roles: &[RoleId]) -> Limit {
+        let base = (self.config.capacity, self.config.per_minute);
+        let (capacity, per_minute) = self
+            .config
+            .roles
+            .iter()
+            .filter(|r| roles.iter().any(|role| role.0 == r.role_id))
+            .map(|r| (r.capacity, r.per_minute))
+            .fold(base, |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+        Limit {
+            capacity: capacity.max(1) as f64,
+            per_second: per_minute.max(1) as f64 / 60.0,
+        }
+    }
+
+    fn check_at(&self, key: BucketKey, limit: Limit, now: Instant) -> Result<(), Cooldown> {
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            // A panic elsewhere must not lock everyone out
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            // Full buckets hold nothing a fresh one wouldn't
+            buckets.retain(|_, b| b.refilled(now) < b.limit.capacity);
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: limit.capacity,
+            updated: now,
+            notified: false,
+            limit,
+        });
+
+        // Time since the last request refills at the limit that applied then; a role
+        // change only counts from this request on
+        bucket.tokens = bucket.refilled(now).min(limit.capacity);
+        bucket.limit = limit;
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.notified = false;
+            return Ok(());
+        }
+
+        let first_notice = !bucket.notified;
+        bucket.notified = true;
+        Err(Cooldown {
+            retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / limit.per_second),
+            first_notice,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RoleRateLimit;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig {
+            capacity: 2,
+            per_minute: 6,
+            roles: vec![RoleRateLimit {
+                role_id: 9,
+                capacity: 5,
+                per_minute: 60,
+            }],
+            ..RateLimitConfig::default()
+        })
+    }
+
+    const KEY: BucketKey = BucketKey { user: 1, guild: None };
+
+    #[test]
+    fn bucket_empties_and_refills() {
+        let limiter = limiter();
+        let limit = limiter.limit_for(&[]);
+        let start = Instant::now();
+
+        assert!(limiter.check_at(KEY, limit, start).is_ok());
+        assert!(limiter.check_at(KEY, limit, start).is_ok());
+
+        let cooldown = limiter.check_at(KEY, limit, start).unwrap_err();
+        assert_eq!(cooldown.retry_after.as_secs(), 10);
+        assert!(cooldown.first_notice);
+        assert!(!limiter.check_at(KEY, limit, start).unwrap_err().first_notice);
+
+        // 6 per minute refills one token every 10 seconds
+        assert!(limiter.check_at(KEY, limit, start + Duration::from_secs(10)).is_ok());
+        assert!(limiter.check_at(KEY, limit, start + Duration::from_secs(10)).is_err());
+    }
+
+    #[test]
+    fn roles_get_their_own_limit() {
+        let limiter = limiter();
+        assert_eq!(limiter.limit_for(&[RoleId(3)]).capacity, 2.0);
+        assert_eq!(limiter.limit_for(&[RoleId(3), RoleId(9)]).capacity, 5.0);
+    }
+
+    #[test]
+    fn pruning_uses_each_buckets_own_limit() {
+        let limiter = limiter();
+        let base = limiter.limit_for(&[]);
+        let vip = limiter.limit_for(&[RoleId(9)]);
+        let start = Instant::now();
+
+        // Base users who have refilled by now, and a role holder one token short of their capacity of 5
+        for user in 1..=PRUNE_THRESHOLD as u64 {
+            let key = BucketKey { user, guild: Some(1) };
+            limiter.check_at(key, base, start).unwrap();
+        }
+        let partial = BucketKey { user: 0, guild: Some(1) };
+        let now = start + Duration::from_secs(60);
+        limiter.check_at(partial, vip, now).unwrap();
+
+        // At the base limit the partial bucket would count as full and be dropped
+        limiter.check_at(KEY, base, now + Duration::from_millis(500)).unwrap();
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert!(buckets.contains_key(&partial));
+        assert!((buckets[&partial].tokens - 4.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn refill_uses_the_previous_limit() {
+        let limiter = limiter();
+        let base = limiter.limit_for(&[]);
+        let vip = limiter.limit_for(&[RoleId(9)]);
+        let start = Instant::now();
+
+        limiter.check_at(KEY, base, start).unwrap();
+        limiter.check_at(KEY, base, start).unwrap();
+        // Gaining the role does not refill the time spent at the base rate any faster
+        assert!(limiter.check_at(KEY, vip, start + Duration::from_secs(5)).is_err());
+        assert!(limiter.check_at(KEY, vip, start + Duration::from_secs(6)).is_ok());
+    }
+
+    #[test]
+    fn disabled_limiter_allows_everything() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            enabled: false,
+            capacity: 1,
+            ..RateLimitConfig::default()
+        });
+        for _ in 0..10 {
+            assert!(limiter.check(UserId(1), None, &[]).is_ok());
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use futures_util::{Stream, StreamExt};
+use serenity::http::Http;
+use serenity::model::channel::{AttachmentType, Message};
+use serenity::model::id::ChannelId;
+use serenity::model::webhook::Webhook;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::formatting::{format_reply, shorten, Reply};
+use crate::postprocess::ActiveFilters;
+
+/// Shown while the model has not produced anything yet
+const PLACEHOLDER: &str = "💭 …";
+
+/// Discord rejects messages over 2000 characters; in-progress edits are cut short of that
+const PREVIEW_LIMIT: usize = 1990;
+
+/// Where a response is posted: as the bot itself, or through a channel's persona webhook
+pub enum ReplyTarget {
+    Bot(ChannelId),
+    Webhook {
+        webhook: Box<Webhook>,
+        username: String,
+        avatar_url: Option<String>,
+    },
+}
+
+impl ReplyTarget {
+    async fn post(&self, http: &Http, content: &str) -> Result<Message, String> {
+        match self {
+            ReplyTarget::Bot(channel) => channel
+                .send_message(http, |m| m.content(content).allowed_mentions(|a| a.empty_parse()))
+                .await
+                .map_err(|e| format!("{:?}", e)),
+            ReplyTarget::Webhook { webhook, username, avatar_url } => webhook
+                .execute(http, true, |w| {
+                    w.content(content).username(username).allowed_mentions(|m| m.empty_parse());
+                    if let Some(avatar_url) = avatar_url {
+                        w.avatar_url(avatar_url);
+                    }
+                    w
+                })
+                .await
+                .map_err(|e| format!("{:?}", e))?
+                .ok_or_else(|| "Webhook did not return the message".to_string()),
+        }
+    }
+
+    async fn edit(&self, http: &Http, message: &mut Message, content: &str) -> Result<(), String> {
+        let result = match self {
+            ReplyTarget::Bot(_) => message.edit(http, |m| m.content(content)).await,
+            ReplyTarget::Webhook { webhook, .. } => webhook
+                .edit_message(http, message.id, |m| m.content(content))
+                .await
+                .map(|_| ()),
+        };
+        result.map_err(|e| format!("{:?}", e))
+    }
+
+    async fn delete(&self, http: &Http, message: &Message) -> Result<(), String> {
+        let result = match self {
+            ReplyTarget::Bot(_) => message.delete(http).await,
+            ReplyTarget::Webhook { webhook, .. } => webhook.delete_message(http, message.id).await,
+        };
+        result.map_err(|e| format!("{:?}", e))
+    }
+
+    async fn post_file(&self, http: &Http, content: &str, body: String) -> Result<(), String> {
+        let file = AttachmentType::Bytes {
+            data: body.into_bytes().into(),
+            filename: "response.md".to_string(),
+        };
+        let result = match self {
+            ReplyTarget::Bot(channel) => channel
+                .send_message(http, |m| m.content(content).add_file(file).allowed_mentions(|a| a.empty_parse()))
+                .await
+                .map(|_| ()),
+            ReplyTarget::Webhook { webhook, username, avatar_url } => webhook
+                .execute(http, true, |w| {
+                    w.content(content).username(username).add_file(file).allowed_mentions(|m| m.empty_parse());
+                    if let Some(avatar_url) = avatar_url {
+                        w.avatar_url(avatar_url);
+                    }
+                    w
+                })
+                .await
+                .map(|_| ()),
+        };
+        result.map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// Relay a streamed chat response into Discord.
+///
+/// Posts a placeholder immediately, edits it with the text received so far at most
+/// once per `edit_interval` (Discord allows roughly 5 edits per 5 seconds per channel),
+/// and finishes with the full response run through `filters`, split across messages or attached as a
+/// file once longer than `attach_over` characters. Returns the cleaned text.
+pub async fn relay_stream<S, B, E>(
+    http: &Arc<Http>,
+    target: &ReplyTarget,
+    mut stream: S,
+    filters: &ActiveFilters<'_>,
+    edit_interval: Duration,
+    attach_over: usize,
+) -> Result<String, String>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Display,
+{
+    let mut message = target
+        .post(http, PLACEHOLDER)
+        .await
+        .map_err(|e| format!("Failed to post placeholder: {}", e))?;
+
+    // Collect raw bytes so multi-byte characters split across chunks decode correctly
+    let mut raw: Vec<u8> = Vec::new();
+    let mut last_edit = Instant::now();
+    let mut last_preview = String::new();
+
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(bytes) => raw.extend_from_slice(bytes.as_ref()),
+            Err(e) => {
+                let _ = target.edit(http, &mut message, "Failed to read chatbot response.").await;
+                return Err(format!("Stream interrupted: {}", e));
+            }
+        }
+
+        if last_edit.elapsed() < edit_interval {
+            continue;
+        }
+
+        let preview = preview_text(&String::from_utf8_lossy(&raw));
+        if preview.is_empty() || preview == last_preview {
+            continue;
+        }
+
+        if let Err(e) = target.edit(http, &mut message, &preview).await {
+            eprintln!("[ERROR] Failed to edit streaming message: {}", e);
+        }
+        last_preview = preview;
+        last_edit = Instant::now();
+    }
+
+    let mut text = filters.apply(&String::from_utf8_lossy(&raw));
+    if text.is_empty() {
+        text = "The chatbot returned nothing.".to_string();
+    }
+
+    match format_reply(&text, attach_over) {
+        Reply::Messages(chunks) => {
+            let mut chunks = chunks.iter();
+            if let Some(first) = chunks.next() {
+                if let Err(e) = target.edit(http, &mut message, first).await {
+                    eprintln!("[ERROR] Failed to send AI response: {}", e);
+                }
+            }
+            for chunk in chunks {
+                if let Err(e) = target.post(http, chunk).await {
+                    eprintln!("[ERROR] Failed to send AI response: {}", e);
+                    break;
+                }
+            }
+        }
+        Reply::Attachment { notice, body } => {
+            match target.post_file(http, &notice, body).await {
+                Ok(()) => {
+                    let _ = target.delete(http, &message).await;
+                }
+                Err(e) => {
+                    eprintln!("[ERROR] Failed to attach AI response: {}", e);
+                    let _ = target.edit(http, &mut message, "The response was too long to send.").await;
+                }
+            }
+        }
+    }
+
+    Ok(text)
+}
+
+/// Trimmed in-progress text, shortened to fit in a single Discord message
+fn preview_text(text: &str) -> String {
+    shorten(text.trim(), PREVIEW_LIMIT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::stream;
+    use serenity::http::HttpBuilder;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use crate::config::PostprocessConfig;
+    use crate::postprocess::Pipeline;
+
+    #[test]
+    fn short_previews_are_trimmed() {
+        assert_eq!(preview_text("  hello \n"), "hello");
+        assert_eq!(preview_text(""), "");
+    }
+
+    #[test]
+    fn long_previews_fit_one_message() {
+        let text = "é".repeat(PREVIEW_LIMIT + 50);
+        let preview = preview_text(&text);
+        assert!(preview.chars().count() <= crate::formatting::MESSAGE_LIMIT);
+        assert!(preview.ends_with('…'));
+        assert_eq!(preview_text(&"é".repeat(PREVIEW_LIMIT)), "é".repeat(PREVIEW_LIMIT));
+    }
+
+    fn message_json(content: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": "900",
+            "channel_id": "100",
+            "author": { "id": "1", "username": "bot", "discriminator": "0001", "avatar": null },
+            "content": content,
+            "timestamp": "2024-01-01T00:00:00Z",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "pinned": false,
+            "type": 0,
+        })
+    }
+
+    /// Relay `text` in one chunk and return the (method, path) of every call made to Discord
+    async fn relay(text: &str, attach_over: usize) -> (Vec<(String, String)>, MockServer) {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v10/channels/100/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(message_json(PLACEHOLDER)))
+            .mount(&server)
+            .await;
+        Mock::given(method("PATCH"))
+            .and(path("/api/v10/channels/100/messages/900"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(message_json(text)))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v10/channels/100/messages/900"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let http = Arc::new(
+            HttpBuilder::new("token")
+                .proxy(server.uri())
+                .unwrap()
+                .ratelimiter_disabled(true)
+                .build(),
+        );
+        let pipeline = Pipeline::new(&PostprocessConfig::default()).unwrap();
+        let chunks = stream::iter(vec![Ok::<_, String>(text.to_string())]);
+        let sent = relay_stream(
+            &http,
+            &ReplyTarget::Bot(ChannelId(100)),
+            chunks,
+            &pipeline.for_context(None, ""),
+            Duration::from_secs(60),
+            attach_over,
+        )
+        .await
+        .unwrap();
+        assert_eq!(sent, text);
+
+        let calls = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| (r.method.to_string(), r.url.path().to_string()))
+            .collect();
+        (calls, server)
+    }
+
+    #[tokio::test]
+    async fn replies_at_the_threshold_are_edited_in() {
+        let (calls, _server) = relay(&"a".repeat(30), 30).await;
+        assert_eq!(
+            calls,
+            vec![
+                ("POST".to_string(), "/api/v10/channels/100/messages".to_string()),
+                ("PATCH".to_string(), "/api/v10/channels/100/messages/900".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn replies_over_the_threshold_are_attached() {
+        let (calls, server) = relay(&"a".repeat(31), 30).await;
+        assert_eq!(
+            calls,
+            vec![
+                ("POST".to_string(), "/api/v10/channels/100/messages".to_string()),
+                ("POST".to_string(), "/api/v10/channels/100/messages".to_string()),
+                ("DELETE".to_string(), "/api/v10/channels/100/messages/900".to_string()),
+            ]
+        );
+        let upload = &server.received_requests().await.unwrap()[1];
+        let body = String::from_utf8_lossy(&upload.body);
+        assert!(body.contains("filename=\"response.md\""));
+        assert!(body.contains("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"));
+    }
+
+    #[tokio::test]
+    async fn replies_do_not_ping() {
+        let (_, server) = relay("hi @everyone", 0).await;
+        let placeholder = &server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&placeholder.body).unwrap();
+        assert_eq!(body["allowed_mentions"]["parse"], serde_json::json!([]));
+    }
+}
@@ -0,0 +1,374 @@
+use serenity::http::Http;
+use serenity::model::id::ChannelId;
+use std::fmt;
+use std::path::Path;
+use std::process::{Child, Command as StdCommand, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex};
+use once_cell::sync::Lazy;
+use crate::backend::ChatBackend;
+use crate::chatbot_logs;
+use crate::config::ChatbotConfig;
+
+/// Lifecycle of the supervised Python chatbot process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatbotState {
+    /// Process spawned, not yet checked
+    Starting,
+    /// Process alive but /healthcheck not passing yet (model loading)
+    Warming,
+    /// Healthcheck passing, accepting requests
+    Ready,
+    /// Process exited unexpectedly
+    Crashed,
+    /// Not running (never started, stopped by a user, or gave up restarting)
+    Stopped,
+}
+
+impl fmt::Display for ChatbotState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            ChatbotState::Starting => "🟡 Starting",
+            ChatbotState::Warming => "🟠 Warming up",
+            ChatbotState::Ready => "🟢 Ready",
+            ChatbotState::Crashed => "🔴 Crashed",
+            ChatbotState::Stopped => "⚫ Stopped",
+        };
+        f.write_str(label)
+    }
+}
+
+struct Supervised {
+    child: Option<Child>,
+    /// Bumped on every start/stop so stale supervisor tasks exit
+    generation: u64,
+    /// Consecutive crashes since the last time the process became ready
+    restarts: u32,
+}
+
+/// Global async-safe storage for the running chatbot process
+static CHATBOT_PROCESS: Lazy<Mutex<Supervised>> = Lazy::new(|| {
+    Mutex::new(Supervised {
+        child: None,
+        generation: 0,
+        restarts: 0,
+    })
+});
+
+/// Current state, observable by anyone waiting for readiness
+static STATE: Lazy<watch::Sender<ChatbotState>> =
+    Lazy::new(|| watch::channel(ChatbotState::Stopped).0);
+
+/// Everything the supervisor task needs to run, restart and report
+#[derive(Clone)]
+pub struct SupervisorContext {
+    pub http: Arc<Http>,
+    pub config: ChatbotConfig,
+    pub backend: Arc<dyn ChatBackend>,
+}
+
+pub fn current_state() -> ChatbotState {
+    *STATE.borrow()
+}
+
+/// Helper: start Python chatbot process
+fn start_ai_chatbot(config: &ChatbotConfig) -> Result<Child, String> {
+    let python_exe = config.python_exe();
+    let chatbot_script = &config.script_path;
+
+    if !Path::new(chatbot_script).exists() {
+        return Err(format!("{} not found.", chatbot_script));
+    }
+
+    let mut child = StdCommand::new(python_exe)
+        .arg(chatbot_script)
+        // Flush Python output line by line so the logs are live
+        .env("PYTHONUNBUFFERED", "1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start chatbot: {}", e))?;
+
+    chatbot_logs::capture(&mut child, config);
+    Ok(child)
+}
+
+/// Record a state change and tell the admin channel about it
+async fn set_state(ctx: &SupervisorContext, state: ChatbotState, detail: Option<String>) {
+    let previous = STATE.send_replace(state);
+    if previous == state {
+        return;
+    }
+
+    println!("[LOG] Chatbot state: {:?} -> {:?}", previous, state);
+
+    if let Some(channel_id) = ctx.config.admin_channel_id {
+        let content = match detail {
+            Some(detail) => format!("Chatbot: {} ({})", state, detail),
+            None => format!("Chatbot: {}", state),
+        };
+        if let Err(e) = ChannelId(channel_id).say(&ctx.http, content).await {
+            eprintln!("[ERROR] Failed to post chatbot state to admin channel: {:?}", e);
+        }
+    }
+}
+
+/// Spawn the process and its supervisor task. Fails if it is already running.
+pub async fn start(ctx: SupervisorContext) -> Result<(), String> {
+    let mut supervised = CHATBOT_PROCESS.lock().await;
+    if supervised.child.is_some() {
+        return Err("Chatbot is already running.".to_string());
+    }
+
+    let child = start_ai_chatbot(&ctx.config)?;
+    supervised.child = Some(child);
+    supervised.generation += 1;
+    supervised.restarts = 0;
+    let generation = supervised.generation;
+    drop(supervised);
+
+    set_state(&ctx, ChatbotState::Starting, None).await;
+    tokio::spawn(supervise(ctx, generation));
+    Ok(())
+}
+
+/// Kill the process and stop supervising it. Returns false if it was not running.
+pub async fn stop(ctx: &SupervisorContext) -> bool {
+    let mut supervised = CHATBOT_PROCESS.lock().await;
+    supervised.generation += 1;
+    let child = supervised.child.take();
+    drop(supervised);
+
+    let was_running = match child {
+        Some(mut child) => {
+            if let Err(e) = child.kill() {
+                eprintln!("[ERROR] Failed to kill chatbot process: {:?}", e);
+            }
+            // wait() blocks until the process is reaped, so keep it off the async workers
+            match tokio::task::spawn_blocking(move || child.wait()).await {
+                Ok(Ok(status)) => println!("[LOG] Chatbot process stopped: {}", status),
+                Ok(Err(e)) => eprintln!("[ERROR] Failed to wait for chatbot process: {:?}", e),
+                Err(e) => eprintln!("[ERROR] Chatbot wait task failed: {:?}", e),
+            }
+            true
+        }
+        None => current_state() != ChatbotState::Stopped,
+    };
+
+    set_state(ctx, ChatbotState::Stopped, None).await;
+    was_running
+}
+
+/// Wait until the chatbot is Ready, or report the state it ended up in instead
+pub async fn wait_until_ready(timeout: Duration) -> Result<(), ChatbotState> {
+    let mut rx = STATE.subscribe();
+    let wait = async move {
+        rx.wait_for(|state| {
+            matches!(state, ChatbotState::Ready | ChatbotState::Crashed | ChatbotState::Stopped)
+        })
+        .await
+        .map(|state| *state)
+    };
+    match tokio::time::timeout(timeout, wait).await {
+        Ok(Ok(ChatbotState::Ready)) => Ok(()),
+        Ok(Ok(state)) => Err(state),
+        Ok(Err(_)) => Err(ChatbotState::Stopped),
+        Err(_) => Err(current_state()),
+    }
+}
+
+/// Poll the process and healthcheck; restart with exponential backoff on crashes
+async fn supervise(ctx: SupervisorContext, generation: u64) {
+    let poll = Duration::from_secs(ctx.config.health_poll_secs.max(1));
+    let mut started_at = Instant::now();
+
+    loop {
+        tokio::time::sleep(poll).await;
+
+        let mut supervised = CHATBOT_PROCESS.lock().await;
+        if supervised.generation != generation {
+            return;
+        }
+
+        let exit = match supervised.child.as_mut().map(|child| child.try_wait()) {
+            Some(Ok(Some(status))) => Some(status.to_string()),
+            Some(Ok(None)) => None,
+            Some(Err(e)) => {
+                eprintln!("[ERROR] Failed to poll chatbot process: {:?}", e);
+                None
+            }
+            None => return,
+        };
+
+        if let Some(status) = exit {
+            supervised.child = None;
+            supervised.restarts += 1;
+            let restarts = supervised.restarts;
+            drop(supervised);
+
+            eprintln!("[ERROR] Chatbot process exited: {}", status);
+            set_state(&ctx, ChatbotState::Crashed, Some(format!("exited with {}", status))).await;
+
+            if restarts > ctx.config.max_restarts {
+                set_state(
+                    &ctx,
+                    ChatbotState::Stopped,
+                    Some(format!("gave up after {} restarts", ctx.config.max_restarts)),
+                )
+                .await;
+                return;
+            }
+
+            let backoff = restart_backoff(&ctx.config, restarts);
+            println!("[LOG] Restarting chatbot in {:?} (attempt {})", backoff, restarts);
+            tokio::time::sleep(backoff).await;
+
+            let mut supervised = CHATBOT_PROCESS.lock().await;
+            if supervised.generation != generation {
+                return;
+            }
+            match start_ai_chatbot(&ctx.config) {
+                Ok(child) => {
+                    supervised.child = Some(child);
+                    drop(supervised);
+                    started_at = Instant::now();
+                    set_state(&ctx, ChatbotState::Starting, Some(format!("restart {}", restarts))).await;
+                }
+                Err(e) => {
+                    drop(supervised);
+                    eprintln!("[ERROR] {}", e);
+                    set_state(&ctx, ChatbotState::Stopped, Some(e)).await;
+                    return;
+                }
+            }
+            continue;
+        }
+        drop(supervised);
+
+        // Process is alive: check whether it is serving yet
+        let healthy = ctx.backend.health().await;
+        match (current_state(), healthy) {
+            (ChatbotState::Ready, true) => {}
+            (_, true) => {
+                CHATBOT_PROCESS.lock().await.restarts = 0;
+                let detail = format!("ready after {}s", started_at.elapsed().as_secs());
+                set_state(&ctx, ChatbotState::Ready, Some(detail)).await;
+            }
+            (ChatbotState::Starting, false) => set_state(&ctx, ChatbotState::Warming, None).await,
+            (ChatbotState::Ready, false) => {
+                set_state(&ctx, ChatbotState::Warming, Some("healthcheck failing".to_string())).await
+            }
+            (_, false) => {}
+        }
+    }
+}
+
+/// base * 2^(attempt-1), capped
+fn restart_backoff(config: &ChatbotConfig, attempt: u32) -> Duration {
+    let base = config.restart_backoff_secs.max(1);
+    let secs = base.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    Duration::from_secs(secs.min(config.max_backoff_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BackendConfig;
+
+    /// STATE is process-wide, so tests that drive it take turns
+    static STATE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+    /// A context with no admin channel, so state changes stay local
+    fn context() -> SupervisorContext {
+        SupervisorContext {
+            http: Arc::new(Http::new("token")),
+            config: ChatbotConfig::default(),
+            backend: crate::backend::from_config(&BackendConfig::default()),
+        }
+    }
+
+    fn config(base: u64, max: u64) -> ChatbotConfig {
+        ChatbotConfig {
+            restart_backoff_secs: base,
+            max_backoff_secs: max,
+            ..ChatbotConfig::default()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_per_attempt() {
+        let config = config(2, 60);
+        let secs: Vec<u64> = (1..=6).map(|attempt| restart_backoff(&config, attempt).as_secs()).collect();
+        assert_eq!(secs, vec![2, 4, 8, 16, 32, 60]);
+    }
+
+    #[test]
+    fn backoff_edges() {
+        // Attempt 0 behaves like the first attempt, and a zero base still waits a second
+        assert_eq!(restart_backoff(&config(5, 60), 0), Duration::from_secs(5));
+        assert_eq!(restart_backoff(&config(0, 60), 1), Duration::from_secs(1));
+        // Huge attempt counts or bases neither overflow nor exceed the cap
+        assert_eq!(restart_backoff(&config(2, 60), u32::MAX), Duration::from_secs(60));
+        assert_eq!(restart_backoff(&config(u64::MAX, 600), 40), Duration::from_secs(600));
+    }
+
+    #[tokio::test]
+    async fn state_follows_the_lifecycle() {
+        let _serial = STATE_LOCK.lock().await;
+        let ctx = context();
+        set_state(&ctx, ChatbotState::Stopped, None).await;
+        let mut rx = STATE.subscribe();
+
+        let lifecycle = [
+            ChatbotState::Starting,
+            ChatbotState::Warming,
+            ChatbotState::Ready,
+            ChatbotState::Crashed,
+            ChatbotState::Stopped,
+        ];
+        for state in lifecycle {
+            set_state(&ctx, state, None).await;
+            assert!(rx.has_changed().unwrap());
+            assert_eq!(*rx.borrow_and_update(), state);
+            assert_eq!(current_state(), state);
+        }
+    }
+
+    #[tokio::test]
+    async fn waiting_ends_when_ready() {
+        let _serial = STATE_LOCK.lock().await;
+        let ctx = context();
+        set_state(&ctx, ChatbotState::Starting, None).await;
+
+        let waiter = tokio::spawn(wait_until_ready(Duration::from_secs(10)));
+        set_state(&ctx, ChatbotState::Warming, None).await;
+        set_state(&ctx, ChatbotState::Ready, None).await;
+        assert_eq!(waiter.await.unwrap(), Ok(()));
+
+        // Already ready returns straight away
+        assert_eq!(wait_until_ready(Duration::from_millis(1)).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn waiting_stops_early_on_crash_or_stop() {
+        let _serial = STATE_LOCK.lock().await;
+        let ctx = context();
+        for end in [ChatbotState::Crashed, ChatbotState::Stopped] {
+            set_state(&ctx, ChatbotState::Warming, None).await;
+            let waiter = tokio::spawn(wait_until_ready(Duration::from_secs(10)));
+            set_state(&ctx, end, None).await;
+            let started = Instant::now();
+            assert_eq!(waiter.await.unwrap(), Err(end));
+            assert!(started.elapsed() < Duration::from_secs(1));
+        }
+    }
+
+    #[tokio::test]
+    async fn waiting_times_out_with_the_current_state() {
+        let _serial = STATE_LOCK.lock().await;
+        let ctx = context();
+        set_state(&ctx, ChatbotState::Warming, None).await;
+        assert_eq!(wait_until_ready(Duration::from_millis(50)).await, Err(ChatbotState::Warming));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::db::thread::ChatThread;
+use crate::db::user::Conversation;
+use crate::formatting::shorten;
+
+/// Discord's limit on thread names
+const NAME_LIMIT: usize = 100;
+
+/// Sent in place of a user message when a thread is archived
+pub const SUMMARY_PROMPT: &str = "Summarize the conversation above in two or three sentences, \
+     written as notes for yourself so you can pick it up again later. Reply with the summary only.";
+
+/// Most thread turns read back when summarising
+pub const SUMMARY_MAX_TURNS: usize = 50;
+
+/// Most of the user's earlier thread summaries given to a thread as context
+pub const SUMMARY_CONTEXT: usize = 3;
+
+/// Title for a new thread: the user's nickname and the start of the message that opened it
+pub fn thread_name(nickname: &str, message: &str) -> String {
+    let words: Vec<&str> = message.split_whitespace().collect();
+    let name = if words.is_empty() {
+        format!("Chat with {}", nickname)
+    } else {
+        format!("{}: {}", nickname, words.join(" "))
+    };
+    shorten(&name, NAME_LIMIT)
+}
+
+/// Stored when the backend can't summarise the thread, so the history still records it
+pub fn fallback_summary(conversations: &[Conversation]) -> String {
+    match conversations.first() {
+        Some(first) => format!(
+            "{} exchange(s), starting with: \"{}\"",
+            conversations.len(),
+            shorten(first.prompt.trim(), 200)
+        ),
+        None => "An empty thread.".to_string(),
+    }
+}
+
+/// The history entry recording an archived thread. It has no session, so it is part
+/// of the user's regular context from then on.
+pub fn summary_conversation(thread: &ChatThread, name: &str, summary: String, now: i64) -> Conversation {
+    Conversation {
+        prompt: format!("[Thread summary] {}", name),
+        response: summary,
+        timestamp: now,
+        channel_id: Some(thread.parent_id.clone()),
+        session_id: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(prompt: &str) -> Conversation {
+        Conversation {
+            prompt: prompt.to_string(),
+            response: "ok".to_string(),
+            timestamp: 0,
+            channel_id: None,
+            session_id: Some("200".to_string()),
+        }
+    }
+
+    #[test]
+    fn names_fit_discord_limit() {
+        assert_eq!(thread_name("Ada", "  what is\n a monad? "), "Ada: what is a monad?");
+        assert_eq!(thread_name("Ada", ""), "Chat with Ada");
+
+        let long = thread_name("Ada", &"word ".repeat(50));
+        assert_eq!(long.chars().count(), NAME_LIMIT);
+        assert!(long.ends_with('…'));
+    }
+
+    #[test]
+    fn fallback_mentions_first_prompt() {
+        let turns = [conversation("Tell me about Rust"), conversation("And lifetimes?")];
+        assert_eq!(fallback_summary(&turns), "2 exchange(s), starting with: \"Tell me about Rust\"");
+        assert_eq!(fallback_summary(&[]), "An empty thread.");
+    }
+}
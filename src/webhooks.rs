@@ -0,0 +1,299 @@
+use serenity::http::Http;
+use serenity::model::id::{ChannelId, WebhookId};
+use serenity::model::webhook::Webhook;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::db::channel_persona::ChannelPersona;
+use crate::db::persona::Persona;
+use crate::db::Store;
+
+/// Name of the webhook the bot creates in persona channels. Each message overrides
+/// the displayed name and avatar, so one webhook serves any persona.
+pub const MANAGED_WEBHOOK_NAME: &str = "Valory persona";
+
+/// Creates, caches and cleans up the webhooks used by channels bound with /channel-persona
+pub struct WebhookManager {
+    store: Arc<dyn Store>,
+    cache: Mutex<HashMap<u64, Webhook>>,
+}
+
+impl WebhookManager {
+    pub fn new(store: Arc<dyn Store>) -> Self {
+        Self {
+            store,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The channel's managed webhook, creating one if it is missing or was deleted
+    pub async fn webhook_for(&self, http: &Http, binding: &ChannelPersona) -> Result<Webhook, String> {
+        let channel_id = parse_id(&binding.channel_id)?;
+        if let Some(webhook) = self.cache.lock().await.get(&channel_id) {
+            return Ok(webhook.clone());
+        }
+
+        // The stored webhook, unless someone deleted it since
+        if let (Some(id), Some(token)) = (&binding.webhook_id, &binding.webhook_token) {
+            match Webhook::from_id_with_token(http, WebhookId(parse_id(id)?), token).await {
+                Ok(webhook) => {
+                    self.cache.lock().await.insert(channel_id, webhook.clone());
+                    return Ok(webhook);
+                }
+                Err(e) => println!("[WARN] Stored webhook for channel {} is gone: {:?}", channel_id, e),
+            }
+        }
+
+        let webhook = find_or_create(http, ChannelId(channel_id)).await?;
+        let mut updated = binding.clone();
+        updated.webhook_id = Some(webhook.id.0.to_string());
+        updated.webhook_token = webhook.token.clone();
+        self.store.save_channel_persona(updated).await?;
+        self.cache.lock().await.insert(channel_id, webhook.clone());
+        Ok(webhook)
+    }
+
+    /// Bind a copy of `persona` to the channel, keeping the channel's existing webhook if it has one
+    pub async fn bind(&self, guild_id: u64, channel_id: u64, persona: &Persona) -> Result<ChannelPersona, String> {
+        let mut binding = ChannelPersona::new(guild_id, channel_id, persona);
+        if let Some(existing) = self.store.get_channel_persona(channel_id).await? {
+            binding.webhook_id = existing.webhook_id;
+            binding.webhook_token = existing.webhook_token;
+        }
+        self.store.save_channel_persona(binding.clone()).await?;
+        Ok(binding)
+    }
+
+    /// Remove the channel's binding and delete its webhook. Returns the persona that was bound.
+    pub async fn unbind(&self, http: &Http, channel_id: u64) -> Result<Option<String>, String> {
+        let binding = match self.store.remove_channel_persona(channel_id).await? {
+            Some(binding) => binding,
+            None => return Ok(None),
+        };
+        self.forget(channel_id).await;
+
+        if let (Some(id), Some(token)) = (&binding.webhook_id, &binding.webhook_token) {
+            let deleted = match Webhook::from_id_with_token(http, WebhookId(parse_id(id)?), token).await {
+                Ok(webhook) => webhook.delete(http).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = deleted {
+                // Already gone, or the bot lost Manage Webhooks; the binding is removed either way
+                println!("[WARN] Could not delete webhook for channel {}: {:?}", channel_id, e);
+            }
+        }
+        Ok(Some(binding.persona))
+    }
+
+    /// Drop the cached webhook so the next reply looks it up again
+    pub async fn forget(&self, channel_id: u64) {
+        self.cache.lock().await.remove(&channel_id);
+    }
+}
+
+/// Reuse a webhook the bot made earlier in the channel, or create a new one
+async fn find_or_create(http: &Http, channel: ChannelId) -> Result<Webhook, String> {
+    let existing = channel
+        .webhooks(http)
+        .await
+        .map_err(|e| format!("Failed to list webhooks in channel {}: {:?}", channel, e))?
+        .into_iter()
+        .find(|w| w.name.as_deref() == Some(MANAGED_WEBHOOK_NAME) && w.token.is_some());
+    if let Some(webhook) = existing {
+        return Ok(webhook);
+    }
+
+    let webhook = channel
+        .create_webhook(http, MANAGED_WEBHOOK_NAME)
+        .await
+        .map_err(|e| format!("Failed to create webhook in channel {}: {:?}", channel, e))?;
+    println!("[LOG] Created persona webhook {} in channel {}", webhook.id, channel);
+    Ok(webhook)
+}
+
+fn parse_id(id: &str) -> Result<u64, String> {
+    id.parse::<u64>().map_err(|_| format!("Invalid id '{}'", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::http::HttpBuilder;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use crate::db::memory::MemoryStore;
+
+    fn http_for(server: &MockServer) -> Http {
+        HttpBuilder::new("token")
+            .proxy(server.uri())
+            .unwrap()
+            .ratelimiter_disabled(true)
+            .build()
+    }
+
+    fn pirate() -> Persona {
+        Persona {
+            name: "pirate".to_string(),
+            owner_id: "1".to_string(),
+            system_prompt: "Talk like a pirate.".to_string(),
+            temperature: None,
+            greeting: None,
+            avatar_url: None,
+            created_at: 0,
+        }
+    }
+
+    fn webhook_json(id: u64, name: &str, token: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id.to_string(),
+            "type": 1,
+            "channel_id": "100",
+            "guild_id": "10",
+            "name": name,
+            "token": token,
+            "avatar": null,
+        })
+    }
+
+    fn unknown_webhook() -> ResponseTemplate {
+        ResponseTemplate::new(404).set_body_json(serde_json::json!({"message": "Unknown Webhook", "code": 10015}))
+    }
+
+    async fn bound(store: &Arc<dyn Store>, webhook: Option<(&str, &str)>) -> ChannelPersona {
+        let mut binding = ChannelPersona::new(10, 100, &pirate());
+        if let Some((id, token)) = webhook {
+            binding.webhook_id = Some(id.to_string());
+            binding.webhook_token = Some(token.to_string());
+        }
+        store.save_channel_persona(binding.clone()).await.unwrap();
+        binding
+    }
+
+    #[tokio::test]
+    async fn reuses_stored_webhook_and_caches_it() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v10/webhooks/55/secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(webhook_json(55, MANAGED_WEBHOOK_NAME, "secret")))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(500)).expect(0).mount(&server).await;
+
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+        let binding = bound(&store, Some(("55", "secret"))).await;
+        let manager = WebhookManager::new(store);
+        let http = http_for(&server);
+        assert_eq!(manager.webhook_for(&http, &binding).await.unwrap().id, WebhookId(55));
+        assert_eq!(manager.webhook_for(&http, &binding).await.unwrap().id, WebhookId(55));
+    }
+
+    #[tokio::test]
+    async fn recreates_deleted_webhook_and_persists_it() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v10/webhooks/55/secret"))
+            .respond_with(unknown_webhook())
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v10/channels/100/webhooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v10/channels/100/webhooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(webhook_json(66, MANAGED_WEBHOOK_NAME, "fresh")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+        let binding = bound(&store, Some(("55", "secret"))).await;
+        let manager = WebhookManager::new(store.clone());
+        let webhook = manager.webhook_for(&http_for(&server), &binding).await.unwrap();
+        assert_eq!(webhook.id, WebhookId(66));
+
+        let saved = store.get_channel_persona(100).await.unwrap().unwrap();
+        assert_eq!(saved.webhook_id.as_deref(), Some("66"));
+        assert_eq!(saved.webhook_token.as_deref(), Some("fresh"));
+    }
+
+    #[tokio::test]
+    async fn adopts_managed_webhook_already_in_channel() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v10/channels/100/webhooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                webhook_json(70, "Someone else's", "other"),
+                webhook_json(77, MANAGED_WEBHOOK_NAME, "ours"),
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(500)).expect(0).mount(&server).await;
+
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+        let binding = bound(&store, None).await;
+        let manager = WebhookManager::new(store.clone());
+        assert_eq!(manager.webhook_for(&http_for(&server), &binding).await.unwrap().id, WebhookId(77));
+        let saved = store.get_channel_persona(100).await.unwrap().unwrap();
+        assert_eq!(saved.webhook_id.as_deref(), Some("77"));
+    }
+
+    #[tokio::test]
+    async fn rebinding_keeps_the_webhook() {
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+        bound(&store, Some(("55", "secret"))).await;
+        let manager = WebhookManager::new(store.clone());
+
+        let chef = Persona {
+            name: "chef".to_string(),
+            ..pirate()
+        };
+        let binding = manager.bind(10, 100, &chef).await.unwrap();
+        assert_eq!(binding.persona, "chef");
+        assert_eq!(binding.webhook_id.as_deref(), Some("55"));
+        assert_eq!(store.get_channel_persona(100).await.unwrap(), Some(binding));
+    }
+
+    #[tokio::test]
+    async fn unbind_deletes_the_webhook() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v10/webhooks/55/secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(webhook_json(55, MANAGED_WEBHOOK_NAME, "secret")))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v10/webhooks/55/secret"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+        bound(&store, Some(("55", "secret"))).await;
+        let manager = WebhookManager::new(store.clone());
+        let http = http_for(&server);
+        assert_eq!(manager.unbind(&http, 100).await.unwrap(), Some("pirate".to_string()));
+        assert_eq!(store.get_channel_persona(100).await.unwrap(), None);
+        assert_eq!(manager.unbind(&http, 100).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn unbind_succeeds_when_webhook_is_gone() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v10/webhooks/55/secret"))
+            .respond_with(unknown_webhook())
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE")).respond_with(ResponseTemplate::new(500)).expect(0).mount(&server).await;
+
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+        bound(&store, Some(("55", "secret"))).await;
+        let manager = WebhookManager::new(store.clone());
+        assert_eq!(manager.unbind(&http_for(&server), 100).await.unwrap(), Some("pirate".to_string()));
+        assert_eq!(store.get_channel_persona(100).await.unwrap(), None);
+    }
+}